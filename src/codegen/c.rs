@@ -0,0 +1,407 @@
+use std::io::{Write, Error, ErrorKind};
+use std::result::Result;
+use core::*;
+
+static HEADING: &'static str =
+"#include <math.h>
+#include <stddef.h>
+#include <string.h>
+
+#ifndef META_DIFF_C_HELPERS
+#define META_DIFF_C_HELPERS
+/* A column major matrix, scalars are represented as 1x1 matrices */
+typedef struct {
+	size_t rows;
+	size_t cols;
+	double* data;
+} md_matrix;
+
+#define MD_AT(x, i, j) x[((x##_rows) == 1 ? 0 : (i)) + ((x##_cols) == 1 ? 0 : (j)) * (x##_rows)]
+#define MD_MIN(a, b) ((a) < (b) ? (a) : (b))
+
+static inline double md_sign(double x) {
+	return x > 0.0 ? 1.0 : (x < 0.0 ? -1.0 : 0.0);
+}
+
+static inline void md_dot(size_t a_rows, size_t a_cols, const double* a, size_t b_rows, size_t b_cols, const double* b, double* out) {
+	size_t i, j, k;
+	if (a_rows * a_cols == 1 || b_rows * b_cols == 1) {
+		const double scale = a_rows * a_cols == 1 ? a[0] : b[0];
+		const double* x = a_rows * a_cols == 1 ? b : a;
+		const size_t n = a_rows * a_cols == 1 ? b_rows * b_cols : a_rows * a_cols;
+		for (i = 0; i < n; ++i) out[i] = scale * x[i];
+		return;
+	}
+	for (i = 0; i < a_rows * b_cols; ++i) out[i] = 0.0;
+	for (j = 0; j < b_cols; ++j) {
+		for (k = 0; k < a_cols; ++k) {
+			const double value = b[k + j * b_rows];
+			for (i = 0; i < a_rows; ++i) out[i + j * a_rows] += a[i + k * a_rows] * value;
+		}
+	}
+}
+
+static inline void md_quad(size_t rows, size_t cols, const double* a, const double* b, double* out) {
+	size_t i, j, k, l;
+	for (l = 0; l < cols; ++l) {
+		for (k = 0; k < cols; ++k) {
+			double value = 0.0;
+			for (j = 0; j < rows; ++j) {
+				for (i = 0; i < rows; ++i) value += a[i + k * rows] * b[i + j * rows] * a[j + l * rows];
+			}
+			out[k + l * cols] = value;
+		}
+	}
+}
+
+/* Gauss-Jordan elimination with partial pivoting, the scratch buffer must hold n * n elements */
+static inline void md_inverse(size_t n, const double* in, double* out, double* scratch) {
+	size_t i, j, k;
+	memcpy(scratch, in, sizeof(double) * n * n);
+	for (i = 0; i < n * n; ++i) out[i] = 0.0;
+	for (i = 0; i < n; ++i) out[i + i * n] = 1.0;
+	for (k = 0; k < n; ++k) {
+		size_t pivot = k;
+		double value;
+		for (i = k + 1; i < n; ++i) {
+			if (fabs(scratch[i + k * n]) > fabs(scratch[pivot + k * n])) pivot = i;
+		}
+		for (j = 0; j < n; ++j) {
+			double t = scratch[k + j * n]; scratch[k + j * n] = scratch[pivot + j * n]; scratch[pivot + j * n] = t;
+			t = out[k + j * n]; out[k + j * n] = out[pivot + j * n]; out[pivot + j * n] = t;
+		}
+		value = scratch[k + k * n];
+		for (j = 0; j < n; ++j) {
+			scratch[k + j * n] /= value;
+			out[k + j * n] /= value;
+		}
+		for (i = 0; i < n; ++i) {
+			if (i == k) continue;
+			value = scratch[i + k * n];
+			for (j = 0; j < n; ++j) {
+				scratch[i + j * n] -= value * scratch[k + j * n];
+				out[i + j * n] -= value * out[k + j * n];
+			}
+		}
+	}
+}
+
+static inline void md_transpose(size_t rows, size_t cols, const double* in, double* out) {
+	size_t i, j;
+	for (j = 0; j < cols; ++j) {
+		for (i = 0; i < rows; ++i) out[j + i * cols] = in[i + j * rows];
+	}
+}
+
+static inline void md_matrix_diag(size_t rows, size_t cols, const double* in, double* out) {
+	size_t i;
+	for (i = 0; i < MD_MIN(rows, cols); ++i) out[i] = in[i + i * rows];
+}
+
+static inline void md_vector_diag(size_t n, const double* in, double* out) {
+	size_t i;
+	for (i = 0; i < n * n; ++i) out[i] = 0.0;
+	for (i = 0; i < n; ++i) out[i + i * n] = in[i];
+}
+
+/* dim is 0 for all, 1 for first and 2 for second dimension, norm is 0 for sum, 1 for L1 and 2 for L2 */
+static inline void md_reduce(size_t rows, size_t cols, int dim, int norm, const double* in, double* out) {
+	size_t i, j;
+	const size_t n = dim == 0 ? 1 : (dim == 1 ? cols : rows);
+	for (i = 0; i < n; ++i) out[i] = 0.0;
+	for (j = 0; j < cols; ++j) {
+		for (i = 0; i < rows; ++i) {
+			const double x = in[i + j * rows];
+			out[dim == 0 ? 0 : (dim == 1 ? j : i)] += norm == 0 ? x : (norm == 1 ? fabs(x) : x * x);
+		}
+	}
+}
+
+static inline void md_get_block(size_t rows, const double* in, size_t start_x, size_t start_y, size_t out_rows, size_t out_cols, double* out) {
+	size_t i, j;
+	for (j = 0; j < out_cols; ++j) {
+		for (i = 0; i < out_rows; ++i) out[i + j * out_rows] = in[start_x + i + (start_y + j) * rows];
+	}
+}
+
+static inline void md_set_block(size_t rows, size_t cols, const double* in, size_t start_x, size_t start_y, size_t out_rows, double* out) {
+	size_t i, j;
+	for (j = 0; j < cols; ++j) {
+		for (i = 0; i < rows; ++i) out[start_x + i + (start_y + j) * out_rows] = in[i + j * rows];
+	}
+}
+
+static inline void md_replicate(size_t rows, size_t cols, const double* in, size_t out_rows, size_t out_cols, double* out) {
+	size_t i, j;
+	for (j = 0; j < out_cols; ++j) {
+		for (i = 0; i < out_rows; ++i) out[i + j * out_rows] = in[i % rows + (j % cols) * rows];
+	}
+}
+#endif
+
+";
+
+/// A buffer in the workspace, together with the C expressions for its dimensions
+struct Buffer {
+	name: String,
+	rows: String,
+	cols: String
+}
+
+/// Writes a C99 source file, which computes all of the outputs of the graph and the gradients of all parameters.
+///
+/// The generated code has no dependencies apart from the C standard library and does not allocate any memory.
+/// Two functions are generated - `<name>_workspace_size`, which returns the number of bytes the workspace needs
+/// for the given input dimensions, and `<name>`, which evaluates the graph using the workspace provided.
+/// The results are written as `md_matrix` structures pointing inside the workspace.
+/// The size of each buffer is the inferred `dims` of its node written in terms of the dimensions of the inputs,
+/// thus a singleton dimension is broadcast only if it is known when the graph is built.
+/// An `InvalidInput` error is returned if the size of any node depends on the values of the inputs.
+pub fn write_c(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
+	let inputs = super::input_nodes(graph);
+	let outputs = super::output_names(graph);
+	let (ordering, grads, params) = try!(super::codegen_ordering(graph));
+	let mut buffers : Vec<(usize, Vec<Buffer>)> = Vec::new();
+	for i in ordering.iter() {
+		if !inputs.contains(i) {
+			buffers.push((*i, try!(node_buffers(graph, *i))));
+		}
+	}
+	let arguments = inputs.iter().map(|i| format!("const md_matrix* {}", graph.nodes[*i].as_ref().unwrap().name))
+		.collect::<Vec<String>>().join(", ");
+	try!(write!(fmt, "{}", HEADING));
+	// Workspace size
+	try!(write!(fmt, "size_t {}_workspace_size({}) {{\n", graph.name, arguments));
+	try!(write_dims(fmt, graph, &inputs, &buffers));
+	try!(write!(fmt, "\tsize_t size = 0;\n"));
+	for &(_, ref list) in buffers.iter() {
+		for buffer in list.iter() {
+			try!(write!(fmt, "\tsize += {0}_rows * {0}_cols;\n", buffer.name));
+		}
+	}
+	try!(write!(fmt, "\treturn sizeof(double) * size;\n}}\n\n"));
+	// Function signature
+	let results = outputs.iter().cloned().chain(params.iter().map(|p| format!("grad_{}", p)))
+		.collect::<Vec<String>>();
+	try!(write!(fmt, "void {}({}, double* workspace, {}) {{\n", graph.name, arguments,
+		results.iter().map(|r| format!("md_matrix* {}", r)).collect::<Vec<String>>().join(", ")));
+	try!(write_dims(fmt, graph, &inputs, &buffers));
+	for i in inputs.iter() {
+		try!(write!(fmt, "\tconst double* const n{} = {}->data;\n", i, graph.nodes[*i].as_ref().unwrap().name));
+	}
+	for &(_, ref list) in buffers.iter() {
+		for buffer in list.iter() {
+			try!(write!(fmt, "\tdouble* const {0} = workspace;\n\tworkspace += {0}_rows * {0}_cols;\n", buffer.name));
+		}
+	}
+	// Function body
+	for &(i, _) in buffers.iter() {
+		try!(write_operation(fmt, graph, i));
+	}
+	// Return the outputs and the gradients
+	for (name, id) in results.iter().zip(graph.outputs.iter().chain(grads.iter())) {
+		let cast = if inputs.contains(id) {"(double*) "} else {""};
+		try!(write!(fmt, "\t{0}->rows = n{1}_rows;\n\t{0}->cols = n{1}_cols;\n\t{0}->data = {2}n{1};\n", name, id, cast));
+	}
+	write!(fmt, "}}\n")
+}
+
+/// Writes the declarations of the dimensions of all inputs and buffers
+fn write_dims(fmt: &mut Write, graph: & ComputeGraph, inputs: &Vec<usize>, buffers: &Vec<(usize, Vec<Buffer>)>) -> Result<(),Error>{
+	for i in inputs.iter() {
+		let name = &graph.nodes[*i].as_ref().unwrap().name;
+		try!(write!(fmt, "\tconst size_t n{0}_rows = {1}->rows;\n\tconst size_t n{0}_cols = {1}->cols;\n", i, name));
+	}
+	for &(_, ref list) in buffers.iter() {
+		for buffer in list.iter() {
+			try!(write!(fmt, "\tconst size_t {0}_rows = {1};\n\tconst size_t {0}_cols = {2};\n",
+				buffer.name, buffer.rows, buffer.cols));
+		}
+	}
+	Ok(())
+}
+
+/// Returns the buffers needed for computing the node, where the last one holds the result
+fn node_buffers(graph: & ComputeGraph, id: usize) -> Result<Vec<Buffer>, Error>{
+	let node = try!(graph.get_node(id).map_err(super::graph_error));
+	let var = format!("n{}", id);
+	let buffer = |name: String, dims: &Pair<SymPolynomial>| -> Result<Buffer, Error> {
+		Ok(Buffer{name: name, rows: try!(c_polynomial(graph, &dims.0)), cols: try!(c_polynomial(graph, &dims.1))})
+	};
+	match node.node_type {
+		Type::Float(_) | Type::Integer(_) => return Ok(vec![try!(buffer(var, &node.dims))]),
+		_ => ()
+	}
+	match node.op.op_type {
+		OPERATOR_MINV => Ok(vec![try!(buffer(format!("{}_scratch", var), &node.dims)), try!(buffer(var, &node.dims))]),
+		OPERATOR_DOT => {
+			// Each intermediate product gets its own buffer
+			let mut list : Vec<Buffer> = Vec::new();
+			let mut dims = try!(graph.get_node(node.op.parents[0]).map_err(super::graph_error)).dims.clone();
+			for (i, p) in node.op.parents.iter().enumerate().skip(1).take(node.op.parents.len().saturating_sub(2)) {
+				let other = try!(graph.get_node(*p).map_err(super::graph_error)).dims.clone();
+				dims = dot_dims(dims, other);
+				list.push(try!(buffer(format!("{}_{}", var, i), &dims)));
+			}
+			list.push(try!(buffer(var, &node.dims)));
+			Ok(list)
+		},
+		_ => Ok(vec![try!(buffer(var, &node.dims))])
+	}
+}
+
+/// Returns a C expression for the value of an integer node, which must be computable from the input dimensions only
+fn index_value(graph: & ComputeGraph, id: usize) -> Result<String, Error>{
+	c_polynomial(graph, &try!(graph.symbolic_value(id).map_err(super::graph_error)))
+}
+
+/// Returns a C expression for the polynomial, where the symbols of the inputs' dimensions are replaced by their variables.
+/// An error if the polynomial depends on the value of any node
+fn c_polynomial(graph: & ComputeGraph, value: &SymPolynomial) -> Result<String, Error>{
+	if value.terms.len() == 0 {
+		return Ok("0".to_string())
+	}
+	let mut result = String::new();
+	// The constant term is always first in the ordering, but it is written last
+	let terms = value.terms.iter().skip_while(|x| x.0.powers.len() == 0)
+		.chain(value.terms.iter().take_while(|x| x.0.powers.len() == 0));
+	for (i, &(ref monomial, coefficient)) in terms.enumerate() {
+		let mut factors = if coefficient.abs() != 1 || monomial.powers.len() == 0 {vec![format!("{}", coefficient.abs())]} else {Vec::new()};
+		for &(ref symbol, power) in monomial.powers.iter() {
+			let variable = try!(c_symbol(graph, symbol));
+			for _ in 0..power {
+				factors.push(variable.clone());
+			}
+		}
+		result = result + match (i, coefficient < 0) {
+			(0, true) => "-",
+			(0, false) => "",
+			(_, true) => " - ",
+			(_, false) => " + "
+		} + &factors.join(" * ");
+	}
+	Ok(if value.terms.len() > 1 {format!("({})", result)} else {result})
+}
+
+/// Returns the C variable of a symbol of the inputs' dimensions
+fn c_symbol(graph: & ComputeGraph, symbol: &str) -> Result<String, Error>{
+	for i in super::input_nodes(graph) {
+		let name = &graph.nodes[i].as_ref().unwrap().name;
+		if *symbol == format!("{}_rows", name) {
+			return Ok(format!("n{}_rows", i))
+		} else if *symbol == format!("{}_cols", name) {
+			return Ok(format!("n{}_cols", i))
+		}
+	}
+	// Any other symbol is the value of a node
+	match (symbol.starts_with("n"), symbol[1..].parse::<usize>()) {
+		(true, Ok(id)) => Err(not_static(id)),
+		_ => Err(Error::new(ErrorKind::InvalidInput, format!("Unknown dimension {}", symbol)))
+	}
+}
+
+/// The error returned when a dimension depends on the values of the inputs
+fn not_static(id: usize) -> Error {
+	Error::new(ErrorKind::InvalidInput, format!("The value of node {} can not be computed from the dimensions of the inputs", id))
+}
+
+/// Writes the loop nest computing an elementwise operator
+fn write_elementwise(fmt: &mut Write, var: &str, expr: &str) -> Result<(),Error>{
+	write!(fmt, "\tfor (size_t j = 0; j < {0}_cols; ++j) {{\n\t\tfor (size_t i = 0; i < {0}_rows; ++i) {{\n\t\t\t{0}[i + j * {0}_rows] = {1};\n\t\t}}\n\t}}\n", var, expr)
+}
+
+/// Writes the statements computing the value of the node
+fn write_operation(fmt: &mut Write, graph: & ComputeGraph, id: usize) -> Result<(),Error>{
+	let node = try!(graph.get_node(id).map_err(super::graph_error));
+	let var = format!("n{}", id);
+	match node.node_type {
+		Type::Float(x) => return write!(fmt, "\t{}[0] = {:?};\n", var, x),
+		Type::Integer(x) => return write!(fmt, "\t{}[0] = {:?};\n", var, x as f64),
+		_ => ()
+	}
+	let p = node.op.parents.iter().map(|p| format!("n{}", p)).collect::<Vec<String>>();
+	let x = p.iter().map(|p| format!("MD_AT({}, i, j)", p)).collect::<Vec<String>>();
+	let arg = |i: usize| index_value(graph, node.op.args[i]);
+	match node.op.op_type {
+		OperatorType::Constant(ConstantOperatorType::None) => unreachable!(),
+		OPERATOR_CONST => write_elementwise(fmt, &var, &x[0]),
+		OperatorType::Custom(id) => write_elementwise(fmt, &var, &try!(super::custom_code(id, "c", &x))),
+		OPERATOR_EYE => write_elementwise(fmt, &var, "i == j ? 1.0 : 0.0"),
+		OPERATOR_SIGN => write_elementwise(fmt, &var, &format!("md_sign({})", x[0])),
+		OPERATOR_SIZE_1 => write!(fmt, "\t{}[0] = (double) {}_rows;\n", var, p[0]),
+		OPERATOR_SIZE_2 => write!(fmt, "\t{}[0] = (double) {}_cols;\n", var, p[0]),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) =>
+			write!(fmt, "\t{0}[0] = (double) ({1}_rows * {1}_cols);\n", var, p[0]),
+		OPERATOR_ZEROS => write_elementwise(fmt, &var, "0.0"),
+		OPERATOR_ONES => write_elementwise(fmt, &var, "1.0"),
+		OPERATOR_LT => write_elementwise(fmt, &var, &format!("{} < {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_LTE => write_elementwise(fmt, &var, &format!("{} <= {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_GT => write_elementwise(fmt, &var, &format!("{} > {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_GTE => write_elementwise(fmt, &var, &format!("{} >= {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_EQ => write_elementwise(fmt, &var, &format!("{} == {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_NEQ => write_elementwise(fmt, &var, &format!("{} != {} ? 1.0 : 0.0", x[0], x[1])),
+		OPERATOR_NEG => write_elementwise(fmt, &var, &format!("-{}", x[0])),
+		OPERATOR_DIV => write_elementwise(fmt, &var, &format!("1.0 / {}", x[0])),
+		OPERATOR_MINV => write!(fmt, "\tmd_inverse({1}_rows, {1}, {0}, {0}_scratch);\n", var, p[0]),
+		OPERATOR_TRANSPOSE => write!(fmt, "\tmd_transpose({1}_rows, {1}_cols, {1}, {0});\n", var, p[0]),
+		OPERATOR_MDIAG => write!(fmt, "\tmd_matrix_diag({1}_rows, {1}_cols, {1}, {0});\n", var, p[0]),
+		OPERATOR_VDIAG => write!(fmt, "\tmd_vector_diag({1}_rows * {1}_cols, {1}, {0});\n", var, p[0]),
+		OPERATOR_COS => write_elementwise(fmt, &var, &format!("cos({})", x[0])),
+		OPERATOR_SIN => write_elementwise(fmt, &var, &format!("sin({})", x[0])),
+		OPERATOR_TAN => write_elementwise(fmt, &var, &format!("tan({})", x[0])),
+		OPERATOR_COSH => write_elementwise(fmt, &var, &format!("cosh({})", x[0])),
+		OPERATOR_SINH => write_elementwise(fmt, &var, &format!("sinh({})", x[0])),
+		OPERATOR_TANH => write_elementwise(fmt, &var, &format!("tanh({})", x[0])),
+		OPERATOR_ABS => write_elementwise(fmt, &var, &format!("fabs({})", x[0])),
+		OPERATOR_LOG => write_elementwise(fmt, &var, &format!("log({})", x[0])),
+		OPERATOR_EXP => write_elementwise(fmt, &var, &format!("exp({})", x[0])),
+		OPERATOR_SQRT => write_elementwise(fmt, &var, &format!("sqrt({})", x[0])),
+		OPERATOR_SQUARE => write_elementwise(fmt, &var, &format!("{0} * {0}", x[0])),
+		OPERATOR_SIGM => write_elementwise(fmt, &var, &format!("1.0 / (1.0 + exp(-{}))", x[0])),
+		OPERATOR_RECT => write_elementwise(fmt, &var, &format!("fmax({}, 0.0)", x[0])),
+		OPERATOR_SUM_1 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 1, 0, {1}, {0});\n", var, p[0]),
+		OPERATOR_SUM_2 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 2, 0, {1}, {0});\n", var, p[0]),
+		OPERATOR_SUM_ALL => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 0, 0, {1}, {0});\n", var, p[0]),
+		OPERATOR_L2_1 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 1, 2, {1}, {0});\n", var, p[0]),
+		OPERATOR_L2_2 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 2, 2, {1}, {0});\n", var, p[0]),
+		OPERATOR_L2_ALL => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 0, 2, {1}, {0});\n", var, p[0]),
+		OPERATOR_L1_1 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 1, 1, {1}, {0});\n", var, p[0]),
+		OPERATOR_L1_2 => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 2, 1, {1}, {0});\n", var, p[0]),
+		OPERATOR_L1_ALL => write!(fmt, "\tmd_reduce({1}_rows, {1}_cols, 0, 1, {1}, {0});\n", var, p[0]),
+		OPERATOR_MAX => write_elementwise(fmt, &var, &format!("fmax({}, {})", x[0], x[1])),
+		OPERATOR_MIN => write_elementwise(fmt, &var, &format!("fmin({}, {})", x[0], x[1])),
+		OPERATOR_POW => write_elementwise(fmt, &var, &format!("pow({}, {})", x[0], x[1])),
+		OPERATOR_QUAD => write!(fmt, "\tmd_quad({1}_rows, {1}_cols, {1}, {2}, {0});\n", var, p[0], p[1]),
+		OPERATOR_ADD => write_elementwise(fmt, &var, &x.join(" + ")),
+		OPERATOR_MUL => write_elementwise(fmt, &var, &x.join(" * ")),
+		OPERATOR_DOT => {
+			let mut last = p[0].clone();
+			for (i, x) in p.iter().enumerate().skip(1) {
+				let name = if i == p.len() - 1 {var.clone()} else {format!("{}_{}", var, i)};
+				try!(write!(fmt, "\tmd_dot({0}_rows, {0}_cols, {0}, {1}_rows, {1}_cols, {1}, {2});\n", last, x, name));
+				last = name;
+			}
+			Ok(())
+		},
+		OPERATOR_HORZCAT | OPERATOR_VERTCAT => {
+			let horizontal = node.op.op_type == OPERATOR_HORZCAT;
+			let mut offset = "0".to_string();
+			for x in p.iter() {
+				let (start_x, start_y) = if horizontal {("0".to_string(), offset.clone())} else {(offset.clone(), "0".to_string())};
+				try!(write!(fmt, "\tmd_set_block({1}_rows, {1}_cols, {1}, {2}, {3}, {0}_rows, {0});\n", var, x, start_x, start_y));
+				offset = format!("{} + {}_{}", offset, x, if horizontal {"cols"} else {"rows"});
+			}
+			Ok(())
+		},
+		OPERATOR_SUBINDEX => write!(fmt, "\tmd_get_block({1}_rows, {1}, {2}, {3}, {0}_rows, {0}_cols, {0});\n",
+			var, p[0], try!(arg(0)), try!(arg(2))),
+		OPERATOR_SUBASSIGN => {
+			try!(write_elementwise(fmt, &var, "0.0"));
+			write!(fmt, "\tmd_set_block({1}_rows, {1}_cols, {1}, {2}, {3}, {0}_rows, {0});\n",
+				var, p[0], try!(arg(0)), try!(arg(2)))
+		},
+		OPERATOR_RESHAPE => write!(fmt, "\tmemcpy({0}, {1}, sizeof(double) * {0}_rows * {0}_cols);\n", var, p[0]),
+		OPERATOR_REPLICATEH | OPERATOR_REPLICATEV =>
+			write!(fmt, "\tmd_replicate({1}_rows, {1}_cols, {1}, {0}_rows, {0}_cols, {0});\n", var, p[0])
+	}
+}
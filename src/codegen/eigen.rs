@@ -1,7 +1,243 @@
 use std::io::{Write, Error};
 use std::result::Result;
-use core::ComputeGraph;
+use std::collections::HashMap;
+use core::*;
 
+static HEADING: &'static str =
+"#include <Eigen/Dense>
+#include <algorithm>
+#include <cmath>
+#include <functional>
+#include <stdexcept>
+
+#ifndef META_DIFF_EIGEN_HELPERS
+#define META_DIFF_EIGEN_HELPERS
+namespace meta_diff {
+	typedef Eigen::MatrixXd Matrix;
+
+	inline Matrix scalar(double value) {
+		return Matrix::Constant(1, 1, value);
+	}
+
+	inline Eigen::Index index(const Matrix& m) {
+		return static_cast<Eigen::Index>(m(0, 0));
+	}
+
+	inline Matrix broadcast(const Matrix& m, Eigen::Index rows, Eigen::Index cols) {
+		if (m.rows() == rows && m.cols() == cols) return m;
+		if (m.rows() == 1 && m.cols() == 1) return Matrix::Constant(rows, cols, m(0, 0));
+		if (m.rows() == 1 && m.cols() == cols) return m.replicate(rows, 1);
+		if (m.cols() == 1 && m.rows() == rows) return m.replicate(1, cols);
+		throw std::invalid_argument(\"meta_diff: operands can not be broadcast together\");
+	}
+
+	template<typename F>
+	inline Matrix binary(const Matrix& a, const Matrix& b, F f) {
+		Eigen::Index rows = std::max(a.rows(), b.rows());
+		Eigen::Index cols = std::max(a.cols(), b.cols());
+		return broadcast(a, rows, cols).binaryExpr(broadcast(b, rows, cols), f);
+	}
+
+	inline Matrix add(const Matrix& a, const Matrix& b) {
+		return binary(a, b, std::plus<double>());
+	}
+
+	inline Matrix mul(const Matrix& a, const Matrix& b) {
+		return binary(a, b, std::multiplies<double>());
+	}
+
+	inline Matrix dot(const Matrix& a, const Matrix& b) {
+		// A product with a scalar is an elementwise multiplication
+		if (a.size() == 1 || b.size() == 1) return mul(a, b);
+		return a * b;
+	}
+
+	inline Matrix pow(const Matrix& a, const Matrix& b) {
+		return binary(a, b, [](double x, double y) { return std::pow(x, y); });
+	}
+
+	inline Matrix max(const Matrix& a, const Matrix& b) {
+		return binary(a, b, [](double x, double y) { return std::max(x, y); });
+	}
+
+	inline Matrix min(const Matrix& a, const Matrix& b) {
+		return binary(a, b, [](double x, double y) { return std::min(x, y); });
+	}
+
+	template<typename F>
+	inline Matrix compare(const Matrix& a, const Matrix& b, F f) {
+		return binary(a, b, [f](double x, double y) { return f(x, y) ? 1.0 : 0.0; });
+	}
+
+	inline Matrix sign(const Matrix& m) {
+		return m.unaryExpr([](double x) { return static_cast<double>((x > 0) - (x < 0)); });
+	}
+
+	inline Matrix sigmoid(const Matrix& m) {
+		return m.unaryExpr([](double x) { return 1.0 / (1.0 + std::exp(-x)); });
+	}
+
+	inline Matrix vector_diag(const Matrix& m) {
+		Eigen::VectorXd v = Eigen::Map<const Eigen::VectorXd>(m.data(), m.size());
+		return v.asDiagonal();
+	}
+}
+#endif
+
+";
+
+/// Writes a self-contained C++ header with a function, which computes all of the outputs of the graph and the gradients of all parameters.
+///
+/// All values are represented as `Eigen::MatrixXd`, where scalars are 1x1 matrices and are broadcasted in elementwise operations.
+/// The arguments of the function are all parameters and constant inputs in the order they were defined,
+/// followed by references to the outputs and then to the gradients of the parameters, named `grad_<parameter>`.
 pub fn write_eigen(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
-	write!(fmt, "{}", graph.nodes.len())
+	let inputs = super::input_nodes(graph);
+	let outputs = super::output_names(graph);
+	let (ordering, grads, params) = try!(super::codegen_ordering(graph));
+	let mut vars : HashMap<usize, String> = HashMap::new();
+	let guard = format!("META_DIFF_{}_HPP", graph.name.to_uppercase());
+	try!(write!(fmt, "#ifndef {0}\n#define {0}\n{1}", guard, HEADING));
+	// Function signature
+	let mut arguments : Vec<String> = Vec::new();
+	for i in inputs.iter() {
+		let name = graph.nodes[*i].as_ref().unwrap().name.clone();
+		arguments.push(format!("const Eigen::MatrixXd& {}", name));
+		vars.insert(*i, name);
+	}
+	for name in outputs.iter() {
+		arguments.push(format!("Eigen::MatrixXd& {}", name));
+	}
+	for name in params.iter() {
+		arguments.push(format!("Eigen::MatrixXd& grad_{}", name));
+	}
+	try!(write!(fmt, "inline void {}({}) {{\n", graph.name, arguments.join(", ")));
+	// Function body
+	for i in ordering.iter() {
+		if vars.contains_key(i) {
+			continue;
+		}
+		let node = graph.nodes[*i].as_ref().unwrap();
+		let (var, declaration) = match graph.outputs.iter().position(|x| x == i) {
+			Some(index) => (outputs[index].clone(), outputs[index].clone()),
+			None => (format!("n{}", i), format!("Eigen::MatrixXd n{}", i))
+		};
+		match node.node_type {
+			Type::Float(x) => try!(write!(fmt, "\t{} = meta_diff::scalar({:?});\n", declaration, x)),
+			Type::Integer(x) => try!(write!(fmt, "\t{} = meta_diff::scalar({});\n", declaration, x)),
+			_ => {
+				let parents = node.op.parents.iter().map(|p| vars[p].clone()).collect::<Vec<String>>();
+				let args = node.op.args.iter().map(|a| vars[a].clone()).collect::<Vec<String>>();
+				try!(write_operation(fmt, &var, &declaration, node.op.op_type, &parents, &args));
+			}
+		}
+		vars.insert(*i, var);
+	}
+	// Assign the remaining outputs
+	for (id, name) in graph.outputs.iter().zip(outputs.iter()) {
+		if vars[id] != *name {
+			try!(write!(fmt, "\t{} = {};\n", name, vars[id]));
+		}
+	}
+	for (id, name) in grads.iter().zip(params.iter()) {
+		try!(write!(fmt, "\tgrad_{} = {};\n", name, vars[id]));
+	}
+	write!(fmt, "}}\n\n#endif\n")
+}
+
+/// Chains the elements of `p` with the binary helper function `func`
+fn fold(func: &str, p: &Vec<String>) -> String {
+	p.iter().skip(1).fold(p[0].clone(), |acc, x| format!("meta_diff::{}({}, {})", func, acc, x))
+}
+
+/// Writes the statement(s) computing the result of the operator in to `var`
+fn write_operation(fmt: &mut Write, var: &str, declaration: &str, op_type: OperatorType, p: &Vec<String>, a: &Vec<String>)
+	-> Result<(), Error> {
+	let expr = match op_type {
+		OperatorType::Constant(ConstantOperatorType::None) => unreachable!(),
+		OperatorType::Custom(id) => try!(super::custom_code(id, "eigen", p)),
+		OPERATOR_CONST => p[0].clone(),
+		OPERATOR_EYE => format!("Eigen::MatrixXd::Identity(meta_diff::index({0}), meta_diff::index({0}))", p[0]),
+		OPERATOR_SIGN => format!("meta_diff::sign({})", p[0]),
+		OPERATOR_SIZE_1 => format!("meta_diff::scalar({}.rows())", p[0]),
+		OPERATOR_SIZE_2 => format!("meta_diff::scalar({}.cols())", p[0]),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) =>
+			format!("meta_diff::scalar({}.size())", p[0]),
+		OPERATOR_ZEROS => format!("Eigen::MatrixXd::Zero(meta_diff::index({}), meta_diff::index({}))", p[0], p[1]),
+		OPERATOR_ONES => format!("Eigen::MatrixXd::Ones(meta_diff::index({}), meta_diff::index({}))", p[0], p[1]),
+		OPERATOR_LT => format!("meta_diff::compare({}, {}, std::less<double>())", p[0], p[1]),
+		OPERATOR_LTE => format!("meta_diff::compare({}, {}, std::less_equal<double>())", p[0], p[1]),
+		OPERATOR_GT => format!("meta_diff::compare({}, {}, std::greater<double>())", p[0], p[1]),
+		OPERATOR_GTE => format!("meta_diff::compare({}, {}, std::greater_equal<double>())", p[0], p[1]),
+		OPERATOR_EQ => format!("meta_diff::compare({}, {}, std::equal_to<double>())", p[0], p[1]),
+		OPERATOR_NEQ => format!("meta_diff::compare({}, {}, std::not_equal_to<double>())", p[0], p[1]),
+		OPERATOR_NEG => format!("-{}", p[0]),
+		OPERATOR_DIV => format!("{}.cwiseInverse()", p[0]),
+		OPERATOR_MINV => format!("{}.inverse()", p[0]),
+		OPERATOR_TRANSPOSE => format!("{}.transpose()", p[0]),
+		OPERATOR_MDIAG => format!("{}.diagonal()", p[0]),
+		OPERATOR_VDIAG => format!("meta_diff::vector_diag({})", p[0]),
+		OPERATOR_COS => format!("{}.array().cos().matrix()", p[0]),
+		OPERATOR_SIN => format!("{}.array().sin().matrix()", p[0]),
+		OPERATOR_TAN => format!("{}.array().tan().matrix()", p[0]),
+		OPERATOR_COSH => format!("{}.array().cosh().matrix()", p[0]),
+		OPERATOR_SINH => format!("{}.array().sinh().matrix()", p[0]),
+		OPERATOR_TANH => format!("{}.array().tanh().matrix()", p[0]),
+		OPERATOR_ABS => format!("{}.cwiseAbs()", p[0]),
+		OPERATOR_LOG => format!("{}.array().log().matrix()", p[0]),
+		OPERATOR_EXP => format!("{}.array().exp().matrix()", p[0]),
+		OPERATOR_SQRT => format!("{}.cwiseSqrt()", p[0]),
+		OPERATOR_SQUARE => format!("{}.cwiseAbs2()", p[0]),
+		OPERATOR_SIGM => format!("meta_diff::sigmoid({})", p[0]),
+		OPERATOR_RECT => format!("{}.cwiseMax(0.0)", p[0]),
+		OPERATOR_SUM_1 => format!("{}.colwise().sum()", p[0]),
+		OPERATOR_SUM_2 => format!("{}.rowwise().sum()", p[0]),
+		OPERATOR_SUM_ALL => format!("meta_diff::scalar({}.sum())", p[0]),
+		OPERATOR_L2_1 => format!("{}.cwiseAbs2().colwise().sum()", p[0]),
+		OPERATOR_L2_2 => format!("{}.cwiseAbs2().rowwise().sum()", p[0]),
+		OPERATOR_L2_ALL => format!("meta_diff::scalar({}.squaredNorm())", p[0]),
+		OPERATOR_L1_1 => format!("{}.cwiseAbs().colwise().sum()", p[0]),
+		OPERATOR_L1_2 => format!("{}.cwiseAbs().rowwise().sum()", p[0]),
+		OPERATOR_L1_ALL => format!("meta_diff::scalar({}.cwiseAbs().sum())", p[0]),
+		OPERATOR_MAX => format!("meta_diff::max({}, {})", p[0], p[1]),
+		OPERATOR_MIN => format!("meta_diff::min({}, {})", p[0], p[1]),
+		OPERATOR_POW => format!("meta_diff::pow({}, {})", p[0], p[1]),
+		OPERATOR_QUAD => format!("{0}.transpose() * {1} * {0}", p[0], p[1]),
+		OPERATOR_ADD => fold("add", p),
+		OPERATOR_MUL => fold("mul", p),
+		OPERATOR_DOT => fold("dot", p),
+		OPERATOR_HORZCAT => {
+			let cols = p.iter().map(|x| format!("{}.cols()", x)).collect::<Vec<String>>();
+			try!(write_resize(fmt, var, declaration, &format!("{}.rows()", p[0]), &cols.join(" + ")));
+			return write!(fmt, "\t{} << {};\n", var, p.join(", "))
+		},
+		OPERATOR_VERTCAT => {
+			let rows = p.iter().map(|x| format!("{}.rows()", x)).collect::<Vec<String>>();
+			try!(write_resize(fmt, var, declaration, &rows.join(" + "), &format!("{}.cols()", p[0])));
+			return write!(fmt, "\t{} << {};\n", var, p.join(", "))
+		},
+		OPERATOR_SUBINDEX => format!(
+			"{}.block(meta_diff::index({}), meta_diff::index({}), meta_diff::index({}), meta_diff::index({}))",
+			p[0], a[0], a[2], a[1], a[3]),
+		OPERATOR_SUBASSIGN => {
+			try!(write!(fmt, "\t{} = Eigen::MatrixXd::Zero(meta_diff::index({}), meta_diff::index({}));\n",
+				declaration, a[4], a[5]));
+			return write!(fmt, "\t{}.block(meta_diff::index({}), meta_diff::index({}), meta_diff::index({}), meta_diff::index({})) = {};\n",
+				var, a[0], a[2], a[1], a[3], p[0])
+		},
+		OPERATOR_RESHAPE => format!(
+			"Eigen::Map<const Eigen::MatrixXd>({}.data(), meta_diff::index({}), meta_diff::index({}))", p[0], a[0], a[1]),
+		OPERATOR_REPLICATEH => format!("{}.replicate(1, meta_diff::index({}))", p[0], a[0]),
+		OPERATOR_REPLICATEV => format!("{}.replicate(meta_diff::index({}), 1)", p[0], a[0])
+	};
+	write!(fmt, "\t{} = {};\n", declaration, expr)
+}
+
+/// Declares or resizes `var` to the given dimensions
+fn write_resize(fmt: &mut Write, var: &str, declaration: &str, rows: &str, cols: &str) -> Result<(), Error> {
+	if var == declaration {
+		write!(fmt, "\t{}.resize({}, {});\n", var, rows, cols)
+	} else {
+		write!(fmt, "\t{}({}, {});\n", declaration, rows, cols)
+	}
 }
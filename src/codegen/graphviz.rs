@@ -7,121 +7,51 @@ static HEADING: &'static str =
 	bgcolor=\"transparent\";
 	ranksep=.75;
 	node [shape=box, fontsize=16, style=filled];
-	subgraph cluster_0{
-		label=\"Forward Calculations\";
 ";
-static MIDDLE: &'static str =
-"	}
-	subgraph cluster_1{
-		label=\"Gradient Calculations\";
-";
-static MIDDLE_2: &'static str =
-"	}
-	subgraph cluster_2{
-		label=\"Hessian Calculations\";
-";
-
 
+/// The label of the cluster containing the nodes of the gradient level
+fn cluster_label(level: usize) -> String {
+	match level {
+		0 => "Forward Calculations".to_string(),
+		1 => "Gradient Calculations".to_string(),
+		2 => "Hessian Calculations".to_string(),
+		_ => format!("Gradient Level {} Calculations", level)
+	}
+}
 
 pub fn write_graphviz(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
 	try!(write!(fmt, "{}", HEADING));
 	let target = graph.outputs[0];
 	let outputs = graph.outputs.clone();
-	// Forward Calculation
-	for option in graph.nodes.iter(){
-		match *option{
-			None => (),
-			Some(ref value) => match {
-				if value.grad_level != 0 {
-					continue;
-				}
-				if outputs.contains(&value.id){
-					write_node(fmt, value, &value.name, "red", "ellipse")
-				}
-				else {
-					match value.node_type {
-						Type::Float(_) | Type::Integer(_)  => write_node(fmt, value, &value.name, "yellow", "rectangle"),
-						Type::ConstInput  => write_node(fmt, value, &value.name, "orange", "rectangle"),
-						Type::ConstDerived => write_node(fmt, value, &value.name, "orangered", "rectangle"),
-						Type::Parameter => write_node(fmt, value, &value.name, "green", "rectangle"),
-						Type::ParameterDerived => write_node(fmt, value, &value.name, "blue", "rectangle")
-					}
-				}
-			} {
-				Ok(()) => (),
-				Err(msg) => return Err(msg)
+	// One cluster for each gradient level
+	let max_level = graph.nodes.iter().filter_map(|x| x.as_ref()).map(|x| x.grad_level).max().unwrap_or(0);
+	for level in 0..max_level + 1 {
+		try!(write!(fmt, "\tsubgraph cluster_{}{{\n\t\tlabel=\"{}\";\n", level, cluster_label(level)));
+		for option in graph.nodes.iter(){
+			let value = match *option {
+				Some(ref value) if value.grad_level == level => value,
+				_ => continue
+			};
+			let grad_name = format!("GradOf{:?}", value.grad_parents);
+			let name = if level == 0 || value.grad_parents.len() == 0 {&value.name} else {&grad_name};
+			if level > 0 && value.id == target {
+				try!(write_node(fmt, value, name, "green", "ellipse"))
 			}
-		}
-	}
-	try!(write!(fmt, "{}", MIDDLE));
-	// Backward Calculation
-	for option in graph.nodes.iter(){
-		match *option{
-			None => (),
-			Some(ref value) => match {
-				if value.grad_level != 1 {
-					continue;
-				}
-				let mut name =  &format!("GradOf{:?}",value.grad_parents);
-				if value.grad_parents.len() == 0 {
-					name = &value.name;
-				}
-				if value.id == target {
-					write_node(fmt, value, name, "green", "ellipse")
-				}
-				else if outputs.contains(&value.id){
-					write_node(fmt, value, name, "red", "ellipse")
-				}
-				else {
-					match value.node_type {
-						Type::Float(_) | Type::Integer(_)  => write_node(fmt, value, name, "yellow", "rectangle"),
-						Type::ConstInput  => write_node(fmt, value, name, "orange", "rectangle"),
-						Type::ConstDerived => write_node(fmt, value, name, "orangered", "rectangle"),
-						Type::Parameter => write_node(fmt, value, name, "green", "rectangle"),
-						Type::ParameterDerived => write_node(fmt, value, name, "blue", "rectangle")
-					}
-				}
-			} {
-				Ok(()) => (),
-				Err(msg) => return Err(msg)
+			else if outputs.contains(&value.id){
+				try!(write_node(fmt, value, name, "red", "ellipse"))
 			}
-		}
-	}
-	try!(write!(fmt, "{}", MIDDLE_2));
-	// Hessian order
-	for option in graph.nodes.iter(){
-		match *option{
-			None => (),
-			Some(ref value) => match {
-				if value.grad_level != 2 {
-					continue;
-				}
-				let mut name =  &format!("GradOf{:?}",value.grad_parents);
-				if value.grad_parents.len() == 0 {
-					name = &value.name;
-				}
-				if value.id == target {
-					write_node(fmt, value, name, "green", "ellipse")
-				}
-				else if outputs.contains(&value.id){
-					write_node(fmt, value, name, "red", "ellipse")
-				}
-				else {
-					match value.node_type {
-						Type::Float(_) | Type::Integer(_)  => write_node(fmt, value, name, "yellow", "rectangle"),
-						Type::ConstInput  => write_node(fmt, value, name, "orange", "rectangle"),
-						Type::ConstDerived => write_node(fmt, value, name, "orangered", "rectangle"),
-						Type::Parameter => write_node(fmt, value, name, "green", "rectangle"),
-						Type::ParameterDerived => write_node(fmt, value, name, "blue", "rectangle")
-					}
-				}
-			} {
-				Ok(()) => (),
-				Err(msg) => return Err(msg)
+			else {
+				try!(match value.node_type {
+					Type::Float(_) | Type::Integer(_)  => write_node(fmt, value, name, "yellow", "rectangle"),
+					Type::ConstInput  => write_node(fmt, value, name, "orange", "rectangle"),
+					Type::ConstDerived => write_node(fmt, value, name, "orangered", "rectangle"),
+					Type::Parameter => write_node(fmt, value, name, "green", "rectangle"),
+					Type::ParameterDerived => write_node(fmt, value, name, "blue", "rectangle")
+				})
 			}
 		}
+		try!(write!(fmt, "{}", "\t}\n"));
 	}
-	try!(write!(fmt, "{}", "\t}\n"));
 	// Write connections
 	for option in graph.nodes.iter(){
 		match *option{
@@ -1,7 +1,132 @@
 use std::io::{Write, Error};
 use std::result::Result;
-use core::ComputeGraph;
+use std::collections::HashMap;
+use core::*;
 
+/// Writes a Matlab function, which computes all of the outputs of the graph and the gradients of all parameters.
+///
+/// The arguments of the function are all parameters and constant inputs in the order they were defined.
+/// The returned values are the outputs of the graph followed by the gradients of the parameters, named `grad_<parameter>`.
+/// Each node is computed by a single statement in the order given by `ComputeGraph::generate_ordering`.
 pub fn write_matlab(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
-	write!(fmt, "{}", graph.nodes.len())
+	let inputs = super::input_nodes(graph);
+	let outputs = super::output_names(graph);
+	let (ordering, grads, params) = try!(super::codegen_ordering(graph));
+	let mut vars : HashMap<usize, String> = HashMap::new();
+	// Function signature
+	let mut returns = outputs.clone();
+	returns.extend(params.iter().map(|name| format!("grad_{}", name)));
+	let mut arguments : Vec<String> = Vec::new();
+	for i in inputs.iter() {
+		let name = graph.nodes[*i].as_ref().unwrap().name.clone();
+		vars.insert(*i, name.clone());
+		arguments.push(name);
+	}
+	try!(write!(fmt, "function [{}] = {}({})\n", returns.join(", "), graph.name, arguments.join(", ")));
+	// Function body
+	for i in ordering.iter() {
+		if vars.contains_key(i) {
+			continue;
+		}
+		let node = graph.nodes[*i].as_ref().unwrap();
+		match node.node_type {
+			Type::Float(x) => {vars.insert(*i, literal(format!("{}", x))); continue;},
+			Type::Integer(x) => {vars.insert(*i, literal(format!("{}", x))); continue;},
+			_ => ()
+		}
+		let var = match graph.outputs.iter().position(|x| x == i) {
+			Some(index) => outputs[index].clone(),
+			None => format!("n{}", i)
+		};
+		let parents = node.op.parents.iter().map(|p| vars[p].clone()).collect::<Vec<String>>();
+		let args = node.op.args.iter().map(|a| vars[a].clone()).collect::<Vec<String>>();
+		try!(write_operation(fmt, &var, node.op.op_type, &parents, &args));
+		vars.insert(*i, var);
+	}
+	// Assign the returned values
+	for (id, name) in graph.outputs.iter().zip(outputs.iter()) {
+		if vars[id] != *name {
+			try!(write!(fmt, "\t{} = {};\n", name, vars[id]));
+		}
+	}
+	for (id, name) in grads.iter().zip(params.iter()) {
+		try!(write!(fmt, "\tgrad_{} = {};\n", name, vars[id]));
+	}
+	write!(fmt, "end\n")
+}
+
+/// Wraps negative numbers in brackets
+fn literal(value: String) -> String {
+	if value.starts_with("-") {
+		format!("({})", value)
+	} else {
+		value
+	}
+}
+
+/// Writes the statement(s) assigning the result of the operator to `var`
+fn write_operation(fmt: &mut Write, var: &str, op_type: OperatorType, p: &Vec<String>, a: &Vec<String>) -> Result<(), Error> {
+	let expr = match op_type {
+		OperatorType::Constant(ConstantOperatorType::None) => unreachable!(),
+		OperatorType::Custom(id) => try!(super::custom_code(id, "matlab", p)),
+		OPERATOR_CONST => p[0].clone(),
+		OPERATOR_EYE => format!("eye({})", p[0]),
+		OPERATOR_SIGN => format!("sign({})", p[0]),
+		OPERATOR_SIZE_1 => format!("size({}, 1)", p[0]),
+		OPERATOR_SIZE_2 => format!("size({}, 2)", p[0]),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) => format!("numel({})", p[0]),
+		OPERATOR_ZEROS => format!("zeros({}, {})", p[0], p[1]),
+		OPERATOR_ONES => format!("ones({}, {})", p[0], p[1]),
+		OPERATOR_LT => format!("double({} < {})", p[0], p[1]),
+		OPERATOR_LTE => format!("double({} <= {})", p[0], p[1]),
+		OPERATOR_GT => format!("double({} > {})", p[0], p[1]),
+		OPERATOR_GTE => format!("double({} >= {})", p[0], p[1]),
+		OPERATOR_EQ => format!("double({} == {})", p[0], p[1]),
+		OPERATOR_NEQ => format!("double({} ~= {})", p[0], p[1]),
+		OPERATOR_NEG => format!("-{}", p[0]),
+		OPERATOR_DIV => format!("1 ./ {}", p[0]),
+		OPERATOR_MINV => format!("inv({})", p[0]),
+		OPERATOR_TRANSPOSE => format!("{}'", p[0]),
+		OPERATOR_MDIAG | OPERATOR_VDIAG => format!("diag({})", p[0]),
+		OPERATOR_COS => format!("cos({})", p[0]),
+		OPERATOR_SIN => format!("sin({})", p[0]),
+		OPERATOR_TAN => format!("tan({})", p[0]),
+		OPERATOR_COSH => format!("cosh({})", p[0]),
+		OPERATOR_SINH => format!("sinh({})", p[0]),
+		OPERATOR_TANH => format!("tanh({})", p[0]),
+		OPERATOR_ABS => format!("abs({})", p[0]),
+		OPERATOR_LOG => format!("log({})", p[0]),
+		OPERATOR_EXP => format!("exp({})", p[0]),
+		OPERATOR_SQRT => format!("sqrt({})", p[0]),
+		OPERATOR_SQUARE => format!("{} .^ 2", p[0]),
+		OPERATOR_SIGM => format!("1 ./ (1 + exp(-{}))", p[0]),
+		OPERATOR_RECT => format!("max({}, 0)", p[0]),
+		OPERATOR_SUM_1 => format!("sum({}, 1)", p[0]),
+		OPERATOR_SUM_2 => format!("sum({}, 2)", p[0]),
+		OPERATOR_SUM_ALL => format!("sum(sum({}))", p[0]),
+		OPERATOR_L2_1 => format!("sum({} .^ 2, 1)", p[0]),
+		OPERATOR_L2_2 => format!("sum({} .^ 2, 2)", p[0]),
+		OPERATOR_L2_ALL => format!("sum(sum({} .^ 2))", p[0]),
+		OPERATOR_L1_1 => format!("sum(abs({}), 1)", p[0]),
+		OPERATOR_L1_2 => format!("sum(abs({}), 2)", p[0]),
+		OPERATOR_L1_ALL => format!("sum(sum(abs({})))", p[0]),
+		OPERATOR_MAX => format!("max({}, {})", p[0], p[1]),
+		OPERATOR_MIN => format!("min({}, {})", p[0], p[1]),
+		OPERATOR_POW => format!("{} .^ {}", p[0], p[1]),
+		OPERATOR_QUAD => format!("{0}' * {1} * {0}", p[0], p[1]),
+		OPERATOR_ADD => p.join(" + "),
+		OPERATOR_MUL => p.join(" .* "),
+		OPERATOR_DOT => p.join(" * "),
+		OPERATOR_HORZCAT => format!("[{}]", p.join(", ")),
+		OPERATOR_VERTCAT => format!("[{}]", p.join("; ")),
+		OPERATOR_SUBINDEX => format!("{0}({1} + 1:{1} + {2}, {3} + 1:{3} + {4})", p[0], a[0], a[1], a[2], a[3]),
+		OPERATOR_SUBASSIGN => {
+			try!(write!(fmt, "\t{} = zeros({}, {});\n", var, a[4], a[5]));
+			return write!(fmt, "\t{}({} + 1:{} + {}, {} + 1:{} + {}) = {};\n", var, a[0], a[0], a[1], a[2], a[2], a[3], p[0])
+		},
+		OPERATOR_RESHAPE => format!("reshape({}, {}, {})", p[0], a[0], a[1]),
+		OPERATOR_REPLICATEH => format!("repmat({}, 1, {})", p[0], a[0]),
+		OPERATOR_REPLICATEV => format!("repmat({}, {}, 1)", p[0], a[0])
+	};
+	write!(fmt, "\t{} = {};\n", var, expr)
 }
@@ -1,10 +1,77 @@
 mod eigen;
 mod matlab;
 mod graphviz;
+mod rust;
+mod numpy;
+mod c;
+mod opencl;
 
 pub use self::graphviz::write_graphviz;
 pub use self::matlab::write_matlab;
 pub use self::eigen::write_eigen;
+pub use self::rust::{write_rust, RawMatrix};
+pub use self::numpy::write_numpy;
+pub use self::c::write_c;
+pub use self::opencl::write_opencl;
 
+use std::io::{Error, ErrorKind};
+use core::*;
 
+/// Converts a `GraphError` raised while generating code to an `std::io::Error`
+fn graph_error(err: GraphError) -> Error {
+	Error::new(ErrorKind::InvalidInput, format!("{}", err))
+}
 
+/// Returns the code of the registered operator for the backend with the variables of the parents substituted
+fn custom_code(id: usize, backend: &str, parents: &Vec<String>) -> Result<String, Error> {
+	let op = try!(custom_operator(id).ok_or(
+		Error::new(ErrorKind::InvalidInput, format!("No operator is registered with id {}", id))));
+	op.code_for(backend, parents).ok_or(
+		Error::new(ErrorKind::InvalidInput, format!("The operator {} has no code for the {} backend", op.name, backend)))
+}
+
+/// Returns the ids of all `Parameter` and `ConstInput` nodes, in the order they were created
+fn input_nodes(graph: &ComputeGraph) -> Vec<usize> {
+	graph.nodes.iter().filter_map(|option| match *option {
+		Some(ref node) => match node.node_type {
+			Type::Parameter | Type::ConstInput => Some(node.id),
+			_ => None
+		},
+		None => None
+	}).collect::<Vec<usize>>()
+}
+
+/// Returns a unique variable name for each of the graph outputs.
+/// The name of the node is used when it is a valid identifier, otherwise `out<index>`
+fn output_names(graph: &ComputeGraph) -> Vec<String> {
+	let mut names : Vec<String> = Vec::new();
+	for (i, id) in graph.outputs.iter().enumerate() {
+		let mut name = match graph.nodes[*id] {
+			Some(ref node) if is_identifier(&node.name) => node.name.clone(),
+			_ => format!("out{}", i + 1)
+		};
+		if names.contains(&name) {
+			name = format!("{}_{}", name, i + 1);
+		}
+		names.push(name);
+	}
+	names
+}
+
+/// Checks if the string can be used as a variable name in all of the target languages
+fn is_identifier(name: &str) -> bool {
+	match name.chars().next() {
+		Some(c) if c.is_alphabetic() => name.chars().all(|c| c.is_alphanumeric() || c == '_'),
+		_ => false
+	}
+}
+
+/// Returns the ordering of all nodes required to compute the outputs and the gradients of the parameters,
+/// together with the gradient node ids and the names of their parameters
+fn codegen_ordering(graph: &ComputeGraph) -> Result<(Vec<usize>, Vec<usize>, Vec<String>), Error> {
+	let (grads, params) = graph.get_params();
+	let mut targets = graph.outputs.clone();
+	targets.extend(grads.iter().cloned());
+	let ordering = try!(graph.generate_ordering(targets).map_err(graph_error));
+	Ok((ordering, grads, params))
+}
@@ -0,0 +1,152 @@
+use std::io::{Write, Error};
+use std::result::Result;
+use std::collections::HashMap;
+use core::*;
+
+static HEADING: &'static str =
+"import numpy as np
+
+
+def _scalar(value):
+    return np.array([[value]], dtype=np.float64)
+
+
+def _dot(a, b):
+    # A product with a scalar is an elementwise multiplication
+    if a.size == 1 or b.size == 1:
+        return a * b
+    return a.dot(b)
+
+
+def _index(value):
+    return int(np.asarray(value).flat[0])
+
+
+def _subassign(value, start_x, size_x, start_y, size_y, rows, cols):
+    result = np.zeros((rows, cols))
+    result[start_x:start_x + size_x, start_y:start_y + size_y] = value
+    return result
+
+
+";
+
+/// Writes a Python module with a single function, which computes all of the outputs of the graph and the gradients of all parameters using NumPy.
+///
+/// All values are represented as two dimensional arrays, where scalars are 1x1 arrays.
+/// The arguments of the function are all parameters and constant inputs in the order they were defined.
+/// The function returns the outputs of the graph followed by a dictionary of the gradients, keyed by the names of the parameters.
+pub fn write_numpy(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
+	let inputs = super::input_nodes(graph);
+	let outputs = super::output_names(graph);
+	let (ordering, grads, params) = try!(super::codegen_ordering(graph));
+	let mut vars : HashMap<usize, String> = HashMap::new();
+	try!(write!(fmt, "{}", HEADING));
+	// Function signature
+	let mut arguments : Vec<String> = Vec::new();
+	for i in inputs.iter() {
+		let name = graph.nodes[*i].as_ref().unwrap().name.clone();
+		vars.insert(*i, name.clone());
+		arguments.push(name);
+	}
+	try!(write!(fmt, "def {}({}):\n", graph.name, arguments.join(", ")));
+	for name in arguments.iter() {
+		try!(write!(fmt, "    {0} = np.atleast_2d(np.asarray({0}, dtype=np.float64))\n", name));
+	}
+	// Function body
+	for i in ordering.iter() {
+		if vars.contains_key(i) {
+			continue;
+		}
+		let node = graph.nodes[*i].as_ref().unwrap();
+		let var = match graph.outputs.iter().position(|x| x == i) {
+			Some(index) => outputs[index].clone(),
+			None => format!("n{}", i)
+		};
+		let expr = match node.node_type {
+			Type::Float(x) => format!("_scalar({:?})", x),
+			Type::Integer(x) => format!("_scalar({:?})", x as f64),
+			_ => {
+				let parents = node.op.parents.iter().map(|p| vars[p].clone()).collect::<Vec<String>>();
+				let args = node.op.args.iter().map(|a| vars[a].clone()).collect::<Vec<String>>();
+				match node.op.op_type {
+					OperatorType::Custom(id) => try!(super::custom_code(id, "numpy", &parents)),
+					op_type => operation(op_type, &parents, &args)
+				}
+			}
+		};
+		try!(write!(fmt, "    {} = {}\n", var, expr));
+		vars.insert(*i, var);
+	}
+	// Return the outputs and the gradients
+	let mut results = graph.outputs.iter().map(|id| vars[id].clone()).collect::<Vec<String>>();
+	let gradients = grads.iter().zip(params.iter())
+		.map(|(id, name)| format!("'{}': {}", name, vars[id])).collect::<Vec<String>>();
+	results.push(format!("{{{}}}", gradients.join(", ")));
+	write!(fmt, "    return {}\n", results.join(", "))
+}
+
+/// Returns the expression computing the result of the operator
+fn operation(op_type: OperatorType, p: &Vec<String>, a: &Vec<String>) -> String {
+	match op_type {
+		OperatorType::Constant(ConstantOperatorType::None) | OperatorType::Custom(_) => unreachable!(),
+		OPERATOR_CONST => p[0].clone(),
+		OPERATOR_EYE => format!("np.eye(_index({}))", p[0]),
+		OPERATOR_SIGN => format!("np.sign({})", p[0]),
+		OPERATOR_SIZE_1 => format!("_scalar({}.shape[0])", p[0]),
+		OPERATOR_SIZE_2 => format!("_scalar({}.shape[1])", p[0]),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) =>
+			format!("_scalar({}.size)", p[0]),
+		OPERATOR_ZEROS => format!("np.zeros((_index({}), _index({})))", p[0], p[1]),
+		OPERATOR_ONES => format!("np.ones((_index({}), _index({})))", p[0], p[1]),
+		OPERATOR_LT => format!("({} < {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_LTE => format!("({} <= {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_GT => format!("({} > {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_GTE => format!("({} >= {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_EQ => format!("({} == {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_NEQ => format!("({} != {}).astype(np.float64)", p[0], p[1]),
+		OPERATOR_NEG => format!("-{}", p[0]),
+		OPERATOR_DIV => format!("1.0 / {}", p[0]),
+		OPERATOR_MINV => format!("np.linalg.inv({})", p[0]),
+		OPERATOR_TRANSPOSE => format!("{}.T", p[0]),
+		OPERATOR_MDIAG => format!("np.diag({}).reshape(-1, 1)", p[0]),
+		OPERATOR_VDIAG => format!("np.diag({}.ravel())", p[0]),
+		OPERATOR_COS => format!("np.cos({})", p[0]),
+		OPERATOR_SIN => format!("np.sin({})", p[0]),
+		OPERATOR_TAN => format!("np.tan({})", p[0]),
+		OPERATOR_COSH => format!("np.cosh({})", p[0]),
+		OPERATOR_SINH => format!("np.sinh({})", p[0]),
+		OPERATOR_TANH => format!("np.tanh({})", p[0]),
+		OPERATOR_ABS => format!("np.abs({})", p[0]),
+		OPERATOR_LOG => format!("np.log({})", p[0]),
+		OPERATOR_EXP => format!("np.exp({})", p[0]),
+		OPERATOR_SQRT => format!("np.sqrt({})", p[0]),
+		OPERATOR_SQUARE => format!("np.square({})", p[0]),
+		OPERATOR_SIGM => format!("1.0 / (1.0 + np.exp(-{}))", p[0]),
+		OPERATOR_RECT => format!("np.maximum({}, 0.0)", p[0]),
+		OPERATOR_SUM_1 => format!("np.sum({}, axis=0, keepdims=True)", p[0]),
+		OPERATOR_SUM_2 => format!("np.sum({}, axis=1, keepdims=True)", p[0]),
+		OPERATOR_SUM_ALL => format!("_scalar(np.sum({}))", p[0]),
+		OPERATOR_L2_1 => format!("np.sum(np.square({}), axis=0, keepdims=True)", p[0]),
+		OPERATOR_L2_2 => format!("np.sum(np.square({}), axis=1, keepdims=True)", p[0]),
+		OPERATOR_L2_ALL => format!("_scalar(np.sum(np.square({})))", p[0]),
+		OPERATOR_L1_1 => format!("np.sum(np.abs({}), axis=0, keepdims=True)", p[0]),
+		OPERATOR_L1_2 => format!("np.sum(np.abs({}), axis=1, keepdims=True)", p[0]),
+		OPERATOR_L1_ALL => format!("_scalar(np.sum(np.abs({})))", p[0]),
+		OPERATOR_MAX => format!("np.maximum({}, {})", p[0], p[1]),
+		OPERATOR_MIN => format!("np.minimum({}, {})", p[0], p[1]),
+		OPERATOR_POW => format!("np.power({}, {})", p[0], p[1]),
+		OPERATOR_QUAD => format!("{0}.T.dot({1}).dot({0})", p[0], p[1]),
+		OPERATOR_ADD => p.join(" + "),
+		OPERATOR_MUL => p.join(" * "),
+		OPERATOR_DOT => p.iter().skip(1).fold(p[0].clone(), |acc, x| format!("_dot({}, {})", acc, x)),
+		OPERATOR_HORZCAT => format!("np.hstack([{}])", p.join(", ")),
+		OPERATOR_VERTCAT => format!("np.vstack([{}])", p.join(", ")),
+		OPERATOR_SUBINDEX => format!("{0}[_index({1}):_index({1}) + _index({2}), _index({3}):_index({3}) + _index({4})]",
+			p[0], a[0], a[1], a[2], a[3]),
+		OPERATOR_SUBASSIGN => format!("_subassign({}, {})", p[0],
+			a.iter().map(|x| format!("_index({})", x)).collect::<Vec<String>>().join(", ")),
+		OPERATOR_RESHAPE => format!("np.reshape({}, (_index({}), _index({})), order='F')", p[0], a[0], a[1]),
+		OPERATOR_REPLICATEH => format!("np.tile({}, (1, _index({})))", p[0], a[0]),
+		OPERATOR_REPLICATEV => format!("np.tile({}, (_index({}), 1))", p[0], a[0])
+	}
+}
@@ -0,0 +1,507 @@
+use std::io::{Write, Error};
+use std::result::Result;
+use std::collections::{HashMap, HashSet};
+use core::*;
+
+static HEADING: &'static str =
+"#pragma OPENCL EXTENSION cl_khr_fp64 : enable
+
+#ifndef META_DIFF_OPENCL_HELPERS
+#define META_DIFF_OPENCL_HELPERS
+/* All matrices are column major, scalars are represented as 1x1 matrices */
+#define MD_TILE 16
+#define MD_REDUCE_LOCAL 64
+#define MD_AT(x, i, j) x[((x##_rows) == 1 ? 0 : (i)) + ((x##_cols) == 1 ? 0 : (j)) * (x##_rows)]
+
+/* c = a * b, using tiles of MD_TILE x MD_TILE elements in local memory */
+void md_gemm(__global const double* a, __global const double* b, __global double* c, const int rows, const int inner, const int cols,
+		__local double* tile_a, __local double* tile_b) {
+	const int li = get_local_id(0);
+	const int lj = get_local_id(1);
+	const int i = get_global_id(0);
+	const int j = get_global_id(1);
+	double value = 0.0;
+	for (int t = 0; t < inner; t += MD_TILE) {
+		tile_a[li + lj * MD_TILE] = (i < rows && t + lj < inner) ? a[i + (t + lj) * rows] : 0.0;
+		tile_b[li + lj * MD_TILE] = (t + li < inner && j < cols) ? b[t + li + j * inner] : 0.0;
+		barrier(CLK_LOCAL_MEM_FENCE);
+		for (int k = 0; k < MD_TILE; ++k) {
+			value += tile_a[li + k * MD_TILE] * tile_b[k + lj * MD_TILE];
+		}
+		barrier(CLK_LOCAL_MEM_FENCE);
+	}
+	if (i < rows && j < cols) {
+		c[i + j * rows] = value;
+	}
+}
+
+/* c = a * b with rows x cols elements, where a product with a scalar is an elementwise multiplication.
+ * Launched as md_gemm, the scalar case is taken by the whole work group */
+void md_dot(__global const double* a, const int a_rows, const int a_cols, __global const double* b, const int b_rows, const int b_cols,
+		__global double* c, const int rows, const int cols, __local double* tile_a, __local double* tile_b) {
+	if (a_rows * a_cols == 1 || b_rows * b_cols == 1) {
+		const int i = get_global_id(0);
+		const int j = get_global_id(1);
+		if (i < rows && j < cols) {
+			c[i + j * rows] = a_rows * a_cols == 1 ? a[0] * b[i + j * rows] : a[i + j * rows] * b[0];
+		}
+		return;
+	}
+	md_gemm(a, b, c, rows, a_cols, cols, tile_a, tile_b);
+}
+
+/* Reduces n elements starting at offset with the given stride using a single work group of MD_REDUCE_LOCAL items.
+ * norm is 0 for sum, 1 for L1 and 2 for L2 */
+void md_reduce(__global const double* in, const int offset, const int stride, const int n, const int norm,
+		__global double* out, __local double* scratch) {
+	const int l = get_local_id(0);
+	double value = 0.0;
+	for (int k = l; k < n; k += MD_REDUCE_LOCAL) {
+		const double x = in[offset + k * stride];
+		value += norm == 0 ? x : (norm == 1 ? fabs(x) : x * x);
+	}
+	scratch[l] = value;
+	barrier(CLK_LOCAL_MEM_FENCE);
+	for (int s = MD_REDUCE_LOCAL / 2; s > 0; s /= 2) {
+		if (l < s) {
+			scratch[l] += scratch[l + s];
+		}
+		barrier(CLK_LOCAL_MEM_FENCE);
+	}
+	if (l == 0) {
+		*out = scratch[0];
+	}
+}
+
+/* Gauss-Jordan elimination with partial pivoting, the scratch buffer must hold n * n elements */
+void md_inverse(const int n, __global const double* in, __global double* out, __global double* scratch) {
+	for (int k = 0; k < n * n; ++k) {
+		scratch[k] = in[k];
+		out[k] = k % (n + 1) == 0 ? 1.0 : 0.0;
+	}
+	for (int k = 0; k < n; ++k) {
+		int pivot = k;
+		for (int i = k + 1; i < n; ++i) {
+			if (fabs(scratch[i + k * n]) > fabs(scratch[pivot + k * n])) pivot = i;
+		}
+		for (int j = 0; j < n; ++j) {
+			double t = scratch[k + j * n]; scratch[k + j * n] = scratch[pivot + j * n]; scratch[pivot + j * n] = t;
+			t = out[k + j * n]; out[k + j * n] = out[pivot + j * n]; out[pivot + j * n] = t;
+		}
+		double value = scratch[k + k * n];
+		for (int j = 0; j < n; ++j) {
+			scratch[k + j * n] /= value;
+			out[k + j * n] /= value;
+		}
+		for (int i = 0; i < n; ++i) {
+			if (i == k) continue;
+			value = scratch[i + k * n];
+			for (int j = 0; j < n; ++j) {
+				scratch[i + j * n] -= value * scratch[k + j * n];
+				out[i + j * n] -= value * out[k + j * n];
+			}
+		}
+	}
+}
+
+/* Element (k, l) of a' * b * a */
+double md_quad_at(__global const double* a, const int rows, __global const double* b, const int k, const int l) {
+	double value = 0.0;
+	for (int j = 0; j < rows; ++j) {
+		for (int i = 0; i < rows; ++i) {
+			value += a[i + k * rows] * b[i + j * rows] * a[j + l * rows];
+		}
+	}
+	return value;
+}
+#endif
+
+";
+
+/// Writes OpenCL kernels, which compute all of the outputs of the graph and the gradients of all parameters.
+///
+/// The kernels are host agnostic - each input and each node computed by a kernel is stored in a separate buffer,
+/// which has to be provided by the host. Every parameter of a kernel is named after a node, so the host binds them
+/// by name, without knowing which nodes have been fused:
+///
+/// * `n<id>` is the buffer of the node, the first parameter being the buffer the kernel writes.
+///   Nodes fused in to a kernel, listed in the comment above it, are never passed and need no buffer.
+/// * `n<id>_rows` and `n<id>_cols` are the dimensions of the node with that buffer.
+/// * `const int n<id>` is the value of a literal or a `Size` node, which the host knows from the dimensions of the inputs,
+///   e.g. the start of a `SubIndex`. Such nodes get a buffer only if they are an output, a gradient or are read by a `Dot`,
+///   `MatrixInverse`, `Quadratic` or a reduction.
+///
+/// The ids of the inputs, outputs and gradients are given in the comments at the top of the file.
+/// The kernels should be launched in the order they are written, the launch configuration of each is given in a comment above it.
+/// Chains of elementwise operators are fused into a single kernel, `Dot` is computed with a tiled matrix multiplication
+/// or elementwise if an operand is a scalar, while `Sum`, `L1` and `L2` are computed with a work group reduction
+/// for each row, column or the whole matrix.
+pub fn write_opencl(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
+	let inputs = super::input_nodes(graph);
+	let outputs = super::output_names(graph);
+	let (ordering, grads, params) = try!(super::codegen_ordering(graph));
+	let targets = graph.outputs.iter().chain(grads.iter()).cloned().collect::<Vec<usize>>();
+	// Find all consumers of each node
+	let mut consumers : HashMap<usize, Vec<usize>> = HashMap::new();
+	let mut arguments : HashSet<usize> = HashSet::new();
+	for i in ordering.iter() {
+		let node = graph.nodes[*i].as_ref().unwrap();
+		for p in node.op.parents.iter() {
+			let list = consumers.entry(*p).or_insert(Vec::new());
+			if !list.contains(i) {
+				list.push(*i);
+			}
+		}
+		arguments.extend(node.op.args.iter().cloned());
+		// The code of registered operators is checked upfront, as the kernel expressions can not fail
+		if let OperatorType::Custom(id) = node.op.op_type {
+			try!(super::custom_code(id, "opencl", &node.op.parents.iter().map(|p| format!("n{}", p)).collect()));
+		}
+	}
+	// Decide which nodes are fused into their consumers and which need their own kernel
+	let mut inlined : HashSet<usize> = HashSet::new();
+	let mut kernels : Vec<usize> = Vec::new();
+	for i in ordering.iter() {
+		let node = graph.nodes[*i].as_ref().unwrap();
+		let used_by = consumers.get(i).cloned().unwrap_or(Vec::new());
+		if inputs.contains(i) {
+			continue;
+		} else if is_host(node) {
+			if targets.contains(i) || used_by.iter().any(|c| reads_buffers(graph.nodes[*c].as_ref().unwrap().op.op_type)) {
+				kernels.push(*i);
+			}
+		} else if is_elementwise(node.op.op_type) && !targets.contains(i) && !arguments.contains(i)
+			&& used_by.len() == 1 && is_elementwise(graph.nodes[used_by[0]].as_ref().unwrap().op.op_type) {
+			inlined.insert(*i);
+		} else {
+			kernels.push(*i);
+		}
+	}
+	try!(write!(fmt, "{}", HEADING));
+	// Mapping between the nodes and the inputs and results
+	try!(write!(fmt, "// Kernels for `{}`, which must be launched in the order they appear\n", graph.name));
+	try!(write!(fmt, "// Inputs: {}\n", inputs.iter()
+		.map(|i| format!("{} = n{}", graph.nodes[*i].as_ref().unwrap().name, i)).collect::<Vec<String>>().join(", ")));
+	try!(write!(fmt, "// Outputs: {}\n", outputs.iter().zip(graph.outputs.iter())
+		.map(|(name, i)| format!("{} = n{}", name, i)).collect::<Vec<String>>().join(", ")));
+	try!(write!(fmt, "// Gradients: {}\n\n", params.iter().zip(grads.iter())
+		.map(|(name, i)| format!("{} = n{}", name, i)).collect::<Vec<String>>().join(", ")));
+	for i in kernels.iter() {
+		let node = graph.nodes[*i].as_ref().unwrap();
+		match node.op.op_type {
+			OPERATOR_DOT => try!(write_gemm(fmt, graph, *i)),
+			OPERATOR_MINV => try!(write_inverse(fmt, graph, *i)),
+			OPERATOR_SUM_1 | OPERATOR_SUM_2 | OPERATOR_SUM_ALL | OPERATOR_L2_1 | OPERATOR_L2_2 | OPERATOR_L2_ALL
+			| OPERATOR_L1_1 | OPERATOR_L1_2 | OPERATOR_L1_ALL => try!(write_reduction(fmt, graph, *i)),
+			_ => try!(write_pointwise(fmt, graph, &inlined, *i))
+		}
+	}
+	Ok(())
+}
+
+/// Checks if the value of the node is a scalar known to the host - a literal or the size of another node
+fn is_host(node: &ComputeNode) -> bool {
+	match node.node_type {
+		Type::Float(_) | Type::Integer(_) => true,
+		_ => match node.op.op_type {
+			OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(_))) => true,
+			_ => false
+		}
+	}
+}
+
+/// Checks if the operator is applied independently to each element and can be fused with its consumers
+fn is_elementwise(op_type: OperatorType) -> bool {
+	match op_type {
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(_))) => false,
+		OperatorType::Constant(ConstantOperatorType::None) => false,
+		OperatorType::Constant(_) => true,
+		OPERATOR_MINV | OPERATOR_TRANSPOSE | OPERATOR_MDIAG | OPERATOR_VDIAG => false,
+		OperatorType::Unary(UnaryOperatorType::Sum(_)) | OperatorType::Unary(UnaryOperatorType::L2(_))
+		| OperatorType::Unary(UnaryOperatorType::L1(_)) => false,
+		OperatorType::Unary(_) => true,
+		OPERATOR_QUAD => false,
+		OperatorType::Binary(_) => true,
+		OPERATOR_ADD | OPERATOR_MUL => true,
+		OperatorType::Custom(_) => true,
+		_ => false
+	}
+}
+
+/// Checks if the operator requires all of its parents to be stored in buffers
+fn reads_buffers(op_type: OperatorType) -> bool {
+	match op_type {
+		OPERATOR_DOT | OPERATOR_MINV | OPERATOR_QUAD => true,
+		OperatorType::Unary(UnaryOperatorType::Sum(_)) | OperatorType::Unary(UnaryOperatorType::L2(_))
+		| OperatorType::Unary(UnaryOperatorType::L1(_)) => true,
+		_ => false
+	}
+}
+
+/// The norm argument of `md_reduce` and the dimension for a reduction operator
+fn reduction(op_type: OperatorType) -> (usize, Dimension) {
+	match op_type {
+		OperatorType::Unary(UnaryOperatorType::Sum(dim)) => (0, dim),
+		OperatorType::Unary(UnaryOperatorType::L1(dim)) => (1, dim),
+		OperatorType::Unary(UnaryOperatorType::L2(dim)) => (2, dim),
+		_ => unreachable!()
+	}
+}
+
+/// Writes a kernel performing a tiled matrix multiplication for each pair of parents of a `Dot` node.
+/// A pair with an operand known to be a scalar is an elementwise multiplication instead,
+/// while a pair which may be a scalar at runtime is checked by `md_dot`.
+/// The intermediate products are stored in buffers named after the node with a suffix
+fn write_gemm(fmt: &mut Write, graph: & ComputeGraph, id: usize) -> Result<(),Error>{
+	let node = graph.nodes[id].as_ref().unwrap();
+	let is_scalar = |dims: &Pair<SymPolynomial>| dims.0.is_one() && dims.1.is_one();
+	let one = SymPolynomial::constant(1);
+	let may_be_scalar = |dims: &Pair<SymPolynomial>| dims.0.may_equal(&one) && dims.1.may_equal(&one);
+	let mut last = format!("n{}", node.op.parents[0]);
+	let mut dims = graph.nodes[node.op.parents[0]].as_ref().unwrap().dims.clone();
+	for (k, p) in node.op.parents.iter().enumerate().skip(1) {
+		let var = if k == node.op.parents.len() - 1 {format!("n{}", id)} else {format!("n{}_{}", id, k)};
+		let other = graph.nodes[*p].as_ref().unwrap().dims.clone();
+		if is_scalar(&dims) || is_scalar(&other) {
+			let (a, b) = if is_scalar(&dims) {("[0]", "[k]")} else {("[k]", "[0]")};
+			try!(write!(fmt, "// Computes {0} = {1} * n{2}, where one of them is a scalar. Global size: {0}_rows * {0}_cols
+",
+				var, last, p));
+			try!(write!(fmt, "__kernel void {0}_{1}(__global double* {1}, const int {1}_rows, const int {1}_cols, \
+				__global const double* {2}, __global const double* n{3}) {{\n", graph.name, var, last, p));
+			try!(write!(fmt, "\tconst int k = get_global_id(0);\n\tif (k < {0}_rows * {0}_cols) {{\n\t\t{0}[k] = {1}{3} * n{2}{4};\n\t}}\n}}\n\n",
+				var, last, p, a, b));
+		} else if may_be_scalar(&dims) || may_be_scalar(&other) {
+			try!(write!(fmt, "// Computes {0} = {1} * n{2}. Global size: ({0}_rows, {0}_cols) rounded up to MD_TILE, local size: (MD_TILE, MD_TILE)\n",
+				var, last, p));
+			try!(write!(fmt, "__kernel void {0}_{1}(__global double* {1}, const int {1}_rows, const int {1}_cols, \
+				__global const double* {2}, const int {2}_rows, const int {2}_cols, \
+				__global const double* n{3}, const int n{3}_rows, const int n{3}_cols) {{\n", graph.name, var, last, p));
+			try!(write!(fmt, "\t__local double tile_a[MD_TILE * MD_TILE];\n\t__local double tile_b[MD_TILE * MD_TILE];\n"));
+			try!(write!(fmt, "\tmd_dot({1}, {1}_rows, {1}_cols, n{2}, n{2}_rows, n{2}_cols, {0}, {0}_rows, {0}_cols, tile_a, tile_b);\n}}\n\n",
+				var, last, p));
+		} else {
+			try!(write!(fmt, "// Computes {0} = {1} * n{2}. Global size: ({0}_rows, {0}_cols) rounded up to MD_TILE, local size: (MD_TILE, MD_TILE)\n",
+				var, last, p));
+			try!(write!(fmt, "__kernel void {0}_{1}(__global double* {1}, const int {1}_rows, const int {1}_cols, \
+				__global const double* {2}, const int {2}_cols, __global const double* n{3}) {{\n", graph.name, var, last, p));
+			try!(write!(fmt, "\t__local double tile_a[MD_TILE * MD_TILE];\n\t__local double tile_b[MD_TILE * MD_TILE];\n"));
+			try!(write!(fmt, "\tmd_gemm({1}, n{2}, {0}, {0}_rows, {1}_cols, {0}_cols, tile_a, tile_b);\n}}\n\n", var, last, p));
+		}
+		dims = dot_dims(dims, other);
+		last = var;
+	}
+	Ok(())
+}
+
+/// Writes a kernel computing the inverse of a matrix with a single work item
+fn write_inverse(fmt: &mut Write, graph: & ComputeGraph, id: usize) -> Result<(),Error>{
+	let parent = graph.nodes[id].as_ref().unwrap().op.parents[0];
+	try!(write!(fmt, "// Computes n{0} = minv(n{1}), n{0}_scratch must have the same size as n{1}. Global size: 1\n", id, parent));
+	write!(fmt, "__kernel void {0}_n{1}(__global double* n{1}, __global double* n{1}_scratch, \
+		__global const double* n{2}, const int n{2}_rows) {{\n\tmd_inverse(n{2}_rows, n{2}, n{1}, n{1}_scratch);\n}}\n\n",
+		graph.name, id, parent)
+}
+
+/// Writes a kernel computing a sum or a norm, using a work group for each element of the result
+fn write_reduction(fmt: &mut Write, graph: & ComputeGraph, id: usize) -> Result<(),Error>{
+	let node = graph.nodes[id].as_ref().unwrap();
+	let p = format!("n{}", node.op.parents[0]);
+	let (norm, dim) = reduction(node.op.op_type);
+	let (groups, call) = match dim {
+		Dimension::First => (format!("{}_cols * ", p), format!("{0}, g * {0}_rows, 1, {0}_rows, {1}, n{2} + g", p, norm, id)),
+		Dimension::Second => (format!("{}_rows * ", p), format!("{0}, g, {0}_rows, {0}_cols, {1}, n{2} + g", p, norm, id)),
+		Dimension::All => ("".to_string(), format!("{0}, 0, 1, {0}_rows * {0}_cols, {1}, n{2}", p, norm, id))
+	};
+	try!(write!(fmt, "// Computes n{}. Global size: {}MD_REDUCE_LOCAL, local size: MD_REDUCE_LOCAL\n", id, groups));
+	try!(write!(fmt, "__kernel void {0}_n{1}(__global double* n{1}, __global const double* {2}, const int {2}_rows, const int {2}_cols) {{\n",
+		graph.name, id, p));
+	try!(write!(fmt, "\t__local double scratch[MD_REDUCE_LOCAL];\n"));
+	if dim != Dimension::All {
+		try!(write!(fmt, "\tconst int g = get_group_id(0);\n"));
+	}
+	write!(fmt, "\tmd_reduce({}, scratch);\n}}\n\n", call)
+}
+
+/// Writes a kernel with a work item for each element of the node, fusing any inlined elementwise parents
+fn write_pointwise(fmt: &mut Write, graph: & ComputeGraph, inlined: &HashSet<usize>, id: usize) -> Result<(),Error>{
+	let var = format!("n{}", id);
+	let mut kernel = Kernel{graph: graph, inlined: inlined, root: var.clone(), params: Vec::new(), fused: Vec::new()};
+	let expr = kernel.operation(id);
+	kernel.fused.sort();
+	let fused = if kernel.fused.len() > 0 {
+		format!(", fusing {}", kernel.fused.iter().map(|i| format!("n{}", i)).collect::<Vec<String>>().join(", "))
+	} else {
+		"".to_string()
+	};
+	try!(write!(fmt, "// Computes {0}{1}. Global size: {0}_rows * {0}_cols\n", var, fused));
+	let mut params = vec![format!("__global double* {}", var), format!("const int {}_rows", var), format!("const int {}_cols", var)];
+	params.extend(kernel.params.into_iter());
+	try!(write!(fmt, "__kernel void {}_{}({}) {{\n", graph.name, var, params.join(", ")));
+	try!(write!(fmt, "\tconst int k = get_global_id(0);\n\tif (k >= {0}_rows * {0}_cols) return;\n", var));
+	try!(write!(fmt, "\tconst int i = k % {0}_rows;\n\tconst int j = k / {0}_rows;\n", var));
+	write!(fmt, "\t{}[k] = {};\n}}\n\n", var, expr)
+}
+
+/// Builds the expression for a single element of a pointwise kernel, collecting its parameters
+struct Kernel<'a> {
+	graph: &'a ComputeGraph,
+	inlined: &'a HashSet<usize>,
+	root: String,
+	params: Vec<String>,
+	fused: Vec<usize>
+}
+
+impl<'a> Kernel<'a> {
+	fn param(&mut self, param: String) {
+		if !self.params.contains(&param) {
+			self.params.push(param);
+		}
+	}
+
+	/// An integer argument passed by the host
+	fn int(&mut self, id: usize) -> String {
+		self.param(format!("const int n{}", id));
+		format!("n{}", id)
+	}
+
+	/// A node stored in a buffer together with its dimensions
+	fn buffer(&mut self, id: usize) -> String {
+		self.param(format!("__global const double* n{}", id));
+		self.param(format!("const int n{}_rows", id));
+		self.param(format!("const int n{}_cols", id));
+		format!("n{}", id)
+	}
+
+	fn rows(&mut self, id: usize) -> String {
+		if is_host(self.graph.nodes[id].as_ref().unwrap()) {"1".to_string()} else {format!("{}_rows", self.buffer(id))}
+	}
+
+	fn cols(&mut self, id: usize) -> String {
+		if is_host(self.graph.nodes[id].as_ref().unwrap()) {"1".to_string()} else {format!("{}_cols", self.buffer(id))}
+	}
+
+	/// The element of the node broadcast to position (i, j)
+	fn value(&mut self, id: usize) -> String {
+		let node = self.graph.nodes[id].as_ref().unwrap();
+		if is_host(node) || self.inlined.contains(&id) {
+			if self.inlined.contains(&id) && !self.fused.contains(&id) {
+				self.fused.push(id);
+			}
+			self.operation(id)
+		} else {
+			format!("MD_AT({}, i, j)", self.buffer(id))
+		}
+	}
+
+	/// The element of the node at the given linear index, where scalars known to the host are used directly
+	fn at(&mut self, id: usize, index: String) -> String {
+		if is_host(self.graph.nodes[id].as_ref().unwrap()) {
+			self.operation(id)
+		} else {
+			format!("{}[{}]", self.buffer(id), index)
+		}
+	}
+
+	/// The expression computing the element of the node at position (i, j)
+	fn operation(&mut self, id: usize) -> String {
+		let node = self.graph.nodes[id].as_ref().unwrap();
+		match node.node_type {
+			Type::Float(x) => return format!("{:?}", x),
+			Type::Integer(x) => return format!("{:?}", x as f64),
+			_ => ()
+		}
+		let p = node.op.parents.clone();
+		let a = node.op.args.clone();
+		match node.op.op_type {
+			OperatorType::Constant(ConstantOperatorType::None) => unreachable!(),
+			OPERATOR_CONST => self.value(p[0]),
+			OPERATOR_EYE => "(i == j ? 1.0 : 0.0)".to_string(),
+			OPERATOR_SIGN => format!("sign({})", self.value(p[0])),
+			OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(_))) => format!("(double) {}", self.int(id)),
+			OPERATOR_ZEROS => "0.0".to_string(),
+			OPERATOR_ONES => "1.0".to_string(),
+			OPERATOR_LT => format!("({} < {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_LTE => format!("({} <= {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_GT => format!("({} > {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_GTE => format!("({} >= {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_EQ => format!("({} == {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_NEQ => format!("({} != {} ? 1.0 : 0.0)", self.value(p[0]), self.value(p[1])),
+			OPERATOR_NEG => format!("(-{})", self.value(p[0])),
+			OPERATOR_DIV => format!("(1.0 / {})", self.value(p[0])),
+			OPERATOR_TRANSPOSE => {
+				let index = format!("j + i * {}", self.rows(p[0]));
+				self.at(p[0], index)
+			},
+			OPERATOR_MDIAG => {
+				let index = format!("i + i * {}", self.rows(p[0]));
+				self.at(p[0], index)
+			},
+			OPERATOR_VDIAG => format!("(i == j ? {} : 0.0)", self.at(p[0], "i".to_string())),
+			OPERATOR_COS => format!("cos({})", self.value(p[0])),
+			OPERATOR_SIN => format!("sin({})", self.value(p[0])),
+			OPERATOR_TAN => format!("tan({})", self.value(p[0])),
+			OPERATOR_COSH => format!("cosh({})", self.value(p[0])),
+			OPERATOR_SINH => format!("sinh({})", self.value(p[0])),
+			OPERATOR_TANH => format!("tanh({})", self.value(p[0])),
+			OPERATOR_ABS => format!("fabs({})", self.value(p[0])),
+			OPERATOR_LOG => format!("log({})", self.value(p[0])),
+			OPERATOR_EXP => format!("exp({})", self.value(p[0])),
+			OPERATOR_SQRT => format!("sqrt({})", self.value(p[0])),
+			OPERATOR_SQUARE => format!("pown({}, 2)", self.value(p[0])),
+			OPERATOR_SIGM => format!("(1.0 / (1.0 + exp(-{})))", self.value(p[0])),
+			OPERATOR_RECT => format!("fmax({}, 0.0)", self.value(p[0])),
+			OPERATOR_MAX => format!("fmax({}, {})", self.value(p[0]), self.value(p[1])),
+			OPERATOR_MIN => format!("fmin({}, {})", self.value(p[0]), self.value(p[1])),
+			OPERATOR_POW => format!("pow({}, {})", self.value(p[0]), self.value(p[1])),
+			OPERATOR_QUAD => {
+				let (x, x_rows, y) = (self.buffer(p[0]), self.rows(p[0]), self.buffer(p[1]));
+				format!("md_quad_at({}, {}, {}, i, j)", x, x_rows, y)
+			},
+			OPERATOR_ADD => format!("({})", p.iter().map(|x| self.value(*x)).collect::<Vec<String>>().join(" + ")),
+			OPERATOR_MUL => format!("({})", p.iter().map(|x| self.value(*x)).collect::<Vec<String>>().join(" * ")),
+			OperatorType::Custom(id) => {
+				let x = p.iter().map(|x| self.value(*x)).collect::<Vec<String>>();
+				format!("({})", custom_operator(id).and_then(|op| op.code_for("opencl", &x)).unwrap())
+			},
+			OPERATOR_HORZCAT | OPERATOR_VERTCAT => {
+				let horizontal = node.op.op_type == OPERATOR_HORZCAT;
+				let mut offset = "0".to_string();
+				let mut cases : Vec<(String, String)> = Vec::new();
+				for x in p.iter() {
+					let (rows, cols) = (self.rows(*x), self.cols(*x));
+					let (index, size) = if horizontal {
+						(format!("i + (j - {}) * {}", offset, rows), cols)
+					} else {
+						(format!("i - {} + j * {}", offset, rows), rows)
+					};
+					offset = format!("{} + {}", offset, size);
+					cases.push((offset.clone(), self.at(*x, index)));
+				}
+				let last = cases.pop().unwrap().1;
+				cases.iter().rev().fold(last, |acc, &(ref end, ref value)|
+					format!("({} < {} ? {} : {})", if horizontal {"j"} else {"i"}, end, value, acc))
+			},
+			OPERATOR_SUBINDEX => {
+				let (start_x, start_y, rows) = (self.int(a[0]), self.int(a[2]), self.rows(p[0]));
+				self.at(p[0], format!("{} + i + ({} + j) * {}", start_x, start_y, rows))
+			},
+			OPERATOR_SUBASSIGN => {
+				let (start_x, size_x, start_y, size_y) = (self.int(a[0]), self.int(a[1]), self.int(a[2]), self.int(a[3]));
+				let rows = self.rows(p[0]);
+				format!("(i >= {0} && i < {0} + {1} && j >= {2} && j < {2} + {3} ? {4} : 0.0)", start_x, size_x, start_y, size_y,
+					self.at(p[0], format!("i - {} + (j - {}) * {}", start_x, start_y, rows)))
+			},
+			OPERATOR_RESHAPE => {
+				let index = format!("i + j * {}_rows", self.root);
+				self.at(p[0], index)
+			},
+			OPERATOR_REPLICATEH | OPERATOR_REPLICATEV => {
+				let (rows, cols) = (self.rows(p[0]), self.cols(p[0]));
+				self.at(p[0], format!("i % {0} + (j % {1}) * {0}", rows, cols))
+			},
+			OPERATOR_MINV | OPERATOR_DOT | OPERATOR_SUM_1 | OPERATOR_SUM_2 | OPERATOR_SUM_ALL
+			| OPERATOR_L2_1 | OPERATOR_L2_2 | OPERATOR_L2_ALL | OPERATOR_L1_1 | OPERATOR_L1_2 | OPERATOR_L1_ALL => unreachable!()
+		}
+	}
+}
@@ -0,0 +1,362 @@
+use std::io::{Write, Error};
+use std::result::Result;
+use std::collections::HashMap;
+use core::*;
+
+/// The representation of a matrix used by the entry point of the generated Rust code.
+/// The fields are (rows, columns, data), where the data is stored in column major order.
+pub type RawMatrix = (usize, usize, Vec<f64>);
+
+static HEADING: &'static str =
+"#![allow(dead_code, unused_variables, non_snake_case)]
+
+/// A dense column major matrix
+#[derive(Clone, Debug)]
+struct Matrix {
+	rows: usize,
+	cols: usize,
+	data: Vec<f64>
+}
+
+impl Matrix {
+	fn from_raw(raw: (usize, usize, Vec<f64>)) -> Matrix {
+		assert!(raw.0 * raw.1 == raw.2.len(), \"Invalid dimensions of an input matrix\");
+		Matrix{rows: raw.0, cols: raw.1, data: raw.2}
+	}
+
+	fn into_raw(self) -> (usize, usize, Vec<f64>) {
+		(self.rows, self.cols, self.data)
+	}
+
+	fn scalar(value: f64) -> Matrix {
+		Matrix{rows: 1, cols: 1, data: vec![value]}
+	}
+
+	fn constant(rows: usize, cols: usize, value: f64) -> Matrix {
+		Matrix{rows: rows, cols: cols, data: vec![value; rows * cols]}
+	}
+
+	fn eye(n: usize) -> Matrix {
+		let mut result = Matrix::constant(n, n, 0.0);
+		for i in 0..n {
+			result.data[i + i * n] = 1.0;
+		}
+		result
+	}
+
+	fn get(&self, i: usize, j: usize) -> f64 {
+		self.data[i + j * self.rows]
+	}
+
+	fn index(&self) -> usize {
+		self.data[0] as usize
+	}
+
+	fn map<F: Fn(f64) -> f64>(&self, f: F) -> Matrix {
+		Matrix{rows: self.rows, cols: self.cols, data: self.data.iter().map(|&x| f(x)).collect()}
+	}
+
+	fn zip<F: Fn(f64, f64) -> f64>(&self, other: &Matrix, f: F) -> Matrix {
+		let rows = if self.rows == 1 {other.rows} else {self.rows};
+		let cols = if self.cols == 1 {other.cols} else {self.cols};
+		assert!((other.rows == rows || other.rows == 1) && (other.cols == cols || other.cols == 1),
+			\"Operands can not be broadcast together\");
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for i in 0..rows {
+				let a = self.get(if self.rows == 1 {0} else {i}, if self.cols == 1 {0} else {j});
+				let b = other.get(if other.rows == 1 {0} else {i}, if other.cols == 1 {0} else {j});
+				data.push(f(a, b));
+			}
+		}
+		Matrix{rows: rows, cols: cols, data: data}
+	}
+
+	fn add(&self, other: &Matrix) -> Matrix {
+		self.zip(other, |a, b| a + b)
+	}
+
+	fn mul(&self, other: &Matrix) -> Matrix {
+		self.zip(other, |a, b| a * b)
+	}
+
+	fn dot(&self, other: &Matrix) -> Matrix {
+		if self.rows * self.cols == 1 || other.rows * other.cols == 1 {
+			return self.mul(other)
+		}
+		assert!(self.cols == other.rows, \"Inner dimensions of a matrix product do not agree\");
+		let mut result = Matrix::constant(self.rows, other.cols, 0.0);
+		for j in 0..other.cols {
+			for k in 0..self.cols {
+				let b = other.get(k, j);
+				for i in 0..self.rows {
+					result.data[i + j * self.rows] += self.get(i, k) * b;
+				}
+			}
+		}
+		result
+	}
+
+	fn transpose(&self) -> Matrix {
+		let mut data = Vec::with_capacity(self.data.len());
+		for i in 0..self.rows {
+			for j in 0..self.cols {
+				data.push(self.get(i, j));
+			}
+		}
+		Matrix{rows: self.cols, cols: self.rows, data: data}
+	}
+
+	fn inverse(&self) -> Matrix {
+		assert!(self.rows == self.cols, \"Can not invert a non square matrix\");
+		let n = self.rows;
+		let mut a = self.clone();
+		let mut result = Matrix::eye(n);
+		for k in 0..n {
+			// Partial pivoting
+			let mut pivot = k;
+			for i in k + 1..n {
+				if a.get(i, k).abs() > a.get(pivot, k).abs() {
+					pivot = i;
+				}
+			}
+			for j in 0..n {
+				a.data.swap(k + j * n, pivot + j * n);
+				result.data.swap(k + j * n, pivot + j * n);
+			}
+			let d = a.get(k, k);
+			for j in 0..n {
+				a.data[k + j * n] /= d;
+				result.data[k + j * n] /= d;
+			}
+			for i in 0..n {
+				let f = a.get(i, k);
+				if i != k && f != 0.0 {
+					for j in 0..n {
+						a.data[i + j * n] -= f * a.data[k + j * n];
+						result.data[i + j * n] -= f * result.data[k + j * n];
+					}
+				}
+			}
+		}
+		result
+	}
+
+	fn sum(&self, dim: usize) -> Matrix {
+		match dim {
+			1 => {
+				let mut result = Matrix::constant(1, self.cols, 0.0);
+				for j in 0..self.cols {
+					for i in 0..self.rows {
+						result.data[j] += self.get(i, j);
+					}
+				}
+				result
+			},
+			2 => {
+				let mut result = Matrix::constant(self.rows, 1, 0.0);
+				for j in 0..self.cols {
+					for i in 0..self.rows {
+						result.data[i] += self.get(i, j);
+					}
+				}
+				result
+			},
+			_ => Matrix::scalar(self.data.iter().fold(0.0, |acc, x| acc + x))
+		}
+	}
+
+	fn matrix_diag(&self) -> Matrix {
+		let n = if self.rows < self.cols {self.rows} else {self.cols};
+		Matrix{rows: n, cols: 1, data: (0..n).map(|i| self.get(i, i)).collect()}
+	}
+
+	fn vector_diag(&self) -> Matrix {
+		let n = self.data.len();
+		let mut result = Matrix::constant(n, n, 0.0);
+		for i in 0..n {
+			result.data[i + i * n] = self.data[i];
+		}
+		result
+	}
+
+	fn horzcat(parts: &[&Matrix]) -> Matrix {
+		let rows = parts[0].rows;
+		let mut data = Vec::new();
+		for part in parts.iter() {
+			assert!(part.rows == rows, \"Horizontal concatenation of matrices with different number of rows\");
+			data.extend(part.data.iter().cloned());
+		}
+		Matrix{rows: rows, cols: data.len() / rows, data: data}
+	}
+
+	fn vertcat(parts: &[&Matrix]) -> Matrix {
+		let cols = parts[0].cols;
+		let rows = parts.iter().fold(0, |acc, part| acc + part.rows);
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for part in parts.iter() {
+				assert!(part.cols == cols, \"Vertical concatenation of matrices with different number of columns\");
+				for i in 0..part.rows {
+					data.push(part.get(i, j));
+				}
+			}
+		}
+		Matrix{rows: rows, cols: cols, data: data}
+	}
+
+	fn subindex(&self, start_x: usize, size_x: usize, start_y: usize, size_y: usize) -> Matrix {
+		let mut data = Vec::with_capacity(size_x * size_y);
+		for j in start_y..start_y + size_y {
+			for i in start_x..start_x + size_x {
+				data.push(self.get(i, j));
+			}
+		}
+		Matrix{rows: size_x, cols: size_y, data: data}
+	}
+
+	fn subassign(&self, start_x: usize, size_x: usize, start_y: usize, size_y: usize, rows: usize, cols: usize) -> Matrix {
+		let mut result = Matrix::constant(rows, cols, 0.0);
+		for j in 0..size_y {
+			for i in 0..size_x {
+				result.data[start_x + i + (start_y + j) * rows] = self.get(i, j);
+			}
+		}
+		result
+	}
+
+	fn reshape(&self, rows: usize, cols: usize) -> Matrix {
+		assert!(rows * cols == self.data.len(), \"Reshaping to a different number of elements\");
+		Matrix{rows: rows, cols: cols, data: self.data.clone()}
+	}
+
+	fn replicate(&self, times_x: usize, times_y: usize) -> Matrix {
+		let rows = self.rows * times_x;
+		let cols = self.cols * times_y;
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for i in 0..rows {
+				data.push(self.get(i % self.rows, j % self.cols));
+			}
+		}
+		Matrix{rows: rows, cols: cols, data: data}
+	}
+}
+
+";
+
+/// Writes a dependency free Rust source, which computes all of the outputs of the graph and the gradients of all parameters.
+///
+/// The source contains a small dense `Matrix` type and a `#[no_mangle]` entry point named after the graph,
+/// with a signature `fn(Vec<RawMatrix>) -> Vec<RawMatrix>`, such that it can be loaded via `linking::dynamical_linking_rust`.
+/// The inputs are all parameters and constant inputs in the order they were defined.
+/// The returned values are the outputs of the graph followed by the gradients of the parameters.
+pub fn write_rust(fmt: &mut Write, graph: & ComputeGraph) -> Result<(),Error>{
+	let inputs = super::input_nodes(graph);
+	let (ordering, grads, _) = try!(super::codegen_ordering(graph));
+	let mut vars : HashMap<usize, String> = HashMap::new();
+	try!(write!(fmt, "{}", HEADING));
+	// Function signature
+	try!(write!(fmt, "#[no_mangle]\npub fn {}(inputs: Vec<(usize, usize, Vec<f64>)>) -> Vec<(usize, usize, Vec<f64>)> {{\n", graph.name));
+	try!(write!(fmt, "\tassert!(inputs.len() == {}, \"Expected {} inputs\");\n", inputs.len(), inputs.len()));
+	try!(write!(fmt, "\tlet mut inputs = inputs.into_iter();\n"));
+	for i in inputs.iter() {
+		let name = graph.nodes[*i].as_ref().unwrap().name.clone();
+		try!(write!(fmt, "\tlet {} = Matrix::from_raw(inputs.next().unwrap());\n", name));
+		vars.insert(*i, name);
+	}
+	// Function body
+	for i in ordering.iter() {
+		if vars.contains_key(i) {
+			continue;
+		}
+		let node = graph.nodes[*i].as_ref().unwrap();
+		let var = format!("n{}", i);
+		let expr = match node.node_type {
+			Type::Float(x) => format!("Matrix::scalar({:?})", x),
+			Type::Integer(x) => format!("Matrix::scalar({:?})", x as f64),
+			_ => {
+				let parents = node.op.parents.iter().map(|p| vars[p].clone()).collect::<Vec<String>>();
+				let args = node.op.args.iter().map(|a| vars[a].clone()).collect::<Vec<String>>();
+				match node.op.op_type {
+					OperatorType::Custom(id) => try!(super::custom_code(id, "rust", &parents)),
+					op_type => operation(op_type, &parents, &args)
+				}
+			}
+		};
+		try!(write!(fmt, "\tlet {} = {};\n", var, expr));
+		vars.insert(*i, var);
+	}
+	// Return the outputs and the gradients
+	let results = graph.outputs.iter().chain(grads.iter())
+		.map(|id| format!("{}.clone().into_raw()", vars[id])).collect::<Vec<String>>();
+	write!(fmt, "\tvec![{}]\n}}\n", results.join(", "))
+}
+
+/// Returns the expression computing the result of the operator
+fn operation(op_type: OperatorType, p: &Vec<String>, a: &Vec<String>) -> String {
+	match op_type {
+		OperatorType::Constant(ConstantOperatorType::None) | OperatorType::Custom(_) => unreachable!(),
+		OPERATOR_CONST => format!("{}.clone()", p[0]),
+		OPERATOR_EYE => format!("Matrix::eye({}.index())", p[0]),
+		OPERATOR_SIGN => format!("{}.map(|x| if x > 0.0 {{1.0}} else if x < 0.0 {{-1.0}} else {{0.0}})", p[0]),
+		OPERATOR_SIZE_1 => format!("Matrix::scalar({}.rows as f64)", p[0]),
+		OPERATOR_SIZE_2 => format!("Matrix::scalar({}.cols as f64)", p[0]),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) =>
+			format!("Matrix::scalar({}.data.len() as f64)", p[0]),
+		OPERATOR_ZEROS => format!("Matrix::constant({}.index(), {}.index(), 0.0)", p[0], p[1]),
+		OPERATOR_ONES => format!("Matrix::constant({}.index(), {}.index(), 1.0)", p[0], p[1]),
+		OPERATOR_LT => format!("{}.zip(&{}, |a, b| if a < b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_LTE => format!("{}.zip(&{}, |a, b| if a <= b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_GT => format!("{}.zip(&{}, |a, b| if a > b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_GTE => format!("{}.zip(&{}, |a, b| if a >= b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_EQ => format!("{}.zip(&{}, |a, b| if a == b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_NEQ => format!("{}.zip(&{}, |a, b| if a != b {{1.0}} else {{0.0}})", p[0], p[1]),
+		OPERATOR_NEG => format!("{}.map(|x| -x)", p[0]),
+		OPERATOR_DIV => format!("{}.map(|x| 1.0 / x)", p[0]),
+		OPERATOR_MINV => format!("{}.inverse()", p[0]),
+		OPERATOR_TRANSPOSE => format!("{}.transpose()", p[0]),
+		OPERATOR_MDIAG => format!("{}.matrix_diag()", p[0]),
+		OPERATOR_VDIAG => format!("{}.vector_diag()", p[0]),
+		OPERATOR_COS => format!("{}.map(|x| x.cos())", p[0]),
+		OPERATOR_SIN => format!("{}.map(|x| x.sin())", p[0]),
+		OPERATOR_TAN => format!("{}.map(|x| x.tan())", p[0]),
+		OPERATOR_COSH => format!("{}.map(|x| x.cosh())", p[0]),
+		OPERATOR_SINH => format!("{}.map(|x| x.sinh())", p[0]),
+		OPERATOR_TANH => format!("{}.map(|x| x.tanh())", p[0]),
+		OPERATOR_ABS => format!("{}.map(|x| x.abs())", p[0]),
+		OPERATOR_LOG => format!("{}.map(|x| x.ln())", p[0]),
+		OPERATOR_EXP => format!("{}.map(|x| x.exp())", p[0]),
+		OPERATOR_SQRT => format!("{}.map(|x| x.sqrt())", p[0]),
+		OPERATOR_SQUARE => format!("{}.map(|x| x * x)", p[0]),
+		OPERATOR_SIGM => format!("{}.map(|x| 1.0 / (1.0 + (-x).exp()))", p[0]),
+		OPERATOR_RECT => format!("{}.map(|x| if x > 0.0 {{x}} else {{0.0}})", p[0]),
+		OPERATOR_SUM_1 => format!("{}.sum(1)", p[0]),
+		OPERATOR_SUM_2 => format!("{}.sum(2)", p[0]),
+		OPERATOR_SUM_ALL => format!("{}.sum(0)", p[0]),
+		OPERATOR_L2_1 => format!("{}.map(|x| x * x).sum(1)", p[0]),
+		OPERATOR_L2_2 => format!("{}.map(|x| x * x).sum(2)", p[0]),
+		OPERATOR_L2_ALL => format!("{}.map(|x| x * x).sum(0)", p[0]),
+		OPERATOR_L1_1 => format!("{}.map(|x| x.abs()).sum(1)", p[0]),
+		OPERATOR_L1_2 => format!("{}.map(|x| x.abs()).sum(2)", p[0]),
+		OPERATOR_L1_ALL => format!("{}.map(|x| x.abs()).sum(0)", p[0]),
+		OPERATOR_MAX => format!("{}.zip(&{}, |a, b| a.max(b))", p[0], p[1]),
+		OPERATOR_MIN => format!("{}.zip(&{}, |a, b| a.min(b))", p[0], p[1]),
+		OPERATOR_POW => format!("{}.zip(&{}, |a, b| a.powf(b))", p[0], p[1]),
+		OPERATOR_QUAD => format!("{0}.transpose().dot(&{1}).dot(&{0})", p[0], p[1]),
+		OPERATOR_ADD => p.iter().skip(1).fold(p[0].clone(), |acc, x| format!("{}.add(&{})", acc, x)),
+		OPERATOR_MUL => p.iter().skip(1).fold(p[0].clone(), |acc, x| format!("{}.mul(&{})", acc, x)),
+		OPERATOR_DOT => p.iter().skip(1).fold(p[0].clone(), |acc, x| format!("{}.dot(&{})", acc, x)),
+		OPERATOR_HORZCAT => format!("Matrix::horzcat(&[{}])",
+			p.iter().map(|x| format!("&{}", x)).collect::<Vec<String>>().join(", ")),
+		OPERATOR_VERTCAT => format!("Matrix::vertcat(&[{}])",
+			p.iter().map(|x| format!("&{}", x)).collect::<Vec<String>>().join(", ")),
+		OPERATOR_SUBINDEX => format!("{}.subindex({}.index(), {}.index(), {}.index(), {}.index())",
+			p[0], a[0], a[1], a[2], a[3]),
+		OPERATOR_SUBASSIGN => format!("{}.subassign({}.index(), {}.index(), {}.index(), {}.index(), {}.index(), {}.index())",
+			p[0], a[0], a[1], a[2], a[3], a[4], a[5]),
+		OPERATOR_RESHAPE => format!("{}.reshape({}.index(), {}.index())", p[0], a[0], a[1]),
+		OPERATOR_REPLICATEH => format!("{}.replicate(1, {}.index())", p[0], a[0]),
+		OPERATOR_REPLICATEV => format!("{}.replicate({}.index(), 1)", p[0], a[0])
+	}
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use super::operator::*;
+use super::node::*;
+use super::graph::*;
+use super::registry::*;
+
+/// A dense column major matrix, used as the value of the nodes by the interpreter
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+	/// The number of rows
+	pub rows: usize,
+	/// The number of columns
+	pub cols: usize,
+	/// The elements of the matrix in column major order
+	pub data: Vec<f64>
+}
+
+impl Matrix {
+	/// Creates a new matrix from its elements in column major order
+	pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Self {
+		Matrix{rows: rows, cols: cols, data: data}
+	}
+
+	/// Creates a 1x1 matrix
+	pub fn scalar(value: f64) -> Self {
+		Matrix{rows: 1, cols: 1, data: vec![value]}
+	}
+
+	/// Creates a matrix with all elements equal to `value`
+	pub fn constant(rows: usize, cols: usize, value: f64) -> Self {
+		Matrix{rows: rows, cols: cols, data: vec![value; rows * cols]}
+	}
+
+	/// Creates an identity matrix
+	pub fn eye(n: usize) -> Self {
+		let mut result = Matrix::constant(n, n, 0.0);
+		for i in 0..n {
+			result.data[i + i * n] = 1.0;
+		}
+		result
+	}
+
+	/// Returns the element at row `i` and column `j`
+	pub fn get(&self, i: usize, j: usize) -> f64 {
+		self.data[i + j * self.rows]
+	}
+
+	/// Applies the function to every element
+	pub fn map<F: Fn(f64) -> f64>(&self, f: F) -> Matrix {
+		Matrix{rows: self.rows, cols: self.cols, data: self.data.iter().map(|&x| f(x)).collect()}
+	}
+
+	/// Applies the function elementwise on the two matrices, broadcasting any dimension equal to 1
+	pub fn zip<F: Fn(f64, f64) -> f64>(&self, other: &Matrix, f: F) -> Result<Matrix, String> {
+		let rows = if self.rows == 1 {other.rows} else {self.rows};
+		let cols = if self.cols == 1 {other.cols} else {self.cols};
+		if (other.rows != rows && other.rows != 1) || (other.cols != cols && other.cols != 1) {
+			return Err(format!("Can not broadcast together matrices with dimensions ({}, {}) and ({}, {})",
+				self.rows, self.cols, other.rows, other.cols))
+		}
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for i in 0..rows {
+				let a = self.get(if self.rows == 1 {0} else {i}, if self.cols == 1 {0} else {j});
+				let b = other.get(if other.rows == 1 {0} else {i}, if other.cols == 1 {0} else {j});
+				data.push(f(a, b));
+			}
+		}
+		Ok(Matrix{rows: rows, cols: cols, data: data})
+	}
+
+	/// The matrix product, which is a multiplication when any of the two is a scalar
+	pub fn dot(&self, other: &Matrix) -> Result<Matrix, String> {
+		if self.data.len() == 1 || other.data.len() == 1 {
+			return self.zip(other, |a, b| a * b)
+		}
+		if self.cols != other.rows {
+			return Err(format!("Can not multiply matrices with dimensions ({}, {}) and ({}, {})",
+				self.rows, self.cols, other.rows, other.cols))
+		}
+		let mut result = Matrix::constant(self.rows, other.cols, 0.0);
+		for j in 0..other.cols {
+			for k in 0..self.cols {
+				let b = other.get(k, j);
+				for i in 0..self.rows {
+					result.data[i + j * self.rows] += self.get(i, k) * b;
+				}
+			}
+		}
+		Ok(result)
+	}
+
+	/// The transpose of the matrix
+	pub fn transpose(&self) -> Matrix {
+		let mut data = Vec::with_capacity(self.data.len());
+		for i in 0..self.rows {
+			for j in 0..self.cols {
+				data.push(self.get(i, j));
+			}
+		}
+		Matrix{rows: self.cols, cols: self.rows, data: data}
+	}
+
+	/// The inverse of the matrix, computed by Gauss-Jordan elimination with partial pivoting
+	pub fn inverse(&self) -> Result<Matrix, String> {
+		if self.rows != self.cols {
+			return Err(format!("Can not invert a non square matrix with dimensions ({}, {})", self.rows, self.cols))
+		}
+		let n = self.rows;
+		let mut a = self.clone();
+		let mut result = Matrix::eye(n);
+		for k in 0..n {
+			let mut pivot = k;
+			for i in k + 1..n {
+				if a.get(i, k).abs() > a.get(pivot, k).abs() {
+					pivot = i;
+				}
+			}
+			if a.get(pivot, k) == 0.0 {
+				return Err("Can not invert a singular matrix".to_string())
+			}
+			for j in 0..n {
+				a.data.swap(k + j * n, pivot + j * n);
+				result.data.swap(k + j * n, pivot + j * n);
+			}
+			let d = a.get(k, k);
+			for j in 0..n {
+				a.data[k + j * n] /= d;
+				result.data[k + j * n] /= d;
+			}
+			for i in 0..n {
+				let f = a.get(i, k);
+				if i != k && f != 0.0 {
+					for j in 0..n {
+						a.data[i + j * n] -= f * a.data[k + j * n];
+						result.data[i + j * n] -= f * result.data[k + j * n];
+					}
+				}
+			}
+		}
+		Ok(result)
+	}
+
+	/// The sum of the elements along the dimension
+	pub fn sum(&self, dim: Dimension) -> Matrix {
+		match dim {
+			Dimension::First => {
+				let mut result = Matrix::constant(1, self.cols, 0.0);
+				for j in 0..self.cols {
+					for i in 0..self.rows {
+						result.data[j] += self.get(i, j);
+					}
+				}
+				result
+			},
+			Dimension::Second => {
+				let mut result = Matrix::constant(self.rows, 1, 0.0);
+				for j in 0..self.cols {
+					for i in 0..self.rows {
+						result.data[i] += self.get(i, j);
+					}
+				}
+				result
+			},
+			Dimension::All => Matrix::scalar(self.data.iter().fold(0.0, |acc, x| acc + x))
+		}
+	}
+
+	/// Concatenates the matrices horizontally
+	pub fn horzcat(parts: &[&Matrix]) -> Result<Matrix, String> {
+		let rows = parts[0].rows;
+		let mut data = Vec::new();
+		for part in parts.iter() {
+			if part.rows != rows {
+				return Err(format!("Can not concatenate horizontally matrices with {} and {} rows", rows, part.rows))
+			}
+			data.extend(part.data.iter().cloned());
+		}
+		let cols = parts.iter().fold(0, |acc, part| acc + part.cols);
+		Ok(Matrix{rows: rows, cols: cols, data: data})
+	}
+
+	/// Concatenates the matrices vertically
+	pub fn vertcat(parts: &[&Matrix]) -> Result<Matrix, String> {
+		let cols = parts[0].cols;
+		for part in parts.iter() {
+			if part.cols != cols {
+				return Err(format!("Can not concatenate vertically matrices with {} and {} columns", cols, part.cols))
+			}
+		}
+		let rows = parts.iter().fold(0, |acc, part| acc + part.rows);
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for part in parts.iter() {
+				for i in 0..part.rows {
+					data.push(part.get(i, j));
+				}
+			}
+		}
+		Ok(Matrix{rows: rows, cols: cols, data: data})
+	}
+
+	/// Takes the block of the matrix with the given starting row and column and size
+	pub fn subindex(&self, start_x: usize, size_x: usize, start_y: usize, size_y: usize) -> Result<Matrix, String> {
+		if start_x + size_x > self.rows || start_y + size_y > self.cols {
+			return Err(format!("The block [{}, {}, {}, {}] is outside of a matrix with dimensions ({}, {})",
+				start_x, size_x, start_y, size_y, self.rows, self.cols))
+		}
+		let mut data = Vec::with_capacity(size_x * size_y);
+		for j in start_y..start_y + size_y {
+			for i in start_x..start_x + size_x {
+				data.push(self.get(i, j));
+			}
+		}
+		Ok(Matrix{rows: size_x, cols: size_y, data: data})
+	}
+
+	/// Creates a matrix of zeros with dimensions (rows, cols), whose block with the given starting row and column
+	/// and size is equal to this matrix
+	pub fn subassign(&self, start_x: usize, size_x: usize, start_y: usize, size_y: usize, rows: usize, cols: usize)
+		-> Result<Matrix, String> {
+		if size_x != self.rows || size_y != self.cols || start_x + size_x > rows || start_y + size_y > cols {
+			return Err(format!("Can not assign a matrix with dimensions ({}, {}) to the block [{}, {}, {}, {}] of a matrix with dimensions ({}, {})",
+				self.rows, self.cols, start_x, size_x, start_y, size_y, rows, cols))
+		}
+		let mut result = Matrix::constant(rows, cols, 0.0);
+		for j in 0..size_y {
+			for i in 0..size_x {
+				result.data[start_x + i + (start_y + j) * rows] = self.get(i, j);
+			}
+		}
+		Ok(result)
+	}
+
+	/// Reshapes the matrix, keeping the column major order of the elements
+	pub fn reshape(&self, rows: usize, cols: usize) -> Result<Matrix, String> {
+		if rows * cols != self.data.len() {
+			return Err(format!("Can not reshape a matrix with dimensions ({}, {}) to ({}, {})", self.rows, self.cols, rows, cols))
+		}
+		Ok(Matrix{rows: rows, cols: cols, data: self.data.clone()})
+	}
+
+	/// Replicates the matrix `times_x` times vertically and `times_y` times horizontally
+	pub fn replicate(&self, times_x: usize, times_y: usize) -> Matrix {
+		let rows = self.rows * times_x;
+		let cols = self.cols * times_y;
+		let mut data = Vec::with_capacity(rows * cols);
+		for j in 0..cols {
+			for i in 0..rows {
+				data.push(self.get(i % self.rows, j % self.cols));
+			}
+		}
+		Matrix{rows: rows, cols: cols, data: data}
+	}
+
+	/// Interprets the matrix as an integer argument, such as a size or an index
+	fn index(&self) -> Result<usize, String> {
+		match self.data.len() {
+			1 if self.data[0] >= 0.0 => Ok(self.data[0] as usize),
+			1 => Err(format!("Can not use the negative value {} as an index", self.data[0])),
+			_ => Err(format!("Can not use a matrix with dimensions ({}, {}) as an index", self.rows, self.cols))
+		}
+	}
+}
+
+/// Evaluates all nodes in the ordering of the graph
+///
+/// The values of the `Parameter` and `ConstInput` nodes are taken from `inputs` by their names.
+/// Returns the values of all of the nodes, indexed by their ids.
+/// An error if an input is missing or if any of the operations can not be performed on the values of its ancestors
+pub fn eval(graph: &ComputeGraph, inputs: &HashMap<String, Matrix>) -> Result<HashMap<usize, Matrix>, EvalError> {
+	let mut values : HashMap<usize, Matrix> = HashMap::new();
+	for i in graph.ordering.iter() {
+		let node = try!(graph.get_node(*i));
+		let value = match node.node_type {
+			Type::Float(x) => Matrix::scalar(x),
+			Type::Integer(x) => Matrix::scalar(x as f64),
+			Type::Parameter | Type::ConstInput => {
+				let value = try!(inputs.get(&node.name).ok_or_else(|| EvalError::MissingInput(node.name.clone())));
+				if value.rows * value.cols != value.data.len() {
+					return Err(EvalError::InvalidOperation(*i, format!("The input has dimensions ({}, {}), but {} elements",
+						value.rows, value.cols, value.data.len())))
+				}
+				value.clone()
+			},
+			_ => {
+				let parents = node.op.parents.iter().map(|p| &values[p]).collect::<Vec<&Matrix>>();
+				let args = node.op.args.iter().map(|a| &values[a]).collect::<Vec<&Matrix>>();
+				try!(operation(node.op.op_type, &parents, &args).map_err(|msg| EvalError::InvalidOperation(*i, msg)))
+			}
+		};
+		values.insert(*i, value);
+	}
+	Ok(values)
+}
+
+/// Computes the result of the operator on the values of its parents and arguments
+fn operation(op_type: OperatorType, p: &Vec<&Matrix>, a: &Vec<&Matrix>) -> Result<Matrix, String> {
+	match op_type {
+		OPERATOR_NONE => Err("Can not evaluate an input without a value".to_string()),
+		OperatorType::Custom(id) => match custom_operator(id) {
+			Some(CustomOperator{value: Some(value), ..}) => value(p),
+			Some(op) => Err(format!("The operator {} has no value rule", op.name)),
+			None => Err(format!("No operator is registered with id {}", id))
+		},
+		OPERATOR_CONST => Ok(p[0].clone()),
+		OPERATOR_EYE => Ok(Matrix::eye(try!(p[0].index()))),
+		OPERATOR_SIGN => Ok(p[0].map(|x| if x > 0.0 {1.0} else if x < 0.0 {-1.0} else {0.0})),
+		OPERATOR_SIZE_1 => Ok(Matrix::scalar(p[0].rows as f64)),
+		OPERATOR_SIZE_2 => Ok(Matrix::scalar(p[0].cols as f64)),
+		OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) =>
+			Ok(Matrix::scalar(p[0].data.len() as f64)),
+		OPERATOR_ZEROS => Ok(Matrix::constant(try!(p[0].index()), try!(p[1].index()), 0.0)),
+		OPERATOR_ONES => Ok(Matrix::constant(try!(p[0].index()), try!(p[1].index()), 1.0)),
+		OPERATOR_LT => p[0].zip(p[1], |a, b| if a < b {1.0} else {0.0}),
+		OPERATOR_LTE => p[0].zip(p[1], |a, b| if a <= b {1.0} else {0.0}),
+		OPERATOR_GT => p[0].zip(p[1], |a, b| if a > b {1.0} else {0.0}),
+		OPERATOR_GTE => p[0].zip(p[1], |a, b| if a >= b {1.0} else {0.0}),
+		OPERATOR_EQ => p[0].zip(p[1], |a, b| if a == b {1.0} else {0.0}),
+		OPERATOR_NEQ => p[0].zip(p[1], |a, b| if a != b {1.0} else {0.0}),
+		OPERATOR_NEG => Ok(p[0].map(|x| -x)),
+		OPERATOR_DIV => Ok(p[0].map(|x| 1.0 / x)),
+		OPERATOR_MINV => p[0].inverse(),
+		OPERATOR_TRANSPOSE => Ok(p[0].transpose()),
+		OPERATOR_MDIAG => {
+			let n = if p[0].rows < p[0].cols {p[0].rows} else {p[0].cols};
+			Ok(Matrix::new(n, 1, (0..n).map(|i| p[0].get(i, i)).collect()))
+		},
+		OPERATOR_VDIAG => {
+			let n = p[0].data.len();
+			let mut result = Matrix::constant(n, n, 0.0);
+			for i in 0..n {
+				result.data[i + i * n] = p[0].data[i];
+			}
+			Ok(result)
+		},
+		OPERATOR_COS => Ok(p[0].map(|x| x.cos())),
+		OPERATOR_SIN => Ok(p[0].map(|x| x.sin())),
+		OPERATOR_TAN => Ok(p[0].map(|x| x.tan())),
+		OPERATOR_COSH => Ok(p[0].map(|x| x.cosh())),
+		OPERATOR_SINH => Ok(p[0].map(|x| x.sinh())),
+		OPERATOR_TANH => Ok(p[0].map(|x| x.tanh())),
+		OPERATOR_ABS => Ok(p[0].map(|x| x.abs())),
+		OPERATOR_LOG => Ok(p[0].map(|x| x.ln())),
+		OPERATOR_EXP => Ok(p[0].map(|x| x.exp())),
+		OPERATOR_SQRT => Ok(p[0].map(|x| x.sqrt())),
+		OPERATOR_SQUARE => Ok(p[0].map(|x| x * x)),
+		OPERATOR_SIGM => Ok(p[0].map(|x| 1.0 / (1.0 + (-x).exp()))),
+		OPERATOR_RECT => Ok(p[0].map(|x| if x > 0.0 {x} else {0.0})),
+		OperatorType::Unary(UnaryOperatorType::Sum(dim)) => Ok(p[0].sum(dim)),
+		OperatorType::Unary(UnaryOperatorType::L2(dim)) => Ok(p[0].map(|x| x * x).sum(dim)),
+		OperatorType::Unary(UnaryOperatorType::L1(dim)) => Ok(p[0].map(|x| x.abs()).sum(dim)),
+		OPERATOR_MAX => p[0].zip(p[1], |a, b| a.max(b)),
+		OPERATOR_MIN => p[0].zip(p[1], |a, b| a.min(b)),
+		OPERATOR_POW => p[0].zip(p[1], |a, b| a.powf(b)),
+		OPERATOR_QUAD => p[0].transpose().dot(p[1]).and_then(|x| x.dot(p[0])),
+		OPERATOR_ADD => p.iter().skip(1).fold(Ok(p[0].clone()), |acc, x| acc.and_then(|acc| acc.zip(x, |a, b| a + b))),
+		OPERATOR_MUL => p.iter().skip(1).fold(Ok(p[0].clone()), |acc, x| acc.and_then(|acc| acc.zip(x, |a, b| a * b))),
+		OPERATOR_DOT => p.iter().skip(1).fold(Ok(p[0].clone()), |acc, x| acc.and_then(|acc| acc.dot(x))),
+		OPERATOR_HORZCAT => Matrix::horzcat(p),
+		OPERATOR_VERTCAT => Matrix::vertcat(p),
+		OPERATOR_SUBINDEX => p[0].subindex(try!(a[0].index()), try!(a[1].index()), try!(a[2].index()), try!(a[3].index())),
+		OPERATOR_SUBASSIGN => p[0].subassign(try!(a[0].index()), try!(a[1].index()), try!(a[2].index()),
+			try!(a[3].index()), try!(a[4].index()), try!(a[5].index())),
+		OPERATOR_RESHAPE => p[0].reshape(try!(a[0].index()), try!(a[1].index())),
+		OPERATOR_REPLICATEH => Ok(p[0].replicate(1, try!(a[0].index()))),
+		OPERATOR_REPLICATEV => Ok(p[0].replicate(try!(a[0].index()), 1))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum EvalError {
+	/// No value was given for the input with this name
+	MissingInput(String),
+	/// The operator of the node can not be performed on the values of its ancestors. Contains the id of the node and a description
+	InvalidOperation(usize, String),
+	Graph(GraphError)
+}
+
+impl ::std::fmt::Display for EvalError {
+	fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+		match *self {
+			EvalError::MissingInput(ref name) => write!(f, "MissingInput: No value given for {}", name),
+			EvalError::InvalidOperation(n, ref msg) => write!(f, "InvalidOperation: node {} - {}", n, msg),
+			EvalError::Graph(ref err) => write!(f, "GraphError: {}", err)
+		}
+	}
+}
+
+impl ::std::error::Error for EvalError {
+	fn description(&self) -> &str {
+		match *self {
+			EvalError::MissingInput(_) => "No value was given for an input",
+			EvalError::InvalidOperation(_, _) => "An operator can not be performed on the values of its ancestors",
+			EvalError::Graph(ref err) => err.description()
+		}
+	}
+
+	fn cause(&self) -> Option<&::std::error::Error> {
+		match *self {
+			EvalError::Graph(ref err) => Some(err),
+			_ => None
+		}
+	}
+}
+
+impl ::std::convert::From<GraphError> for EvalError {
+	fn from(err: GraphError) -> EvalError {
+		EvalError::Graph(err)
+	}
+}
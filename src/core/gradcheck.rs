@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use super::graph::*;
+use super::eval::*;
+
+/// The outcome of the gradient check of a single parameter
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientCheck {
+	/// The name of the parameter
+	pub name: String,
+	/// The largest relative error over all elements of the parameter
+	pub max_error: f64,
+	/// The index in column major order of the element with the largest relative error
+	pub worst_element: usize,
+	/// Whether the largest relative error is below the tolerance
+	pub passed: bool
+}
+
+/// Compares the gradients of all parameters with central finite differences
+///
+/// The gradients are taken from the `grad_child` of each parameter, thus the gradient operator must have already been
+/// applied with the first output of the graph as a target, as done by `direct_gradient`. The first output must be a scalar.
+/// Each element of each parameter is perturbed by `eps` in both directions and the relative error between the two
+/// derivatives is computed as `|analytic - numeric| / max(|analytic|, |numeric|, 1)`.
+///
+/// Returns the check of each parameter, which has a gradient, in the order the parameters were defined.
+/// An error if the graph has no outputs, can not be evaluated or the dimensions of a gradient do not match those of its parameter
+pub fn gradcheck(graph: &ComputeGraph, inputs: &HashMap<String, Matrix>, eps: f64, tol: f64)
+	-> Result<Vec<GradientCheck>, EvalError> {
+	let target = try!(graph.outputs.first().cloned().ok_or(GraphError::NoOutputs));
+	let values = try!(eval(graph, inputs));
+	if values[&target].data.len() != 1 {
+		return Err(EvalError::InvalidOperation(target, format!("Can not check the gradient of a target with dimensions ({}, {})",
+			values[&target].rows, values[&target].cols)))
+	}
+	let mut inputs = inputs.clone();
+	let mut checks : Vec<GradientCheck> = Vec::new();
+	let (grads, names) = graph.get_params();
+	for (grad, name) in grads.iter().zip(names.iter()) {
+		let analytic = &values[grad];
+		let original = try!(inputs.get(name).ok_or_else(|| EvalError::MissingInput(name.clone()))).clone();
+		if analytic.rows != original.rows || analytic.cols != original.cols {
+			return Err(EvalError::InvalidOperation(*grad, format!("The gradient has dimensions ({}, {}), but the parameter {} has ({}, {})",
+				analytic.rows, analytic.cols, name, original.rows, original.cols)))
+		}
+		let mut check = GradientCheck{name: name.clone(), max_error: 0.0, worst_element: 0, passed: true};
+		for k in 0..original.data.len() {
+			let mut perturbed = original.clone();
+			perturbed.data[k] = original.data[k] + eps;
+			inputs.insert(name.clone(), perturbed.clone());
+			let plus = try!(eval(graph, &inputs))[&target].data[0];
+			perturbed.data[k] = original.data[k] - eps;
+			inputs.insert(name.clone(), perturbed);
+			let minus = try!(eval(graph, &inputs))[&target].data[0];
+			let numeric = (plus - minus) / (2.0 * eps);
+			let scale = analytic.data[k].abs().max(numeric.abs()).max(1.0);
+			let error = (analytic.data[k] - numeric).abs() / scale;
+			if error > check.max_error {
+				check.max_error = error;
+				check.worst_element = k;
+			}
+		}
+		check.passed = check.max_error < tol;
+		inputs.insert(name.clone(), original);
+		checks.push(check);
+	}
+	Ok(checks)
+}
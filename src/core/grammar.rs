@@ -26,6 +26,12 @@ functionDefinition = (eol / __)* FUNCTION __ outputs: functionReturn __? EQ __?
 					Some(id) => {
 						// println!("Target {} is {}", output, *id);
 						graph.outputs.push(*id);
+						// Name the output node after its variable, unless it is an input
+						if let Ok(node) = graph.get_mut_node(*id) {
+							if node.op.op_type != OPERATOR_NONE {
+								node.name = output.clone();
+							}
+						}
 					},
 					None => {
 						result = result_err!(input, state,
@@ -116,7 +122,7 @@ expression	-> usize = first: e1  second:(__? op:g1 __? var:expression{(op,var)})
 		Ok(ref mut graph) => match second {
 			Some(tuple) => match graph.add_operation(tuple.0, vec![first, tuple.1]) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			},
 			None => Ok(first)
 		},
@@ -138,7 +144,7 @@ e1	-> usize = first: e2 rest:( __? op:g2 __? var:e2 {
 			} else {
 				match graph.add_operation(OPERATOR_NEG, vec![var]) {
 					Ok(var) => Ok(var),
-					Err(err) => result_err!(input, state, format!("{}",err))
+					Err(err) => graph_err!(input, state, start_pos, err)
 				}
 			}
 		},
@@ -157,7 +163,7 @@ e1	-> usize = first: e2 rest:( __? op:g2 __? var:e2 {
 				vars.extend(rest);
 				match graph.add_operation(OPERATOR_ADD, vars) {
 					Ok(var) => Ok(var),
-					Err(err) => result_err!(input, state, format!("{}",err))
+					Err(err) => graph_err!(input, state, start_pos, err)
 				}
 			}
 		},
@@ -179,7 +185,7 @@ e2	-> usize = first: e3 rest:( __? op:g3 __? var:e3  {
 			} else {
 				match graph.add_operation(OPERATOR_DIV, vec![var]) {
 					Ok(var) => Ok(var),
-					Err(err) => result_err!(input, state, format!("{}",err))
+					Err(err) => graph_err!(input, state, start_pos, err)
 				}
 			}
 		},
@@ -198,7 +204,7 @@ e2	-> usize = first: e3 rest:( __? op:g3 __? var:e3  {
 				vars.extend(rest);
 				match graph.add_operation(OPERATOR_MUL, vars) {
 					Ok(var) => Ok(var),
-					Err(err) => result_err!(input, state, format!("{}",err))
+					Err(err) => graph_err!(input, state, start_pos, err)
 				}
 			}
 		},
@@ -218,7 +224,7 @@ e3	-> usize = vars: e4 ++ (__? DOT_PRODUCT __?) {
 			1 => Ok(vars[0]),
 			_ => match graph.add_operation(OPERATOR_DOT, vars) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			}
 		},
 		Err(ref msg) => Err(msg.clone())
@@ -235,7 +241,7 @@ e4	-> usize = m:MINUS? __? var: e5 {
 		Ok(ref mut graph) => match m {
 			Some(_) => match graph.add_operation(OPERATOR_NEG,vec![var]) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			},
 			None => Ok(var)
 		},
@@ -253,7 +259,7 @@ e5  -> usize = first: e6 second: (__? EXP __? var:e6 {var})? {
 		Ok(ref mut graph) => match second{
 			Some(id) => match graph.add_operation(OPERATOR_POW,vec![first,id]) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			},
 			None => Ok(first)
 		},
@@ -271,7 +277,7 @@ e6	-> usize = var: unaryExpression tr: TRANSPOSE? {
 		Ok(ref mut graph) => match tr{
 			Some(_) => match graph.add_operation(OPERATOR_TRANSPOSE,vec![var]) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			},
 			None => Ok(var)
 		},
@@ -309,7 +315,7 @@ arg3:expression __? COMMA __? arg4:expression __? RSBRACE {
 			Some(id) => match graph.add_operation(
 				OPERATOR_SUBINDEX,vec![*id,arg1,arg2,arg3,arg4]) {
 				Ok(var) => Ok(var),
-				Err(err) => result_err!(input, state, format!("{}",err))
+				Err(err) => graph_err!(input, state, start_pos, err)
 			},
 			None => result_err!(input, state, format!("Use of undefined variable \'{}\'", name))
 		},
@@ -331,7 +337,7 @@ varDotFunc -> usize = name:ID DOT func:ID args:paramList {
 				if ComputeGraph::is_function_name(&func) {
 					match graph.string_to_operator(func, newargs) {
 						Ok(var) => Ok(var),
-						Err(err) => result_err!(input, state, format!("{}",err))
+						Err(err) => graph_err!(input, state, start_pos, err)
 					}
 				} else {
 					result_err!(input, state, format!("Use of undefined function \'{}\'", func))
@@ -354,7 +360,7 @@ funcCall -> usize = func:ID args:paramList {
 			if ComputeGraph::is_function_name(&func) {
 				match graph.string_to_operator(func, args) {
 					Ok(var) => Ok(var),
-					Err(err) => result_err!(input, state, format!("{}",err))
+					Err(err) => graph_err!(input, state, start_pos, err)
 				}
 			} else {
 				result_err!(input, state, format!("Use of undefined function \'{}\'", func))
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 // use std::collections::vec_deque::VecDeque;
 use super::operator::*;
 use super::node::*;
+use super::symbolic::*;
+use super::registry::*;
 
 
 
@@ -11,11 +13,12 @@ use super::node::*;
 #[derive(Clone, Debug, PartialEq)]
 pub struct ComputeGraph{
 	counter: usize,
-	grad_level: u8,
 	pub name: String,
 	pub nodes: Vec<Option<ComputeNode>>,
 	pub ordering: Vec<usize>,
 	pub outputs: Vec<usize>,
+	/// The record of each application of the gradient operator, where the i-th entry has created the nodes of `grad_level` i + 1
+	pub levels: Vec<GradientLevel>,
 	// pub variable_table: HashMap<String, usize>,
 	// symbolic_table: Vec<String>,
 	// symbloic_parents: Vec<usize>,
@@ -72,35 +75,38 @@ impl ComputeGraph{
 
 	/// Create a new compute graph for a function with the input name
 	pub fn new() -> Self{
-		return ComputeGraph{name: "main".to_string(), counter: 0, grad_level: 0, nodes: Vec::new(), ordering: Vec::new(), outputs: Vec::new()}
+		return ComputeGraph{name: "main".to_string(), counter: 0, nodes: Vec::new(), ordering: Vec::new(), outputs: Vec::new(),
+			levels: Vec::new()}
 	}
 
 	/// Creates a new `Parameter` variable with the given name, inserts it in the variable table and returns its id
 	pub fn add_parameter(&mut self, name: String) ->  usize {
-		let mut node = ComputeNode::new(0, Type::Parameter, self.grad_level,
+		let mut node = ComputeNode::new(0, Type::Parameter, self.levels.len(),
 			Operator::new(From::from(ConstantOperatorType::None), vec![], vec![]).unwrap());
+		node.dims = (SymPolynomial::symbol(&(name.clone() + "_rows")), SymPolynomial::symbol(&(name.clone() + "_cols")));
 		node.name = name;
 		self.insert_new(node)
 	}
 
 	/// Creates a new `Float` variable and returns its id
 	pub fn add_float(&mut self, value: f64) -> usize{
-		let node = ComputeNode::new(0, Type::Float(value), self.grad_level,
+		let node = ComputeNode::new(0, Type::Float(value), self.levels.len(),
 			Operator::new(From::from(ConstantOperatorType::None), vec![], vec![]).unwrap());
 		self.insert_new(node)
 	}
 
 	/// Creates a new `Integer` variable and returns its id
 	pub fn add_int(&mut self, value: i64) -> usize{
-		let node = ComputeNode::new(0, Type::Integer(value), self.grad_level,
+		let node = ComputeNode::new(0, Type::Integer(value), self.levels.len(),
 			Operator::new(From::from(ConstantOperatorType::None), vec![], vec![]).unwrap());
 		self.insert_new(node)
 	}
 
 	/// Creates a new `ConstInput` variable with the given name, inserts it in the variable table and returns its id
 	pub fn add_const_input(&mut self, name: String) -> usize{
-		let mut node = ComputeNode::new(0, Type::ConstInput, self.grad_level,
+		let mut node = ComputeNode::new(0, Type::ConstInput, self.levels.len(),
 			Operator::new(From::from(ConstantOperatorType::None), vec![], vec![]).unwrap());
+		node.dims = (SymPolynomial::symbol(&(name.clone() + "_rows")), SymPolynomial::symbol(&(name.clone() + "_cols")));
 		node.name = name;
 		self.insert_new(node)
 	}
@@ -116,6 +122,10 @@ impl ComputeGraph{
 			OperatorType::Special(_) =>  {
 				// First argument is the parent all others are the arguments
 				if args.len() > 0 {
+					match try!(self.get_node(args[0])).node_type {
+						Type::Parameter | Type::ParameterDerived => node_type = Type::ParameterDerived,
+						_ => ()
+					}
 					op_p.push(args[0]);
 				}
 				if args.len() > 1 {
@@ -139,16 +149,159 @@ impl ComputeGraph{
 		};
 		// Create the new node
 		let operator = try!(Operator::new(op_type,op_p,op_args));
+		let dims = try!(self.infer_dims(&operator));
 		// Insert the id as a child of all ancestros
 		for i in operator.get_ancestors(){
 			try!(self.get_mut_node(*i)).children.push(id);
 		}
-		let node = ComputeNode::new(0, node_type, self.grad_level, operator);
+		let mut node = ComputeNode::new(0, node_type, self.levels.len(), operator);
+		node.dims = dims;
 		Ok(self.insert_new(node))
 	}
 
+	/// Infers the symbolic dimensions of the result of the operator from the dimensions of its parents.
+	/// Elementwise operators broadcast any dimension equal to 1, and `Dot` with a scalar is a multiplication.
+	/// Returns a `ShapeMismatch` error if the dimensions of the parents are guaranteed to be incompatible.
+	fn infer_dims(&self, op: &Operator) -> Result<Pair<SymPolynomial>, GraphError> {
+		let mut dims : Vec<Pair<SymPolynomial>> = Vec::new();
+		for p in op.parents.iter() {
+			dims.push(try!(self.get_node(*p)).dims.clone());
+		}
+		let one = SymPolynomial::constant(1);
+		try!(self.check_dims(op, &dims));
+		Ok(match op.op_type {
+			OperatorType::Custom(id) => {
+				let custom = try!(self.custom_operator(id));
+				try!((custom.shape)(&dims).map_err(|msg| GraphError::ShapeMismatch(self.counter, op.parents.clone(), msg, None)))
+			},
+			OperatorType::Constant(ConstantOperatorType::None) => (one.clone(), one),
+			OPERATOR_EYE => {
+				let n = try!(self.symbolic_value(op.parents[0]));
+				(n.clone(), n)
+			},
+			OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(_))) => (one.clone(), one),
+			OPERATOR_ZEROS | OPERATOR_ONES =>
+				(try!(self.symbolic_value(op.parents[0])), try!(self.symbolic_value(op.parents[1]))),
+			OPERATOR_TRANSPOSE => (dims[0].1.clone(), dims[0].0.clone()),
+			OPERATOR_MDIAG => (dims[0].0.clone(), one),
+			OPERATOR_VDIAG => {
+				let n = dims[0].0.clone() * dims[0].1.clone();
+				(n.clone(), n)
+			},
+			OperatorType::Unary(UnaryOperatorType::Sum(dim)) | OperatorType::Unary(UnaryOperatorType::L2(dim))
+			| OperatorType::Unary(UnaryOperatorType::L1(dim)) => match dim {
+				Dimension::First => (one, dims[0].1.clone()),
+				Dimension::Second => (dims[0].0.clone(), one),
+				Dimension::All => (one.clone(), one)
+			},
+			OPERATOR_QUAD => (dims[0].1.clone(), dims[0].1.clone()),
+			OPERATOR_DOT => {
+				let first = dims[0].clone();
+				dims.into_iter().skip(1).fold(first, dot_dims)
+			},
+			OPERATOR_HORZCAT => (dims[0].0.clone(), dims.iter().skip(1).fold(dims[0].1.clone(), |acc, x| acc + x.1.clone())),
+			OPERATOR_VERTCAT => (dims.iter().skip(1).fold(dims[0].0.clone(), |acc, x| acc + x.0.clone()), dims[0].1.clone()),
+			OPERATOR_SUBINDEX => (try!(self.symbolic_value(op.args[1])), try!(self.symbolic_value(op.args[3]))),
+			OPERATOR_SUBASSIGN => (try!(self.symbolic_value(op.args[4])), try!(self.symbolic_value(op.args[5]))),
+			OPERATOR_RESHAPE => (try!(self.symbolic_value(op.args[0])), try!(self.symbolic_value(op.args[1]))),
+			OPERATOR_REPLICATEH => (dims[0].0.clone(), dims[0].1.clone() * try!(self.symbolic_value(op.args[0]))),
+			OPERATOR_REPLICATEV => (dims[0].0.clone() * try!(self.symbolic_value(op.args[0])), dims[0].1.clone()),
+			_ => {
+				// Elementwise operators
+				let first = dims[0].clone();
+				dims.into_iter().skip(1).fold(first, |a, b|
+					(if a.0.is_one() {b.0} else {a.0}, if a.1.is_one() {b.1} else {a.1}))
+			}
+		})
+	}
+
+	/// Checks that the dimensions of the parents are compatible with the operator.
+	/// Since the dimensions are symbolic only mismatches which hold for any values of the inputs' dimensions are reported.
+	fn check_dims(&self, op: &Operator, dims: &Vec<Pair<SymPolynomial>>) -> Result<(), GraphError> {
+		let one = SymPolynomial::constant(1);
+		let is_scalar = |d: &Pair<SymPolynomial>| d.0.is_one() && d.1.is_one();
+		let may_be_scalar = |d: &Pair<SymPolynomial>| d.0.may_equal(&one) && d.1.may_equal(&one);
+		let mismatch = |message: String| Err(GraphError::ShapeMismatch(self.counter, op.parents.clone(), message, None));
+		let pair = |d: &Pair<SymPolynomial>| format!("({}, {})", d.0, d.1);
+		match op.op_type {
+			OPERATOR_MINV | OPERATOR_MDIAG => {
+				if !dims[0].0.may_equal(&dims[0].1) {
+					return mismatch(format!("Can not create an operator {} of a non square matrix with dimensions {}",
+						op.op_type, pair(&dims[0])))
+				}
+			},
+			OPERATOR_DOT => {
+				// Scalars are multiplied elementwise, so only the dimensions of the non scalar parents have to agree
+				let mut last : Option<&Pair<SymPolynomial>> = None;
+				for d in dims.iter().filter(|d| !is_scalar(d)) {
+					if let Some(previous) = last {
+						if !previous.1.may_equal(&d.0) && !may_be_scalar(previous) && !may_be_scalar(d) {
+							return mismatch(format!("Can not create an operator {} with parents of dimensions {} and {}",
+								op.op_type, pair(previous), pair(d)))
+						}
+					}
+					last = Some(d);
+				}
+			},
+			OPERATOR_HORZCAT | OPERATOR_VERTCAT => {
+				for d in dims.iter().skip(1) {
+					let (first, other) = if op.op_type == OPERATOR_HORZCAT {(&dims[0].0, &d.0)} else {(&dims[0].1, &d.1)};
+					if !first.may_equal(other) {
+						return mismatch(format!("Can not create an operator {} with parents of dimensions {} and {}",
+							op.op_type, pair(&dims[0]), pair(d)))
+					}
+				}
+			},
+			OperatorType::Constant(ConstantOperatorType::Binary(_)) | OperatorType::Binary(_) | OPERATOR_ADD | OPERATOR_MUL => {
+				// Elementwise operators, where any dimension which can be 1 is broadcasted
+				let compatible = |a: &SymPolynomial, b: &SymPolynomial| a.may_equal(b) || a.may_equal(&one) || b.may_equal(&one);
+				for (i, a) in dims.iter().enumerate() {
+					for b in dims.iter().skip(i + 1) {
+						if !compatible(&a.0, &b.0) || !compatible(&a.1, &b.1) {
+							return mismatch(format!("Can not create an operator {} with parents of dimensions {} and {}",
+								op.op_type, pair(a), pair(b)))
+						}
+					}
+				}
+			},
+			_ => ()
+		}
+		Ok(())
+	}
+
+	/// Returns the symbolic value of an integer node, such as the argument of `Eye` or `Reshape`.
+	/// Values which can not be expressed through the dimensions of the inputs are represented by a symbol named after the node.
+	pub fn symbolic_value(&self, id: usize) -> Result<SymPolynomial, GraphError> {
+		let node = try!(self.get_node(id));
+		match node.node_type {
+			Type::Integer(x) => return Ok(SymPolynomial::constant(x)),
+			Type::Float(x) if x.fract() == 0.0 => return Ok(SymPolynomial::constant(x as i64)),
+			_ => ()
+		}
+		let parents = &node.op.parents;
+		match node.op.op_type {
+			OPERATOR_CONST => self.symbolic_value(parents[0]),
+			OPERATOR_SIZE_1 => Ok(try!(self.get_node(parents[0])).dims.0.clone()),
+			OPERATOR_SIZE_2 => Ok(try!(self.get_node(parents[0])).dims.1.clone()),
+			OperatorType::Constant(ConstantOperatorType::Unary(ConstantUnaryOperatorType::Size(Dimension::All))) => {
+				let dims = &try!(self.get_node(parents[0])).dims;
+				Ok(dims.0.clone() * dims.1.clone())
+			},
+			OPERATOR_NEG => Ok(-try!(self.symbolic_value(parents[0]))),
+			OPERATOR_ADD | OPERATOR_MUL => {
+				let mut value = try!(self.symbolic_value(parents[0]));
+				for p in parents.iter().skip(1) {
+					let other = try!(self.symbolic_value(*p));
+					value = if node.op.op_type == OPERATOR_ADD {value + other} else {value * other};
+				}
+				Ok(value)
+			},
+			_ => Ok(SymPolynomial::symbol(&format!("n{}", id)))
+		}
+	}
+
 	/// Generates an ordering of computation
-	pub fn generate_ordering(&mut self, mut targets: Vec<usize>) -> Result<Vec<usize>,GraphError> {
+	pub fn generate_ordering(&self, mut targets: Vec<usize>) -> Result<Vec<usize>,GraphError> {
 		// The spanning tree of the targets, e.g. all nodes required to compute them
 		let mut spanning_tree = vec![false; self.counter];
 		// Use the targets vector as a stack
@@ -174,39 +327,45 @@ impl ComputeGraph{
 
 	/// Applies a gradient operator to the graph given the target
 	pub fn gradient(&mut self, target: usize) -> Result<(),GraphError>{
-		// Some sensible checks
-		match self.nodes[target]{
-			Some(ref node) => match node.node_type {
-				Type::Parameter | Type::ParameterDerived => (),
-				_ => return Ok(())
-			},
-			None => return Err(GraphError::AccessNoneNode(target))
+		let params = self.nodes.iter().filter_map(|x| x.as_ref()).filter(|x| x.node_type == Type::Parameter)
+			.map(|x| x.id).collect::<Vec<usize>>();
+		self.gradient_wrt(target, &params)
+	}
+
+	/// Applies a gradient operator to the graph given the target, producing only the gradients needed for the listed nodes
+	///
+	/// The listed nodes can be any non constant nodes, including a `ConstInput`, e.g. for an input saliency map.
+	/// Messages are sent only along the branches which reach any of them and the nodes on these are linked to their new
+	/// gradients, while any other node stays linked to its gradient from a previous application. Thus the target can be
+	/// a gradient itself, e.g. the sum of the second order gradient of `w` gives its third order gradient, while the rest
+	/// of the parameters keep their first order ones.
+	/// Each application adds a new level to `levels`, to which all nodes created by it belong.
+	/// An error if any of the listed nodes is constant
+	pub fn gradient_wrt(&mut self, target: usize, wrt: &[usize]) -> Result<(),GraphError>{
+		for i in wrt.iter() {
+			try!(self.check_differentiable(*i));
 		}
-		self.grad_level += 1;
-		// Keeps all gradient messages
-		let mut messages : HashMap<usize, Vec<usize>> = HashMap::new();
 		let ordering = try!(self.generate_ordering(vec![target]));
-		messages.insert(target, vec![self.add_int(1)]);
-		for i in ordering.iter().rev(){
-			// Get all gradient messages and process them
-			let gradient = match messages.remove(&i) {
-				Some(vec) => match vec.len() {
-					0 => return Err(GraphError::NoGradientMessages(*i)),
-					1 => vec[0],
-					_ => try!(self.add_operation(OPERATOR_ADD, vec))
-				},
-				None => continue//return Err(format!("No incoming messages found for node {}", i))
+		let reachable = try!(self.reachable(&ordering, wrt));
+		if !reachable[target] {
+			return Ok(())
+		}
+		self.levels.push(GradientLevel{target: target, wrt: Vec::new()});
+		let gradients = try!(self.reverse_sweep(target, &ordering, &reachable));
+		for i in ordering.iter().rev() {
+			let gradient = match gradients.get(i) {
+				Some(gradient) => *gradient,
+				None => continue
 			};
-			// Connect the gradient info and the parent
+			if wrt.contains(i) {
+				self.levels.last_mut().unwrap().wrt.push(*i);
+			}
+			// Connect the gradient info and the parent, replacing any gradient from a previous application
+			if let Some(old) = try!(self.get_node(*i)).grad_child {
+				try!(self.get_mut_node(old)).grad_parents.retain(|x| x != i);
+			}
 			try!(self.get_mut_node(gradient)).grad_parents.push(*i);
 			try!(self.get_mut_node(*i)).grad_child = Some(gradient);
-			let gradient = 0;
-			// Generate gradient messages and send them to parents
-			let grad_msgs = try!(self.op_gradient(*i, gradient));
-			for (parent, msg) in grad_msgs{
-				let mut mine = messages.entry(parent).or_insert(Vec::new());
-				mine.push(msg);
-			}
 		}
 		// let mut grad_outputs: Vec<usize> = Vec::new();
 		// // Add gradients of the parameters to outptus
@@ -226,72 +385,518 @@ impl ComputeGraph{
 		Ok(())
 	}
 
-	/// Generates gradient messages from the operator to all of its non constant parents.
-	/// Returns a HashMap<parent,msg>
-	fn op_gradient(&mut self, child: usize, grad: usize) -> Result<HashMap<usize,usize>, GraphError>{
-		let mut gradients : HashMap<usize,usize> = HashMap::new();
+	/// Returns an error if a gradient can not be taken with respect to the node, as it is a number or a constant operator
+	fn check_differentiable(&self, index: usize) -> Result<(), GraphError> {
+		let node = try!(self.get_node(index));
+		match (node.op.op_type, node.node_type.clone()) {
+			(OPERATOR_NONE, Type::Float(_)) | (OPERATOR_NONE, Type::Integer(_)) => Err(GraphError::GradientOfConstant(index)),
+			(OPERATOR_NONE, _) => Ok(()),
+			(OperatorType::Constant(_), _) => Err(GraphError::GradientOfConstant(index)),
+			_ => Ok(())
+		}
+	}
+
+	/// Marks, by their ids, the nodes in the ordering which are any of the `wrt` nodes or depend on them
+	/// through a non constant operator. Only these nodes need to receive gradient messages
+	fn reachable(&self, ordering: &Vec<usize>, wrt: &[usize]) -> Result<Vec<bool>, GraphError> {
+		let mut reachable = vec![false; self.counter];
+		for i in ordering.iter() {
+			let op = &try!(self.get_node(*i)).op;
+			reachable[*i] = wrt.contains(i) || match op.op_type {
+				OperatorType::Constant(_) => false,
+				_ => op.parents.iter().any(|p| reachable[*p])
+			};
+		}
+		Ok(reachable)
+	}
+
+	/// Propagates the gradient messages of the target backwards through the ordering, which must contain its ancestors,
+	/// sending messages only to the reachable nodes.
+	/// Returns the gradient of every node receiving a message, without linking them to the nodes
+	fn reverse_sweep(&mut self, target: usize, ordering: &Vec<usize>, reachable: &Vec<bool>)
+		-> Result<HashMap<usize, usize>, GraphError> {
+		// Keeps all gradient messages
+		let mut messages : HashMap<usize, Vec<usize>> = HashMap::new();
+		let mut gradients : HashMap<usize, usize> = HashMap::new();
+		messages.insert(target, vec![self.add_int(1)]);
+		for i in ordering.iter().rev(){
+			// Get all gradient messages and process them
+			let gradient = match messages.remove(&i) {
+				Some(vec) => match vec.len() {
+					0 => return Err(GraphError::NoGradientMessages(*i)),
+					1 => vec[0],
+					_ => try!(self.add_operation(OPERATOR_ADD, vec))
+				},
+				None => continue//return Err(format!("No incoming messages found for node {}", i))
+			};
+			gradients.insert(*i, gradient);
+			// Generate gradient messages and send them to parents
+			let grad_msgs = try!(self.op_gradient(*i, gradient, reachable));
+			for (parent, msg) in grad_msgs{
+				if !reachable[parent] {
+					continue
+				}
+				let mut mine = messages.entry(parent).or_insert(Vec::new());
+				mine.push(msg);
+			}
+		}
+		Ok(gradients)
+	}
+
+	/// Applies a forward mode gradient operator to the graph, given the tangents of some of its nodes
+	///
+	/// The `tangents` map the ids of nodes, usually inputs, to the ids of the nodes representing their directional derivatives.
+	/// For every node in the ordering, which depends on any of them, a node representing its directional derivative is added,
+	/// such that the tangent of a target is its Jacobian-vector product. Applied on a gradient, this gives the R-operator.
+	/// Returns the tangents of all nodes, including the given ones
+	pub fn forward_gradient(&mut self, tangents: HashMap<usize, usize>) -> Result<HashMap<usize, usize>, GraphError> {
+		let ordering = self.ordering.clone();
+		self.forward_sweep(&ordering, tangents)
+	}
+
+	/// Propagates the tangents forward through the ordering, returning the tangents of all nodes
+	fn forward_sweep(&mut self, ordering: &Vec<usize>, mut tangents: HashMap<usize, usize>)
+		-> Result<HashMap<usize, usize>, GraphError> {
+		for i in ordering.iter() {
+			if tangents.contains_key(i) {
+				continue
+			}
+			if let Some(tangent) = try!(self.op_tangent(*i, &tangents)) {
+				tangents.insert(*i, tangent);
+			}
+		}
+		Ok(tangents)
+	}
+
+	/// Generates the tangent of the node from the tangents of its parents.
+	/// Returns `None` if none of its parents has a tangent
+	fn op_tangent(&mut self, child: usize, tangents: &HashMap<usize, usize>) -> Result<Option<usize>, GraphError> {
+		let op = try!(self.get_node(child)).op.clone();
+		let t = op.parents.iter().map(|p| tangents.get(p).cloned()).collect::<Vec<Option<usize>>>();
+		if t.iter().all(|x| x.is_none()) {
+			return Ok(None)
+		}
+		let tangent = match op.op_type {
+			OperatorType::Constant(_) => return Ok(None),
+			OperatorType::Custom(id) => {
+				let custom = try!(self.custom_operator(id));
+				match custom.tangent {
+					Some(tangent) => try!(tangent(self, child, &t)),
+					None => return Err(GraphError::CustomOperator(format!(
+						"The operator {} has only a reverse mode gradient rule", custom.name)))
+				}
+			},
+			OPERATOR_NEG | OPERATOR_TRANSPOSE | OPERATOR_MDIAG | OPERATOR_VDIAG
+			| OperatorType::Unary(UnaryOperatorType::Sum(_)) => try!(self.add_operation(op.op_type, vec![t[0].unwrap()])),
+			OperatorType::Special(_) => {
+				// The arguments are constant, thus the operator is applied directly on the tangent
+				let mut args = vec![t[0].unwrap()];
+				args.extend(op.args.iter().cloned());
+				try!(self.add_operation(op.op_type, args))
+			},
+			OperatorType::Unary(unary) => {
+				let x = op.parents[0];
+				let derivative = match unary {
+					UnaryOperatorType::Div => {
+						let msg = try!(self.add_operation(OPERATOR_SQUARE, vec![x]));
+						let msg = try!(self.add_operation(OPERATOR_DIV, vec![msg]));
+						try!(self.add_operation(OPERATOR_NEG, vec![msg]))
+					},
+					UnaryOperatorType::MatrixInverse => {
+						let msg = try!(self.add_operation(OPERATOR_DOT, vec![child, t[0].unwrap(), child]));
+						return Ok(Some(try!(self.add_operation(OPERATOR_NEG, vec![msg]))))
+					},
+					UnaryOperatorType::Cos => {
+						let msg = try!(self.add_operation(OPERATOR_SIN, vec![x]));
+						try!(self.add_operation(OPERATOR_NEG, vec![msg]))
+					},
+					UnaryOperatorType::Sin => try!(self.add_operation(OPERATOR_COS, vec![x])),
+					UnaryOperatorType::Tan => {
+						let const_1 = self.add_int(1);
+						let msg = try!(self.add_operation(OPERATOR_SQUARE, vec![child]));
+						try!(self.add_operation(OPERATOR_ADD, vec![const_1, msg]))
+					},
+					UnaryOperatorType::CosH => try!(self.add_operation(OPERATOR_SINH, vec![x])),
+					UnaryOperatorType::SinH => try!(self.add_operation(OPERATOR_COSH, vec![x])),
+					UnaryOperatorType::TanH => {
+						let const_1 = self.add_int(1);
+						let msg = try!(self.add_operation(OPERATOR_SQUARE, vec![child]));
+						let msg = try!(self.add_operation(OPERATOR_NEG, vec![msg]));
+						try!(self.add_operation(OPERATOR_ADD, vec![const_1, msg]))
+					},
+					UnaryOperatorType::Abs | UnaryOperatorType::L1(_) => try!(self.add_operation(OPERATOR_SIGN, vec![x])),
+					UnaryOperatorType::Log => try!(self.add_operation(OPERATOR_DIV, vec![x])),
+					UnaryOperatorType::Exp => child,
+					UnaryOperatorType::Sqrt => {
+						let const_2 = self.add_int(2);
+						let msg = try!(self.add_operation(OPERATOR_MUL, vec![const_2, child]));
+						try!(self.add_operation(OPERATOR_DIV, vec![msg]))
+					},
+					UnaryOperatorType::Square | UnaryOperatorType::L2(_) => {
+						let const_2 = self.add_int(2);
+						try!(self.add_operation(OPERATOR_MUL, vec![const_2, x]))
+					},
+					UnaryOperatorType::Sigmoid => {
+						let const_1 = self.add_int(1);
+						let msg = try!(self.add_operation(OPERATOR_NEG, vec![child]));
+						let msg = try!(self.add_operation(OPERATOR_ADD, vec![const_1, msg]));
+						try!(self.add_operation(OPERATOR_MUL, vec![msg, child]))
+					},
+					UnaryOperatorType::Rectifier => {
+						let const_0 = self.add_int(0);
+						try!(self.add_operation(OPERATOR_GT, vec![x, const_0]))
+					},
+					// Handled above
+					_ => unreachable!()
+				};
+				let msg = try!(self.add_operation(OPERATOR_MUL, vec![derivative, t[0].unwrap()]));
+				match unary {
+					// The norms sum the elementwise derivatives
+					UnaryOperatorType::L1(dim) | UnaryOperatorType::L2(dim) =>
+						try!(self.add_operation(OperatorType::Unary(UnaryOperatorType::Sum(dim)), vec![msg])),
+					_ => msg
+				}
+			},
+			OPERATOR_MAX | OPERATOR_MIN => {
+				// The tangent of the parent selected by the operator
+				let mask_type = if op.op_type == OPERATOR_MAX {OPERATOR_GT} else {OPERATOR_LT};
+				let mut msgs : Vec<usize> = Vec::new();
+				for &(k, other) in [(0, 1), (1, 0)].iter() {
+					if let Some(tangent) = t[k] {
+						let mask = try!(self.add_operation(mask_type, vec![op.parents[k], op.parents[other]]));
+						msgs.push(try!(self.add_operation(OPERATOR_MUL, vec![mask, tangent])));
+					}
+				}
+				try!(self.sum_tangents(msgs))
+			},
+			OPERATOR_POW => {
+				let mut msgs : Vec<usize> = Vec::new();
+				if let Some(tangent) = t[0] {
+					let msg = try!(self.add_operation(OPERATOR_DIV, vec![op.parents[0]]));
+					msgs.push(try!(self.add_operation(OPERATOR_MUL, vec![op.parents[1], child, msg, tangent])));
+				}
+				if let Some(tangent) = t[1] {
+					let msg = try!(self.add_operation(OPERATOR_LOG, vec![op.parents[0]]));
+					msgs.push(try!(self.add_operation(OPERATOR_MUL, vec![child, msg, tangent])));
+				}
+				try!(self.sum_tangents(msgs))
+			},
+			OPERATOR_QUAD => {
+				let (a, b) = (op.parents[0], op.parents[1]);
+				let mut msgs : Vec<usize> = Vec::new();
+				if let Some(tangent) = t[0] {
+					let atr = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![a]));
+					let ttr = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![tangent]));
+					msgs.push(try!(self.add_operation(OPERATOR_DOT, vec![ttr, b, a])));
+					msgs.push(try!(self.add_operation(OPERATOR_DOT, vec![atr, b, tangent])));
+				}
+				if let Some(tangent) = t[1] {
+					let atr = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![a]));
+					msgs.push(try!(self.add_operation(OPERATOR_DOT, vec![atr, tangent, a])));
+				}
+				try!(self.sum_tangents(msgs))
+			},
+			OPERATOR_ADD => {
+				let mut msgs = t.iter().filter_map(|x| *x).collect::<Vec<usize>>();
+				// The tangents of parents broadcast by the addition are expanded to the dimensions of the child,
+				// unless one of the tangents is known to have them, as each other parent has its dimensions or is broadcast
+				let mut dims : Vec<Pair<SymPolynomial>> = Vec::new();
+				for p in op.parents.iter() {
+					dims.push(try!(self.get_node(*p)).dims.clone());
+				}
+				let covers = |a: &SymPolynomial, b: &SymPolynomial| a == b || b.is_one();
+				let expanded = dims.iter().zip(t.iter()).any(|(a, tangent)| tangent.is_some()
+					&& dims.iter().all(|b| covers(&a.0, &b.0) && covers(&a.1, &b.1)));
+				if !expanded {
+					let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![child]));
+					let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![child]));
+					msgs.push(try!(self.add_operation(OPERATOR_ZEROS, vec![rows, cols])));
+				}
+				try!(self.sum_tangents(msgs))
+			},
+			OPERATOR_MUL | OPERATOR_DOT => {
+				// The product where the i-th parent is replaced by its tangent
+				let mut msgs : Vec<usize> = Vec::new();
+				for (i, tangent) in t.iter().enumerate() {
+					if let Some(tangent) = *tangent {
+						let mut parents = op.parents.clone();
+						parents[i] = tangent;
+						msgs.push(try!(self.add_operation(op.op_type, parents)));
+					}
+				}
+				try!(self.sum_tangents(msgs))
+			},
+			OPERATOR_HORZCAT | OPERATOR_VERTCAT => {
+				// The parents without a tangent are replaced by zeros
+				let mut parents : Vec<usize> = Vec::new();
+				for (p, tangent) in op.parents.iter().zip(t.iter()) {
+					parents.push(match *tangent {
+						Some(x) => x,
+						None => {
+							let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![*p]));
+							let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![*p]));
+							try!(self.add_operation(OPERATOR_ZEROS, vec![rows, cols]))
+						}
+					});
+				}
+				try!(self.add_operation(op.op_type, parents))
+			}
+		};
+		Ok(Some(tangent))
+	}
+
+	/// Adds together the tangents, unless there is only one of them
+	fn sum_tangents(&mut self, tangents: Vec<usize>) -> Result<usize, GraphError> {
+		match tangents.len() {
+			1 => Ok(tangents[0]),
+			_ => self.add_operation(OPERATOR_ADD, tangents)
+		}
+	}
+
+	/// Adds the Hessian-vector products of the target with respect to all parameters
+	///
+	/// For each parameter `x` a new `ConstInput` named `x_v` with the same dimensions is added. The products are the gradients
+	/// of the inner product `sum(dL/dx * x_v)`, summed over all parameters, where `L` is the target.
+	/// The gradient of the target is taken first, unless it has already been taken. After this the parameters are still
+	/// linked to their first order gradients, while the products are only listed in the returned `HvpInfo`.
+	pub fn hessian_vector_product(&mut self, target: usize) -> Result<HvpInfo, GraphError> {
+		if try!(self.get_node(target)).grad_child.is_none() {
+			try!(self.gradient(target));
+		}
+		let mut info = HvpInfo{params: Vec::new(), vectors: Vec::new(), products: Vec::new()};
+		let mut grads : Vec<usize> = Vec::new();
+		let mut inner : Vec<usize> = Vec::new();
+		for option in self.nodes.clone().iter() {
+			let node = match *option {
+				Some(ref node) if node.node_type == Type::Parameter && node.grad_child.is_some() => node,
+				_ => continue
+			};
+			let grad = node.grad_child.unwrap();
+			let v = self.add_const_input(node.name.clone() + "_v");
+			try!(self.get_mut_node(v)).dims = node.dims.clone();
+			let product = try!(self.add_operation(OPERATOR_MUL, vec![grad, v]));
+			inner.push(try!(self.add_operation(OPERATOR_SUM_ALL, vec![product])));
+			info.params.push(node.id);
+			info.vectors.push(v);
+			grads.push(grad);
+		}
+		if inner.len() == 0 {
+			return Ok(info)
+		}
+		let inner = match inner.len() {
+			1 => inner[0],
+			_ => try!(self.add_operation(OPERATOR_ADD, inner))
+		};
+		try!(self.gradient(inner));
+		// Link the parameters back to their first order gradients
+		for (param, grad) in info.params.iter().zip(grads.iter()) {
+			let product = match try!(self.get_node(*param)).grad_child {
+				// The gradient does not depend on the parameters, thus the product is zero
+				Some(x) if x == *grad => {
+					let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![*param]));
+					let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![*param]));
+					try!(self.add_operation(OPERATOR_ZEROS, vec![rows, cols]))
+				},
+				Some(x) => {
+					try!(self.get_mut_node(x)).grad_parents.retain(|y| y != param);
+					try!(self.get_mut_node(*grad)).grad_parents.push(*param);
+					try!(self.get_mut_node(*param)).grad_child = Some(*grad);
+					x
+				},
+				None => unreachable!()
+			};
+			info.products.push(product);
+		}
+		Ok(info)
+	}
+
+	/// Adds a node representing the Jacobian of the output with respect to `wrt`
+	///
+	/// Both nodes are vectorized in column major order, thus the Jacobian has `numel(output)` rows and `numel(wrt)` columns,
+	/// with the derivative of the i-th element of the output with respect to the j-th element of `wrt` at (i, j).
+	/// The Jacobian is assembled from one reverse sweep per element of the output, or one forward sweep per element of `wrt`,
+	/// whichever is fewer according to the inferred dimensions, which is why at least one of the two sizes must be constant.
+	/// Reverse sweeps are used whenever the output depends on a custom operator without a tangent rule,
+	/// in which case the size of the output must be constant.
+	/// The gradients of the nodes are left unchanged.
+	/// Returns the id of the Jacobian. An error if `wrt` is constant or the sizes needed for the sweeps are not known
+	pub fn jacobian(&mut self, output: usize, wrt: usize) -> Result<usize, GraphError> {
+		try!(self.check_differentiable(wrt));
+		let out_dims = try!(self.get_node(output)).dims.clone();
+		let wrt_dims = try!(self.get_node(wrt)).dims.clone();
+		let out_shape = match (out_dims.0.as_constant(), out_dims.1.as_constant()) {
+			(Some(rows), Some(cols)) => Some((rows, cols)),
+			_ => None
+		};
+		let wrt_shape = match (wrt_dims.0.as_constant(), wrt_dims.1.as_constant()) {
+			(Some(rows), Some(cols)) => Some((rows, cols)),
+			_ => None
+		};
+		let ordering = try!(self.generate_ordering(vec![output]));
+		let dependent = try!(self.reachable(&ordering, &[wrt]));
+		let mut forward = true;
+		for i in ordering.iter().filter(|i| dependent[**i]) {
+			if let OperatorType::Custom(id) = try!(self.get_node(*i)).op.op_type {
+				forward = forward && try!(self.custom_operator(id)).tangent.is_some();
+			}
+		}
+		// Whether to use reverse sweeps, together with the dimensions of the node swept over element by element
+		let (reverse, rows, cols) = match (out_shape, wrt_shape) {
+			(Some((r, c)), Some((wr, wc))) => if r * c <= wr * wc || !forward {(true, r, c)} else {(false, wr, wc)},
+			(Some((r, c)), None) => (true, r, c),
+			(None, Some((r, c))) => match forward {
+				true => (false, r, c),
+				false => return Err(GraphError::NoTangent(output, wrt))
+			},
+			(None, None) => return Err(GraphError::UnknownSize(output, wrt))
+		};
+		let out_numel = try!(self.add_numel(output));
+		let wrt_numel = try!(self.add_numel(wrt));
+		// The Jacobian is zero if the output does not depend on wrt
+		if !dependent[output] {
+			return self.add_operation(OPERATOR_ZEROS, vec![out_numel, wrt_numel])
+		}
+		let const_1 = self.add_int(1);
+		let mut blocks : Vec<usize> = Vec::new();
+		if reverse {
+			self.levels.push(GradientLevel{target: output, wrt: Vec::new()});
+			for k in 0..rows * cols {
+				// Each row is the gradient of a single element of the output
+				let element = match rows * cols {
+					1 => output,
+					_ => {
+						let row = self.add_int(k % rows);
+						let col = self.add_int(k / rows);
+						try!(self.add_operation(OPERATOR_SUBINDEX, vec![output, row, const_1, col, const_1]))
+					}
+				};
+				let element_ordering = try!(self.generate_ordering(vec![element]));
+				let reachable = try!(self.reachable(&element_ordering, &[wrt]));
+				let gradients = try!(self.reverse_sweep(element, &element_ordering, &reachable));
+				let gradient = match gradients.get(&wrt) {
+					Some(gradient) => *gradient,
+					None => try!(self.add_operation(OPERATOR_ZEROS, vec![const_1, wrt_numel]))
+				};
+				blocks.push(match try!(self.get_node(gradient)).dims.0.is_one() {
+					true => gradient,
+					false => try!(self.add_operation(OPERATOR_RESHAPE, vec![gradient, const_1, wrt_numel]))
+				});
+			}
+		} else {
+			let (wrt_rows, wrt_cols) = (self.add_int(rows), self.add_int(cols));
+			for k in 0..rows * cols {
+				// Each column is the tangent of the output along a single element of wrt
+				let row = self.add_int(k % rows);
+				let col = self.add_int(k / rows);
+				let direction = try!(self.add_operation(OPERATOR_SUBASSIGN,
+					vec![const_1, row, const_1, col, const_1, wrt_rows, wrt_cols]));
+				let mut seed : HashMap<usize, usize> = HashMap::new();
+				seed.insert(wrt, direction);
+				let tangents = try!(self.forward_sweep(&ordering, seed));
+				let tangent = match tangents.get(&output) {
+					Some(tangent) => *tangent,
+					None => try!(self.add_operation(OPERATOR_ZEROS, vec![out_numel, const_1]))
+				};
+				blocks.push(match try!(self.get_node(tangent)).dims.1.is_one() {
+					true => tangent,
+					false => try!(self.add_operation(OPERATOR_RESHAPE, vec![tangent, out_numel, const_1]))
+				});
+			}
+		}
+		match blocks.len() {
+			1 => Ok(blocks[0]),
+			_ => self.add_operation(if reverse {OPERATOR_VERTCAT} else {OPERATOR_HORZCAT}, blocks)
+		}
+	}
+
+	/// Adds an integer node with the number of elements of the node
+	fn add_numel(&mut self, node: usize) -> Result<usize, GraphError> {
+		let dims = try!(self.get_node(node)).dims.clone();
+		match (dims.0.clone() * dims.1.clone()).as_constant() {
+			Some(x) => Ok(self.add_int(x)),
+			None => {
+				let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![node]));
+				let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![node]));
+				self.add_operation(OPERATOR_MUL, vec![rows, cols])
+			}
+		}
+	}
+
+	/// Generates gradient messages from the operator to all of its reachable parents.
+	/// Returns a list of (parent, msg), where a parent appearing several times receives a message for each occurrence
+	fn op_gradient(&mut self, child: usize, grad: usize, reachable: &Vec<bool>) -> Result<Vec<(usize,usize)>, GraphError>{
+		let mut gradients : Vec<(usize,usize)> = Vec::new();
 		let op = try!(self.get_node(child)).op.clone();
 		if op.op_type == OperatorType::Constant(ConstantOperatorType::None) {
 			return Ok(gradients)
 		}
 		match op.op_type{
 			OperatorType::Constant(_) => return Err(GraphError::GradientOfConstant(child)),
+			OperatorType::Custom(id) => {
+				let custom = try!(self.custom_operator(id));
+				for (i, p) in op.parents.iter().enumerate() {
+					if reachable[*p] {
+						let msg = try!((custom.gradient)(self, child, grad, i));
+						gradients.push((*p, msg));
+					}
+				}
+			},
 			OPERATOR_NEG => {
 				let msg = try!(self.add_operation(OPERATOR_NEG,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_DIV => {
 				let mut msg = try!(self.add_operation(OPERATOR_SQUARE,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_DIV,vec![msg]));
 				msg = try!(self.add_operation(OPERATOR_NEG,vec![msg]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_MINV => {
 				let mut msg = try!(self.add_operation(OPERATOR_TRANSPOSE,vec![child]));
 				msg = try!(self.add_operation(OPERATOR_DOT,vec![msg,grad,msg]));
 				msg = try!(self.add_operation(OPERATOR_NEG,vec![msg]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_TRANSPOSE => {
 				let msg = try!(self.add_operation(OPERATOR_TRANSPOSE,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_MDIAG => {
 				let msg = try!(self.add_operation(OPERATOR_VDIAG,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_VDIAG => {
 				let msg = try!(self.add_operation(OPERATOR_MDIAG,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_COS => {
 				let mut msg = try!(self.add_operation(OPERATOR_SIN,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_NEG,vec![msg]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SIN => {
 				let mut msg = try!(self.add_operation(OPERATOR_COS,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_TAN => {
 				let mut msg = try!(self.add_operation(OPERATOR_COS,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_SQUARE,vec![msg]));
 				msg = try!(self.add_operation(OPERATOR_DIV,vec![msg]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_COSH => {
 				let mut msg = try!(self.add_operation(OPERATOR_SINH,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SINH => {
 				let mut msg = try!(self.add_operation(OPERATOR_COSH,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_TANH => {
 				let mut msg = try!(self.add_operation(OPERATOR_SQUARE,vec![child]));
@@ -299,59 +904,59 @@ impl ComputeGraph{
 				let const_1 = self.add_int(1);
 				msg = try!(self.add_operation(OPERATOR_ADD,vec![msg,const_1]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_ABS => {
 				let mut msg = try!(self.add_operation(OPERATOR_SIGN,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_LOG => {
 				let mut msg = try!(self.add_operation(OPERATOR_DIV,op.parents.clone()));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_EXP => {
 				let msg = try!(self.add_operation(OPERATOR_MUL,vec![child,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SQRT => {
 				let const_half = self.add_float(0.5);
 				let mut msg = try!(self.add_operation(OPERATOR_DIV,vec![child]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![const_half,msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SQUARE => {
 				let const_2 = self.add_int(2);
 				let msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![const_2,op.parents[0],grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SIGM => {
 				let const_1 = self.add_int(1);
 				let mut msg = try!(self.add_operation(OPERATOR_NEG,vec![child]));
 				msg = try!(self.add_operation(OPERATOR_ADD,vec![const_1,msg]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,child,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_RECT => {
 				let const_0 = self.add_int(0);
 				let mut msg = try!(self.add_operation(
 					OPERATOR_GT,vec![op.parents[0],const_0]));
 				msg = try!(self.add_operation(OPERATOR_MUL,vec![msg,grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SUM_1 => {
 				let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![op.parents[0]]));
 				let msg = try!(self.add_operation(
 					OPERATOR_REPLICATEV,vec![grad,rows]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SUM_2 => {
 				let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![op.parents[0]]));
 				let msg = try!(self.add_operation(
 					OPERATOR_REPLICATEH,vec![grad,cols]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SUM_ALL => {
 				let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![op.parents[0]]));
@@ -360,7 +965,7 @@ impl ComputeGraph{
 					OPERATOR_REPLICATEV,vec![grad,rows]));
 				msg = try!(self.add_operation(
 					OPERATOR_REPLICATEH,vec![msg,cols]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L2_1 => {
 				let const_2 = self.add_int(2);
@@ -369,7 +974,7 @@ impl ComputeGraph{
 					OPERATOR_REPLICATEV,vec![grad,rows]));
 				msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![const_2, op.parents[0], msg]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L2_2 => {
 				let const_2 = self.add_int(2);
@@ -378,13 +983,13 @@ impl ComputeGraph{
 					OPERATOR_REPLICATEH,vec![grad,cols]));
 				msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![const_2, op.parents[0], msg]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L2_ALL => {
 				let const_2 = self.add_int(2);
 				let msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![const_2, op.parents[0], grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L1_1 => {
 				let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![op.parents[0]]));
@@ -393,7 +998,7 @@ impl ComputeGraph{
 				let msg_sign = try!(self.add_operation(OPERATOR_SIGN, vec![op.parents[0]]));
 				msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![msg_sign, msg]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L1_2 => {
 				let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![op.parents[0]]));
@@ -402,67 +1007,49 @@ impl ComputeGraph{
 				let msg_sign = try!(self.add_operation(OPERATOR_SIGN, vec![op.parents[0]]));
 				msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![msg_sign, msg]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_L1_ALL => {
 				let msg = try!(self.add_operation(OPERATOR_SIGN, vec![op.parents[0]]));
 				let msg = try!(self.add_operation(
 					OPERATOR_MUL,vec![msg, grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
-			OPERATOR_MAX => {
-				if try!(self.is_dependable(op.parents[0])){
-					let mut msg = try!(self.add_operation(
-						OPERATOR_NEG,vec![op.parents[1]]));
-					msg = try!(self.add_operation(OPERATOR_ADD,vec![op.parents[0],msg]));
-					msg = try!(self.add_operation(OPERATOR_SIGN, vec![msg]));
-					msg = try!(self.add_operation(OPERATOR_MUL,vec![msg, grad]));
-					gradients.insert(op.parents[0], msg);
-				}
-				if try!(self.is_dependable(op.parents[1])){
-					let mut msg = try!(self.add_operation(
-						OPERATOR_NEG,vec![op.parents[0]]));
-					msg = try!(self.add_operation(OPERATOR_ADD,vec![op.parents[1],msg]));
-					msg = try!(self.add_operation(OPERATOR_SIGN, vec![msg]));
-					msg = try!(self.add_operation(OPERATOR_MUL,vec![msg, grad]));
-					gradients.insert(op.parents[1], msg);
-				}
-			},
-			OPERATOR_MIN => {
-				if try!(self.is_dependable(op.parents[0])){
-					let mut msg = try!(self.add_operation(
-						OPERATOR_NEG,vec![op.parents[0]]));
-					msg = try!(self.add_operation(OPERATOR_ADD,vec![op.parents[1],msg]));
-					msg = try!(self.add_operation(OPERATOR_SIGN, vec![msg]));
+			OPERATOR_MAX | OPERATOR_MIN => {
+				// The gradient flows only to the parent selected by the operator
+				let mask_type = if op.op_type == OPERATOR_MAX {OPERATOR_GT} else {OPERATOR_LT};
+				if reachable[op.parents[0]]{
+					let mut msg = try!(self.add_operation(mask_type, vec![op.parents[0], op.parents[1]]));
 					msg = try!(self.add_operation(OPERATOR_MUL,vec![msg, grad]));
-					gradients.insert(op.parents[0], msg);
+					msg = try!(self.reduce_broadcast(op.parents[0], msg));
+					gradients.push((op.parents[0], msg));
 				}
-				if try!(self.is_dependable(op.parents[1])){
-					let mut msg = try!(self.add_operation(
-						OPERATOR_NEG,vec![op.parents[1]]));
-					msg = try!(self.add_operation(OPERATOR_ADD,vec![op.parents[0],msg]));
-					msg = try!(self.add_operation(OPERATOR_SIGN, vec![msg]));
+				if reachable[op.parents[1]]{
+					let mut msg = try!(self.add_operation(mask_type, vec![op.parents[1], op.parents[0]]));
 					msg = try!(self.add_operation(OPERATOR_MUL,vec![msg, grad]));
-					gradients.insert(op.parents[1], msg);
+					msg = try!(self.reduce_broadcast(op.parents[1], msg));
+					gradients.push((op.parents[1], msg));
 				}
 			},
 			OPERATOR_POW => {
-				if try!(self.is_dependable(op.parents[0])){
+				if reachable[op.parents[0]]{
 					let mut msg = try!(self.add_operation(
 						OPERATOR_DIV,vec![op.parents[0]]));
 					msg = try!(self.add_operation(
 						OPERATOR_MUL,vec![op.parents[1], child, msg, grad]));
-					gradients.insert(op.parents[0], msg);
+					msg = try!(self.reduce_broadcast(op.parents[0], msg));
+					gradients.push((op.parents[0], msg));
 				}
-				if try!(self.is_dependable(op.parents[1])){
+				if reachable[op.parents[1]]{
 					let mut msg = try!(self.add_operation(
 						OPERATOR_LOG,vec![op.parents[0]]));
 					msg = try!(self.add_operation(OPERATOR_MUL,vec![child, msg, grad]));
-					gradients.insert(op.parents[1], msg);
+					msg = try!(self.reduce_broadcast(op.parents[1], msg));
+					gradients.push((op.parents[1], msg));
 				}
 			},
 			OPERATOR_QUAD => {
-				if try!(self.is_dependable(op.parents[0])){
+				if reachable[op.parents[0]]{
 					let ptr = try!(self.add_operation(
 						OPERATOR_TRANSPOSE,vec![op.parents[1]]));
 					let msg_1 = try!(self.add_operation(
@@ -473,20 +1060,21 @@ impl ComputeGraph{
 						OPERATOR_DOT,vec![op.parents[1], op.parents[0], gradtr]));
 					let msg = try!(self.add_operation(
 						OPERATOR_ADD,vec![msg_1, msg_2]));
-					gradients.insert(op.parents[0], msg);
+					gradients.push((op.parents[0], msg));
 				}
-				if try!(self.is_dependable(op.parents[1])){
+				if reachable[op.parents[1]]{
 					let ptr = try!(self.add_operation(
 						OPERATOR_TRANSPOSE,vec![op.parents[0]]));
 						let msg = try!(self.add_operation(
 							OPERATOR_DOT,vec![op.parents[0], grad, ptr]));
-						gradients.insert(op.parents[1], msg);
+						gradients.push((op.parents[1], msg));
 				}
 			},
 			OPERATOR_ADD => {
 				for i in op.parents.iter(){
-					if try!(self.is_dependable(*i)){
-						gradients.insert(*i, grad);
+					if reachable[*i]{
+						let msg = try!(self.reduce_broadcast(*i, grad));
+						gradients.push((*i, msg));
 					}
 				}
 			},
@@ -496,23 +1084,26 @@ impl ComputeGraph{
 					2 => {
 						let p1 = op.parents[0];
 						let p2 = op.parents[1];
-						if try!(self.is_dependable(p1)){
-							let msg = try!(self.add_operation(OPERATOR_MUL,vec![p2, grad]));
-							gradients.insert(p1, msg);
+						if reachable[p1]{
+							let mut msg = try!(self.add_operation(OPERATOR_MUL,vec![p2, grad]));
+							msg = try!(self.reduce_broadcast(p1, msg));
+							gradients.push((p1, msg));
 						}
-						if try!(self.is_dependable(p2)){
-							let msg = try!(self.add_operation(OPERATOR_MUL,vec![p1, grad]));
-							gradients.insert(p2, msg);
+						if reachable[p2]{
+							let mut msg = try!(self.add_operation(OPERATOR_MUL,vec![p1, grad]));
+							msg = try!(self.reduce_broadcast(p2, msg));
+							gradients.push((p2, msg));
 						}
 					},
 					_ => {
 						for i in op.parents.iter(){
-							if try!(self.is_dependable(*i)){
+							if reachable[*i]{
 								let mut msg = try!(self.add_operation(
 									OPERATOR_DIV,vec![*i]));
 								msg = try!(self.add_operation(
 										OPERATOR_MUL,vec![msg, child, grad]));
-								gradients.insert(*i, msg);
+								msg = try!(self.reduce_broadcast(*i, msg));
+								gradients.push((*i, msg));
 							}
 						}
 					}
@@ -520,81 +1111,65 @@ impl ComputeGraph{
 			},
 			OPERATOR_DOT => {
 				let n = op.parents.len();
-				match n {
-					0...1 => return Err(GraphError::Operator(
-						OperatorError::InvalidNumberOfParents(op.op_type,op.parents.len(),2))),
-					_ => {
-						// Left most parent
-						let p1 = op.parents[0];
-						if try!(self.is_dependable(p1)) {
-							let right_msg =	if n == 2 {
-								try!(self.add_operation(OPERATOR_TRANSPOSE, vec![op.parents[1]]))
-							} else {
-								let right_parents = op.parents[1..].to_owned();
-								let msg = try!(self.add_operation(OPERATOR_DOT, right_parents));
-								try!(self.add_operation(OPERATOR_TRANSPOSE, vec![msg]))
-							};
-							let msg = try!(self.add_operation(OPERATOR_DOT,vec![grad, right_msg]));
-							gradients.insert(p1, msg);
-						}
-						// Right most parent
-						let pend = op.parents[n-1];
-						if try!(self.is_dependable(pend)) {
-							let left_msg = if n == 2 {
-								try!(self.add_operation(OPERATOR_TRANSPOSE, vec![op.parents[0]]))
-							} else {
-								let left_parents = op.parents[..n-1].to_owned();
-								let msg = try!(self.add_operation(OPERATOR_DOT, left_parents));
-								try!(self.add_operation(OPERATOR_TRANSPOSE, vec![msg]))
-							};
-							let msg = try!(self.add_operation(OPERATOR_DOT, vec![left_msg, grad]));
-							gradients.insert(pend, msg);
-						}
-						if n > 2 {
-							// Second from left to right
-							let p = op.parents[1];
-							if try!(self.is_dependable(p)) {
-								let left_msg = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![op.parents[0]]));
-								let right_msg = if n == 3 {
-									try!(self.add_operation(OPERATOR_TRANSPOSE, vec![op.parents[2]]))
-								} else {
-									let right_parents = op.parents[2..].to_owned();
-									let msg = try!(self.add_operation(OPERATOR_DOT, right_parents));
-									try!(self.add_operation(OPERATOR_TRANSPOSE, vec![msg]))
-								};
-								let msg = try!(self.add_operation(OPERATOR_DOT, vec![left_msg, grad, right_msg]));
-								gradients.insert(p, msg);
-							}
-						}
-						if n > 3 {
-							// Second from right to left
-							let p = op.parents[n-2];
-							if try!(self.is_dependable(p)) {
-								let right_msg = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![op.parents[n-1]]));
-								let left_parents = op.parents[..n-3].to_owned();
-								let mut left_msg = try!(self.add_operation(OPERATOR_DOT, left_parents));
-								left_msg = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![left_msg]));
-								let msg = try!(self.add_operation(OPERATOR_DOT,vec![left_msg, grad, right_msg]));
-								gradients.insert(p, msg);
-							}
-						}
-						if n > 4 {
-							// Rest
-							for i in 2..n-3{
-								let p = op.parents[i];
-								if try!(self.is_dependable(p)) {
-									let left_parents = op.parents[..i-1].to_owned();
-									let mut left_msg = try!(self.add_operation(OPERATOR_DOT, left_parents));
-									left_msg = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![left_msg]));
-									let right_parents = op.parents[i+1..].to_owned();
-									let mut right_msg = try!(self.add_operation(OPERATOR_DOT, right_parents));
-									right_msg = try!(self.add_operation(OPERATOR_TRANSPOSE, vec![right_msg]));
-									let msg = try!(self.add_operation(OPERATOR_DOT, vec![left_msg, grad, right_msg]));
-									gradients.insert(p, msg);
-								}
-							}
-						}
+				if n < 2 {
+					return Err(GraphError::Operator(
+						OperatorError::InvalidNumberOfParents(op.op_type,op.parents.len(),2)))
+				}
+				// A product with a scalar is an elementwise multiplication, thus the scalars only scale the product of the rest
+				let mut is_scalar : Vec<bool> = Vec::new();
+				for p in op.parents.iter() {
+					let node = try!(self.get_node(*p));
+					is_scalar.push(node.dims.0.is_one() && node.dims.1.is_one());
+				}
+				let scalars = op.parents.iter().zip(is_scalar.iter()).filter(|x| *x.1).map(|x| *x.0).collect::<Vec<usize>>();
+				let matrices = op.parents.iter().zip(is_scalar.iter()).filter(|x| !*x.1).map(|x| *x.0).collect::<Vec<usize>>();
+				for i in 0..n {
+					let p = op.parents[i];
+					if !reachable[p] {
+						continue
 					}
+					let mut rest = op.parents.clone();
+					rest.remove(i);
+					if is_scalar[i] {
+						let rest = match rest.len() {
+							1 => rest[0],
+							_ => try!(self.add_operation(OPERATOR_DOT, rest))
+						};
+						let mut msg = try!(self.add_operation(OPERATOR_MUL, vec![rest, grad]));
+						msg = try!(self.reduce_broadcast(p, msg));
+						gradients.push((p, msg));
+						continue
+					}
+					// The message to the k-th matrix is (M_1 ... M_k-1)^T grad (M_k+1 ... M_m)^T, scaled by the scalars
+					let m = matrices.len();
+					let k = is_scalar[..i].iter().filter(|x| !**x).count();
+					let mut msg_parents : Vec<usize> = Vec::new();
+					if k > 0 {
+						let left = match k {
+							1 => matrices[0],
+							_ => try!(self.add_operation(OPERATOR_DOT, matrices[..k].to_owned()))
+						};
+						msg_parents.push(try!(self.add_operation(OPERATOR_TRANSPOSE, vec![left])));
+					}
+					msg_parents.push(grad);
+					if k < m - 1 {
+						let right = match m - k {
+							2 => matrices[m - 1],
+							_ => try!(self.add_operation(OPERATOR_DOT, matrices[k+1..].to_owned()))
+						};
+						msg_parents.push(try!(self.add_operation(OPERATOR_TRANSPOSE, vec![right])));
+					}
+					let mut msg = match msg_parents.len() {
+						1 => grad,
+						_ => try!(self.add_operation(OPERATOR_DOT, msg_parents))
+					};
+					if scalars.len() > 0 {
+						let mut factors = scalars.clone();
+						factors.push(msg);
+						msg = try!(self.add_operation(OPERATOR_MUL, factors));
+						msg = try!(self.reduce_broadcast(p, msg));
+					}
+					gradients.push((p, msg));
 				}
 			},
 			OPERATOR_HORZCAT => {
@@ -605,7 +1180,7 @@ impl ComputeGraph{
 					_ => {
 						let mut last : usize = n;
 						for (i,p) in op.parents.iter().enumerate().rev(){
-							if try!(self.is_dependable(*p)) {
+							if reachable[*p] {
 								last = i;
 								break;
 							}
@@ -616,9 +1191,9 @@ impl ComputeGraph{
 							let mut accum = self.add_int(0);
 							for p in op.parents.iter().take(last+1) {
 								let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![*p]));
-								if try!(self.is_dependable(*p)) {
+								if reachable[*p] {
 									let msg = try!(self.add_operation(OPERATOR_SUBINDEX, vec![grad,const_0,rows, accum, cols]));
-									gradients.insert(*p, msg);
+									gradients.push((*p, msg));
 								}
 								accum = try!(self.add_operation(OPERATOR_ADD, vec![accum, cols]));
 							}
@@ -634,7 +1209,7 @@ impl ComputeGraph{
 					_ => {
 						let mut last : usize = n;
 						for (i,p) in op.parents.iter().enumerate().rev(){
-							if try!(self.is_dependable(*p)) {
+							if reachable[*p] {
 								last = i;
 								break;
 							}
@@ -645,9 +1220,9 @@ impl ComputeGraph{
 							let mut accum = self.add_int(0);
 							for p in op.parents.iter().take(last+1) {
 								let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![*p]));
-								if try!(self.is_dependable(*p)) {
+								if reachable[*p] {
 									let msg = try!(self.add_operation(OPERATOR_SUBINDEX, vec![grad,accum,rows, const_0, cols]));
-									gradients.insert(*p, msg);
+									gradients.push((*p, msg));
 								}
 								accum = try!(self.add_operation(OPERATOR_ADD, vec![accum, rows]));
 							}
@@ -656,18 +1231,22 @@ impl ComputeGraph{
 				}
 			},
 			OPERATOR_SUBINDEX => {
+				let rows = try!(self.add_operation(OPERATOR_SIZE_1, vec![op.parents[0]]));
+				let cols = try!(self.add_operation(OPERATOR_SIZE_2, vec![op.parents[0]]));
 				let mut new_parents = vec![grad];
-				new_parents.extend(op.parents.iter().skip(1).cloned());
+				new_parents.extend(op.args.iter().cloned());
+				new_parents.push(rows);
+				new_parents.push(cols);
 				let msg = try!(self.add_operation(
 					OPERATOR_SUBASSIGN,new_parents));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_SUBASSIGN => {
 				let mut new_parents = vec![grad];
-				new_parents.extend(op.parents.iter().skip(1).cloned());
+				new_parents.extend(op.args.iter().take(4).cloned());
 				let msg = try!(self.add_operation(
 					OPERATOR_SUBINDEX,new_parents));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_RESHAPE => {
 				let rows = try!(self.add_operation(
@@ -676,22 +1255,36 @@ impl ComputeGraph{
 					OPERATOR_SIZE_2, vec![op.parents[0]]));
 				let msg = try!(self.add_operation(
 					OPERATOR_RESHAPE,vec![grad, rows, cols]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_REPLICATEH => {
 				let msg = try!(self.add_operation(
 					OPERATOR_SUM_2,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			},
 			OPERATOR_REPLICATEV => {
 				let msg = try!(self.add_operation(
 					OPERATOR_SUM_1,vec![grad]));
-				gradients.insert(op.parents[0], msg);
+				gradients.push((op.parents[0], msg));
 			}
 		}
 		Ok(gradients)
 	}
 
+	/// Sums the gradient message along the dimensions in which the parent has been broadcast by an elementwise operator
+	fn reduce_broadcast(&mut self, parent: usize, msg: usize) -> Result<usize, GraphError> {
+		let dims = try!(self.get_node(parent)).dims.clone();
+		let msg_dims = try!(self.get_node(msg)).dims.clone();
+		let mut result = msg;
+		if dims.0.is_one() && !msg_dims.0.is_one() {
+			result = try!(self.add_operation(OPERATOR_SUM_1, vec![result]));
+		}
+		if dims.1.is_one() && !msg_dims.1.is_one() {
+			result = try!(self.add_operation(OPERATOR_SUM_2, vec![result]));
+		}
+		Ok(result)
+	}
+
 	// #[inline(always)]
 	pub fn get_mut_node(&mut self, index: usize) -> Result<&mut ComputeNode, GraphError>{
 		let l = self.nodes.len();
@@ -702,7 +1295,7 @@ impl ComputeGraph{
 	}
 
 	// #[inline(always)]
-	pub fn get_node(&mut self, index: usize) -> Result<& ComputeNode, GraphError>{
+	pub fn get_node(&self, index: usize) -> Result<& ComputeNode, GraphError>{
 		try!(self.nodes.get(index).ok_or_else(
 			|| GraphError::IndexOutOfBounds(index,self.nodes.len())))
 			.as_ref().ok_or_else(
@@ -736,7 +1329,13 @@ impl ComputeGraph{
 		// Extract children
 		let children = try!(self.get_node(old_parent)).children.clone();
 		for child in children.iter(){
-			try!(try!(self.get_mut_node(*child)).op.swap_parent_in_place(old_parent, new_parent));
+			// The child appears once for every occurrence of the parent in its parents or arguments
+			let op = &mut try!(self.get_mut_node(*child)).op;
+			if op.parents.contains(&old_parent) {
+				try!(op.swap_parent_in_place(old_parent, new_parent));
+			} else {
+				try!(op.swap_argument_in_place(old_parent, new_parent));
+			}
 		}
 		// Add all children to the new parent
 		try!(self.get_mut_node(new_parent)).children.extend(children.iter().cloned());
@@ -744,6 +1343,9 @@ impl ComputeGraph{
 	}
 
 	pub fn string_to_operator(&mut self , name: String, args: Vec<usize>) -> Result<usize,GraphError>{
+		if let Some(id) = custom_operator_id(&name) {
+			return self.add_operation(OperatorType::Custom(id), args)
+		}
 		match &name[..]{
 			"const" => Ok(try!(self.add_operation(OPERATOR_CONST, args))),
 			"eye" => Ok(try!(self.add_operation(OPERATOR_EYE, args))),
@@ -895,20 +1497,27 @@ impl ComputeGraph{
 			| "abs"| "log" | "exp" | "sqrt" | "square" | "sigm" | "rect" | "sum" | "l2" | "l1"
 			| "max" | "min" | "pow" | "quad" | "reshape" | "replicateH"| "replicateV"
 			| "horzcat" | "vertcat" | "dot" => true,
-			_ => false
+			_ => custom_operator_id(name).is_some()
 		}
 	}
 
+	/// Returns the registered custom operator with the id.
+	/// An error if there is no such operator
+	fn custom_operator(&self, id: usize) -> Result<CustomOperator, GraphError> {
+		custom_operator(id).ok_or_else(|| GraphError::CustomOperator(format!("No operator is registered with id {}", id)))
+	}
+
+	/// Returns the ids of the gradient nodes of all parameters, which have one, together with the names of these parameters
 	pub fn get_params(&self) -> (Vec<usize>, Vec<String>) {
 		let mut names : Vec<String> = Vec::new();
 		let mut grads : Vec<usize> = Vec::new();
 		for option in self.nodes.iter(){
 			match *option {
 				Some(ref node) => {
-					match node.node_type {
-						Type::Parameter => {
+					match (node.node_type, node.grad_child) {
+						(Type::Parameter, Some(grad)) => {
 							names.push(node.name.clone());
-							grads.push(node.grad_child.unwrap());
+							grads.push(grad);
 						}
 						_ => ()
 					}
@@ -919,6 +1528,125 @@ impl ComputeGraph{
 		(grads, names)
 	}
 
+	/// Checks all of the structural invariants of the graph - the ids of the nodes match their indices,
+	/// the children of each node mirror the parents and arguments of the other nodes, the ordering is a topological
+	/// order of all nodes, the gradient links are consistent in both directions, the `ParameterDerived` type
+	/// is propagated correctly and all outputs exist.
+	///
+	/// Returns a `GraphError::Verification` describing the first invariant which does not hold
+	pub fn verify(&self) -> Result<(), GraphError> {
+		// The children each node should have
+		let mut expected : Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+		for (i, option) in self.nodes.iter().enumerate() {
+			let node = match *option {
+				Some(ref node) => node,
+				None => continue
+			};
+			if node.id != i {
+				return Err(GraphError::Verification(i, format!("The node has id {}", node.id)))
+			}
+			for a in node.op.get_ancestors() {
+				if self.get_node(*a).is_err() {
+					return Err(GraphError::Verification(i, format!("The ancestor {} does not exist", a)))
+				}
+				expected[*a].push(i);
+			}
+			// Check the node type
+			let derived = {
+				let parents : Vec<&usize> = match node.op.op_type {
+					OperatorType::Constant(_) => Vec::new(),
+					OperatorType::Special(_) => node.op.parents.iter().take(1).collect(),
+					_ => node.op.parents.iter().collect()
+				};
+				parents.iter().any(|p| match self.nodes[**p].as_ref().unwrap().node_type {
+					Type::Parameter | Type::ParameterDerived => true,
+					_ => false
+				})
+			};
+			let valid = match (node.op.op_type, node.node_type) {
+				(OPERATOR_NONE, Type::Float(_)) | (OPERATOR_NONE, Type::Integer(_))
+				| (OPERATOR_NONE, Type::ConstInput) | (OPERATOR_NONE, Type::Parameter) => true,
+				(OPERATOR_NONE, _) => false,
+				(_, Type::ParameterDerived) => derived,
+				(_, Type::ConstDerived) => !derived,
+				_ => false
+			};
+			if !valid {
+				return Err(GraphError::Verification(i, format!("The node has an incorrect type {:?}", node.node_type)))
+			}
+			// Check the gradient links
+			if let Some(grad) = node.grad_child {
+				if !try!(self.get_node(grad).map_err(|_| GraphError::Verification(i,
+					format!("The gradient node {} does not exist", grad)))).grad_parents.contains(&i) {
+					return Err(GraphError::Verification(i, format!("The gradient node {} does not list it as a gradient parent", grad)))
+				}
+			}
+			for p in node.grad_parents.iter() {
+				if try!(self.get_node(*p).map_err(|_| GraphError::Verification(i,
+					format!("The gradient parent {} does not exist", p)))).grad_child != Some(i) {
+					return Err(GraphError::Verification(i, format!("The gradient parent {} does not have it as a gradient", p)))
+				}
+			}
+		}
+		for (i, option) in self.nodes.iter().enumerate() {
+			if let Some(ref node) = *option {
+				let mut children = node.children.clone();
+				children.sort();
+				if children != expected[i] {
+					return Err(GraphError::Verification(i, format!("The children are {:?}, but should be {:?}",
+						node.children, expected[i])))
+				}
+			}
+		}
+		// Check the ordering
+		let mut position : Vec<Option<usize>> = vec![None; self.nodes.len()];
+		for (k, i) in self.ordering.iter().enumerate() {
+			if self.get_node(*i).is_err() {
+				return Err(GraphError::Verification(*i, "The node is in the ordering, but does not exist".to_string()))
+			}
+			if position[*i].is_some() {
+				return Err(GraphError::Verification(*i, "The node is more than once in the ordering".to_string()))
+			}
+			for a in self.nodes[*i].as_ref().unwrap().op.get_ancestors() {
+				if position[*a].is_none() {
+					return Err(GraphError::Verification(*i, format!("The node is before its ancestor {} in the ordering", a)))
+				}
+			}
+			position[*i] = Some(k);
+		}
+		for (i, option) in self.nodes.iter().enumerate() {
+			if option.is_some() && position[i].is_none() {
+				return Err(GraphError::Verification(i, "The node is not in the ordering".to_string()))
+			}
+		}
+		for i in self.outputs.iter() {
+			if self.get_node(*i).is_err() {
+				return Err(GraphError::Verification(*i, "The output does not exist".to_string()))
+			}
+		}
+		Ok(())
+	}
+
+}
+
+/// A single application of the gradient operator
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientLevel {
+	/// The id of the target
+	pub target: usize,
+	/// The ids of the requested nodes, which have been linked to the gradients created by this application
+	pub wrt: Vec<usize>
+}
+
+/// The nodes added by `ComputeGraph::hessian_vector_product`, where the i-th entry of each list corresponds to the same parameter
+#[derive(Clone, Debug, PartialEq)]
+pub struct HvpInfo {
+	/// The ids of the parameters
+	pub params: Vec<usize>,
+	/// The ids of the new `ConstInput` vectors multiplying the Hessian
+	pub vectors: Vec<usize>,
+	/// The ids of the Hessian-vector products
+	pub products: Vec<usize>
 }
 
 #[derive(Clone, Debug)]
@@ -929,6 +1657,20 @@ pub enum GraphError {
 	LastHasChildren,
 	GradientOfConstant(usize),
 	NoGradientMessages(usize),
+	/// The dimensions of the parents of a node are incompatible, thus it was not created. Contains the id the node would have had,
+	/// the ids of its parents, a description and the line and column in the source, when the graph was parsed from one
+	ShapeMismatch(usize, Vec<usize>, String, Option<(usize, usize)>),
+	/// A structural invariant of the graph does not hold. Contains the id of the offending node and a description
+	Verification(usize, String),
+	/// The number of elements of neither of the two nodes is known, thus the Jacobian can not be assembled
+	UnknownSize(usize, usize),
+	/// The number of elements of the output is not known, while the Jacobian can not be assembled from forward sweeps,
+	/// as the output depends on a custom operator without a tangent rule
+	NoTangent(usize, usize),
+	/// A custom operator can not be registered or used as requested
+	CustomOperator(String),
+	/// The graph has no outputs, while an output is needed as a target
+	NoOutputs,
 	Operator(OperatorError)
 }
 
@@ -941,6 +1683,13 @@ impl ::std::fmt::Display for GraphError {
 			GraphError::LastHasChildren => write!(f, "Last node already has children"),
 			GraphError::GradientOfConstant(n) => write!(f, "Can not take a gradient with respect to a cosntant node - {}", n),
 			GraphError::NoGradientMessages(n) => write!(f, "No gradient messages found for node {}", n),
+			GraphError::ShapeMismatch(n, ref parents, ref msg, _) => write!(f, "ShapeMismatch: node {} with parents {:?} - {}", n, parents, msg),
+			GraphError::Verification(n, ref msg) => write!(f, "Verification: node {} - {}", n, msg),
+			GraphError::UnknownSize(n, m) => write!(f, "UnknownSize: neither node {} nor node {} has a known number of elements", n, m),
+			GraphError::NoTangent(n, m) => write!(f, "NoTangent: node {} has an unknown number of elements and depends on node {} \
+				through a custom operator without a tangent rule", n, m),
+			GraphError::CustomOperator(ref msg) => write!(f, "CustomOperator: {}", msg),
+			GraphError::NoOutputs => write!(f, "NoOutputs: The graph has no outputs to use as a target"),
             GraphError::Operator(ref err) => write!(f, "OperatorError: {}", err),
         }
     }
@@ -955,6 +1704,12 @@ impl ::std::error::Error for GraphError {
 			GraphError::LastHasChildren => "Last node already has children",
 			GraphError::GradientOfConstant(_) => "Taking gradient with respect to a constant",
 			GraphError::NoGradientMessages(_) => "No gradient messages were send for a required node",
+			GraphError::ShapeMismatch(_, _, _, _) => "The dimensions of the parents of a node are incompatible",
+			GraphError::Verification(_, _) => "A structural invariant of the graph does not hold",
+			GraphError::UnknownSize(_, _) => "The number of elements of the nodes is not known",
+			GraphError::NoTangent(_, _) => "The Jacobian can be assembled neither from reverse nor from forward sweeps",
+			GraphError::CustomOperator(_) => "A custom operator can not be registered or used as requested",
+			GraphError::NoOutputs => "The graph has no outputs",
             GraphError::Operator(ref err) => err.description(),
         }
     }
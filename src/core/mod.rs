@@ -1,11 +1,19 @@
 mod operator;
+mod symbolic;
 mod node;
 mod graph;
 mod parser;
+mod eval;
+mod gradcheck;
+mod registry;
 
 pub use self::parser::metaFile as parseMetaFile;
 pub use self::parser::ParseError;
 
 pub use self::operator::*;
+pub use self::symbolic::*;
 pub use self::node::*;
 pub use self::graph::*;
+pub use self::eval::*;
+pub use self::gradcheck::*;
+pub use self::registry::*;
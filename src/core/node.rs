@@ -1,5 +1,6 @@
 use std::fmt::{Display, Formatter, Error};
 use super::operator::*;
+use super::symbolic::*;
 
 /// Represents the five types any `ComputeNode`
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -32,10 +33,11 @@ pub struct ComputeNode{
 	/// All nodes dependable on this one
 	pub children: Vec<usize>,
 	/// Defines to what gradient level computation this node belongs
-	pub grad_level: u8,
+	pub grad_level: usize,
 	/// Whether the node should be inlined by any of the source code generators
 	pub inline: bool,
-	// dims: Pair<SymPolynomial>,
+	/// The symbolic dimensions of the node - number of rows and columns
+	pub dims: Pair<SymPolynomial>,
 	/// Defines the node which represents `dL/dx`
 	pub grad_child: Option<usize>,
 	/// If this node has `grad_level` more than 0, it means it could represent for some node `dL/dx`, thus this contains a list of all such nodes
@@ -50,20 +52,22 @@ impl Display for ComputeNode{
 			OperatorType::Constant(ConstantOperatorType::None) =>
 				write!(f, concat!("********{}[{}]********\n",
 					"Type:{:?}\n",
+					"Dims:({}, {})\n",
 					"Children:{:?}"),
-					self.name, self.id, self.node_type, self.children),
+					self.name, self.id, self.node_type, self.dims.0, self.dims.1, self.children),
 			operator => write!(f, concat!("********{}[{}]********\n",
 				"Type:{:?}\n",
+				"Dims:({}, {})\n",
 				"Operator: {:?}\n",
 				"Children:{:?}"),
-				self.name, self.id, self.node_type, operator, self.children),
+				self.name, self.id, self.node_type, self.dims.0, self.dims.1, operator, self.children),
 		}
 	}
 }
 
 impl ComputeNode{
 	/// Creates a new empty `ComputeNode`, its name depends on the input type and gradient level
-	pub fn new(id: usize, node_type: Type, grad_level: u8, op: Operator) -> Self{
+	pub fn new(id: usize, node_type: Type, grad_level: usize, op: Operator) -> Self{
 		let name = if grad_level > 0{
 			"AutoGrad".to_string()
 		}
@@ -71,6 +75,7 @@ impl ComputeNode{
 			format!("{:?}", node_type)
 		};
 		ComputeNode{id: id, node_type: node_type, name: name, children: Vec::new(),
-			grad_level: grad_level, inline: false, grad_child: None, grad_parents: Vec::new(), op:op}
+			grad_level: grad_level, inline: false, grad_child: None, grad_parents: Vec::new(), op:op,
+			dims: (SymPolynomial::constant(1), SymPolynomial::constant(1))}
 	}
 }
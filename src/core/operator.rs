@@ -1,5 +1,5 @@
 /// Represents all possible dimensionality arguments, used in the operators
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Dimension{
 	First,
 	Second,
@@ -19,7 +19,7 @@ impl ::std::convert::From<usize> for Dimension {
 /// An enum for operators which take a single parent node and produce a constant
 ///
 /// The operator should have a single parent and no argumetns
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ConstantUnaryOperatorType{
 	/// Transforms the parent node to a constant one, regardless if whether it is dependable on any parameters or not
 	Const,
@@ -34,7 +34,7 @@ pub enum ConstantUnaryOperatorType{
 /// An enum for operators which take two parent nodes and produce a constant
 ///
 /// The operator should have two parents and no arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ConstantBinaryOperatorType {
 	/// Creates a new matrix of zeros with dimensions given by its parents
 	Zeros,
@@ -57,7 +57,7 @@ pub enum ConstantBinaryOperatorType {
 /// An enum for all operators which  produce a constant
 ///
 /// The operator should have either one or two parents and no arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ConstantOperatorType {
 	/// A `ConstantUnaryOperatorType`
 	Unary(ConstantUnaryOperatorType),
@@ -82,7 +82,7 @@ impl ::std::convert::From<ConstantBinaryOperatorType> for ConstantOperatorType {
 /// An enum for operators which take a single parent node.
 ///
 /// The operator should have one parent and no arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum UnaryOperatorType {
 	/// Represents elemtwise -x
 	Neg,
@@ -133,7 +133,7 @@ pub enum UnaryOperatorType {
 /// An enum for operators which take a two parent nodes
 ///
 /// The operator should have two parents and no arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BinaryOperatorType {
 	/// Represents elementwise max(x,y)
 	Max,
@@ -148,7 +148,7 @@ pub enum BinaryOperatorType {
 /// An enum for operators which are applied to several parent nodes
 ///
 /// The operator should have at least two parents and no arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum NaryOperatorType {
 	/// Represents a + b + ... + z
 	Add,
@@ -165,13 +165,13 @@ pub enum NaryOperatorType {
 /// An enum for special operators which take a single parent node.
 ///
 /// The operator should have one parent and several arguments
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SpecialUnaryOperatorType {
 	/// Takes the sub block of the parent node described by the following 4 arguments.
 	/// The operator has the following syntax: (parent, start_x, sizeOfBlockX, start_y, sizeOfBlockY)
 	SubIndex,
-	/// Represents subassignment. This will produce a matrix for which the sub block described by the 4 argument will be equal to the parent node. E.g. this means that the result is a matrix of zeros, whose subblock is equal to the parent.
-	/// The operator has the following syntax: (parent, start_x, sizeOfBlockX, start_y, sizeOfBlockY)
+	/// Represents subassignment. This will produce a matrix for which the sub block described by the first 4 argument will be equal to the parent node. E.g. this means that the result is a matrix of zeros of size (rows, cols), whose subblock is equal to the parent.
+	/// The operator has the following syntax: (parent, start_x, sizeOfBlockX, start_y, sizeOfBlockY, rows, cols)
 	SubAssign,
 	/// Represents reshaping the parent node to a size given by its arguments.
 	/// The operator has the following syntax: (parent, dim_1, dim_2)
@@ -189,7 +189,7 @@ impl SpecialUnaryOperatorType{
 	pub fn required_num_of_args(&self) -> usize {
 		match *self{
 			SpecialUnaryOperatorType::SubIndex => 4,
-			SpecialUnaryOperatorType::SubAssign => 4,
+			SpecialUnaryOperatorType::SubAssign => 6,
 			SpecialUnaryOperatorType::Reshape => 2,
 			SpecialUnaryOperatorType::ReplicateHorz | SpecialUnaryOperatorType::ReplicateVert=> 1,
 		}
@@ -197,7 +197,7 @@ impl SpecialUnaryOperatorType{
 }
 
 /// An enum that represents all supported mathematical operations
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum OperatorType {
 	/// A `ConstantOperatorType`
 	Constant(ConstantOperatorType),
@@ -208,7 +208,9 @@ pub enum OperatorType {
 	/// A `NaryOperatorType`
 	Nary(NaryOperatorType),
 	/// A `SpecialUnaryOperatorType`
-	Special(SpecialUnaryOperatorType)
+	Special(SpecialUnaryOperatorType),
+	/// A user defined operator, given by its id in the registry
+	Custom(usize)
 }
 
 impl ::std::fmt::Display for OperatorType {
@@ -220,7 +222,11 @@ impl ::std::fmt::Display for OperatorType {
 			OperatorType::Unary(op) => write!(f, "{:?}", op),
 			OperatorType::Binary(op) => write!(f, "{:?}", op),
 			OperatorType::Nary(op) => write!(f, "{:?}", op),
-			OperatorType::Special(op) => write!(f, "{:?}", op)
+			OperatorType::Special(op) => write!(f, "{:?}", op),
+			OperatorType::Custom(id) => match super::registry::custom_operator(id) {
+				Some(op) => write!(f, "{}", op.name),
+				None => write!(f, "Custom({})", id)
+			}
 		}
     }
 }
@@ -268,7 +274,7 @@ impl ::std::convert::From<NaryOperatorType> for OperatorType {
 }
 
 /// A struct that captures all supported mathematical operations with their metadata
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Operator {
 		/// The type of this operator
 		pub op_type: OperatorType,
@@ -342,6 +348,15 @@ impl Operator {
 					if args.len() != sp.required_num_of_args() {
 						return Err(OperatorError::InvaludNumberOfArguments(op_type, args.len(), sp.required_num_of_args()))
 					}
+				},
+				OperatorType::Custom(id) => {
+					let arity = super::registry::custom_operator(id).map(|x| x.arity).unwrap_or(parents.len());
+					if parents.len() != arity {
+						return Err(OperatorError::InvalidNumberOfParents(op_type,parents.len(),arity))
+					}
+					if args.len() != 0 {
+						return Err(OperatorError::InvaludNumberOfArguments(op_type,args.len(),0))
+					}
 				}
 			}
 		}
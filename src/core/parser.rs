@@ -9,6 +9,20 @@ macro_rules! result_err {
 		}
 	};
 }
+macro_rules! graph_err {
+	($input:ident , $state:ident , $pos:ident , $err:expr) => {
+		match $err {
+			// Incompatible dimensions are reported at the expression, which tried to create the node
+			GraphError::ShapeMismatch(node, parents, msg, None) => {
+				let (line, col) = pos_to_line($input, $pos);
+				let err = GraphError::ShapeMismatch(node, parents, msg, Some((line, col)));
+				Err(ParseError{line: line, column: col, offset: $pos,
+					expected: HashSet::new(), msg: Some(format!("{}", err))})
+			},
+			err => result_err!($input, $state, format!("{}", err))
+		}
+	};
+}
 use std::collections::{HashMap, HashSet};
 use super::operator::*;
 use super::node::*;
@@ -477,6 +491,11 @@ fn parse_functionDefinition<'input>(input: &'input str,
                                                                                                                                                                                                                                     =>
                                                                                                                                                                                                                                     {
                                                                                                                                                                                                                                         graph.outputs.push(*id);
+                                                                                                                                                                                                                                        if let Ok(node) = graph.get_mut_node(*id) {
+                                                                                                                                                                                                                                            if node.op.op_type != OPERATOR_NONE {
+                                                                                                                                                                                                                                                node.name = output.clone();
+                                                                                                                                                                                                                                            }
+                                                                                                                                                                                                                                        }
                                                                                                                                                                                                                                     }
                                                                                                                                                                                                                                     None
                                                                                                                                                                                                                                     =>
@@ -1830,17 +1849,13 @@ fn parse_expression<'input>(input: &'input str, state: &mut ParseState,
                                                                                     Ok(var),
                                                                                     Err(err)
                                                                                     =>
-                                                                                    result_err!(input
+                                                                                    graph_err!(input
                                                                                                 ,
                                                                                                 state
                                                                                                 ,
-                                                                                                format
-                                                                                                !
-                                                                                                (
-                                                                                                "{}"
+                                                                                                start_pos
                                                                                                 ,
-                                                                                                err
-                                                                                                )),
+                                                                                                err),
                                                                                 },
                                                                                 None
                                                                                 =>
@@ -1996,17 +2011,13 @@ fn parse_e1<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                                                                                     Ok(var),
                                                                                                                                                     Err(err)
                                                                                                                                                     =>
-                                                                                                                                                    result_err!(input
+                                                                                                                                                    graph_err!(input
                                                                                                                                                                 ,
                                                                                                                                                                 state
                                                                                                                                                                 ,
-                                                                                                                                                                format
-                                                                                                                                                                !
-                                                                                                                                                                (
-                                                                                                                                                                "{}"
+                                                                                                                                                                start_pos
                                                                                                                                                                 ,
-                                                                                                                                                                err
-                                                                                                                                                                )),
+                                                                                                                                                                err),
                                                                                                                                                 }
                                                                                                                                             }
                                                                                                                                         }
@@ -2110,17 +2121,13 @@ fn parse_e1<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                         Ok(var),
                                                                                         Err(err)
                                                                                         =>
-                                                                                        result_err!(input
+                                                                                        graph_err!(input
                                                                                                     ,
                                                                                                     state
                                                                                                     ,
-                                                                                                    format
-                                                                                                    !
-                                                                                                    (
-                                                                                                    "{}"
+                                                                                                    start_pos
                                                                                                     ,
-                                                                                                    err
-                                                                                                    )),
+                                                                                                    err),
                                                                                     }
                                                                                 }
                                                                             },
@@ -2274,17 +2281,13 @@ fn parse_e2<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                                                                                     Ok(var),
                                                                                                                                                     Err(err)
                                                                                                                                                     =>
-                                                                                                                                                    result_err!(input
+                                                                                                                                                    graph_err!(input
                                                                                                                                                                 ,
                                                                                                                                                                 state
                                                                                                                                                                 ,
-                                                                                                                                                                format
-                                                                                                                                                                !
-                                                                                                                                                                (
-                                                                                                                                                                "{}"
+                                                                                                                                                                start_pos
                                                                                                                                                                 ,
-                                                                                                                                                                err
-                                                                                                                                                                )),
+                                                                                                                                                                err),
                                                                                                                                                 }
                                                                                                                                             }
                                                                                                                                         }
@@ -2388,17 +2391,13 @@ fn parse_e2<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                         Ok(var),
                                                                                         Err(err)
                                                                                         =>
-                                                                                        result_err!(input
+                                                                                        graph_err!(input
                                                                                                     ,
                                                                                                     state
                                                                                                     ,
-                                                                                                    format
-                                                                                                    !
-                                                                                                    (
-                                                                                                    "{}"
+                                                                                                    start_pos
                                                                                                     ,
-                                                                                                    err
-                                                                                                    )),
+                                                                                                    err),
                                                                                     }
                                                                                 }
                                                                             },
@@ -2548,17 +2547,13 @@ fn parse_e3<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                         Ok(var),
                                                                         Err(err)
                                                                         =>
-                                                                        result_err!(input
+                                                                        graph_err!(input
                                                                                     ,
                                                                                     state
                                                                                     ,
-                                                                                    format
-                                                                                    !
-                                                                                    (
-                                                                                    "{}"
+                                                                                    start_pos
                                                                                     ,
-                                                                                    err
-                                                                                    )),
+                                                                                    err),
                                                                     },
                                                                 },
                                                                 Err(ref msg)
@@ -2647,17 +2642,13 @@ fn parse_e4<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                                 Ok(var),
                                                                                                 Err(err)
                                                                                                 =>
-                                                                                                result_err!(input
+                                                                                                graph_err!(input
                                                                                                             ,
                                                                                                             state
                                                                                                             ,
-                                                                                                            format
-                                                                                                            !
-                                                                                                            (
-                                                                                                            "{}"
+                                                                                                            start_pos
                                                                                                             ,
-                                                                                                            err
-                                                                                                            )),
+                                                                                                            err),
                                                                                             },
                                                                                             None
                                                                                             =>
@@ -2859,17 +2850,13 @@ fn parse_e5<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                     Ok(var),
                                                                                     Err(err)
                                                                                     =>
-                                                                                    result_err!(input
+                                                                                    graph_err!(input
                                                                                                 ,
                                                                                                 state
                                                                                                 ,
-                                                                                                format
-                                                                                                !
-                                                                                                (
-                                                                                                "{}"
+                                                                                                start_pos
                                                                                                 ,
-                                                                                                err
-                                                                                                )),
+                                                                                                err),
                                                                                 },
                                                                                 None
                                                                                 =>
@@ -2961,17 +2948,13 @@ fn parse_e6<'input>(input: &'input str, state: &mut ParseState, pos: usize)
                                                                                     Ok(var),
                                                                                     Err(err)
                                                                                     =>
-                                                                                    result_err!(input
+                                                                                    graph_err!(input
                                                                                                 ,
                                                                                                 state
                                                                                                 ,
-                                                                                                format
-                                                                                                !
-                                                                                                (
-                                                                                                "{}"
+                                                                                                start_pos
                                                                                                 ,
-                                                                                                err
-                                                                                                )),
+                                                                                                err),
                                                                                 },
                                                                                 None
                                                                                 =>
@@ -3557,17 +3540,13 @@ fn parse_indexedVar<'input>(input: &'input str, state: &mut ParseState,
                                                                                                                                                                                                                                                                                     Ok(var),
                                                                                                                                                                                                                                                                                     Err(err)
                                                                                                                                                                                                                                                                                     =>
-                                                                                                                                                                                                                                                                                    result_err!(input
+                                                                                                                                                                                                                                                                                    graph_err!(input
                                                                                                                                                                                                                                                                                                 ,
                                                                                                                                                                                                                                                                                                 state
                                                                                                                                                                                                                                                                                                 ,
-                                                                                                                                                                                                                                                                                                format
-                                                                                                                                                                                                                                                                                                !
-                                                                                                                                                                                                                                                                                                (
-                                                                                                                                                                                                                                                                                                "{}"
+                                                                                                                                                                                                                                                                                                start_pos
                                                                                                                                                                                                                                                                                                 ,
-                                                                                                                                                                                                                                                                                                err
-                                                                                                                                                                                                                                                                                                )),
+                                                                                                                                                                                                                                                                                                err),
                                                                                                                                                                                                                                                                                 },
                                                                                                                                                                                                                                                                                 None
                                                                                                                                                                                                                                                                                 =>
@@ -3774,17 +3753,13 @@ fn parse_varDotFunc<'input>(input: &'input str, state: &mut ParseState,
                                                                                                                     Ok(var),
                                                                                                                     Err(err)
                                                                                                                     =>
-                                                                                                                    result_err!(input
+                                                                                                                    graph_err!(input
                                                                                                                                 ,
                                                                                                                                 state
                                                                                                                                 ,
-                                                                                                                                format
-                                                                                                                                !
-                                                                                                                                (
-                                                                                                                                "{}"
+                                                                                                                                start_pos
                                                                                                                                 ,
-                                                                                                                                err
-                                                                                                                                )),
+                                                                                                                                err),
                                                                                                                 }
                                                                                                             } else {
                                                                                                                 result_err!(input
@@ -3902,17 +3877,13 @@ fn parse_funcCall<'input>(input: &'input str, state: &mut ParseState,
                                                                                         Ok(var),
                                                                                         Err(err)
                                                                                         =>
-                                                                                        result_err!(input
+                                                                                        graph_err!(input
                                                                                                     ,
                                                                                                     state
                                                                                                     ,
-                                                                                                    format
-                                                                                                    !
-                                                                                                    (
-                                                                                                    "{}"
+                                                                                                    start_pos
                                                                                                     ,
-                                                                                                    err
-                                                                                                    )),
+                                                                                                    err),
                                                                                     }
                                                                                 } else {
                                                                                     result_err!(input
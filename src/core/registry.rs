@@ -0,0 +1,103 @@
+use std::sync::{Once, ONCE_INIT, RwLock};
+use std::collections::HashMap;
+use super::operator::*;
+use super::symbolic::*;
+use super::graph::*;
+use super::eval::Matrix;
+
+/// Computes the dimensions of the result of a custom operator from the dimensions of its parents.
+/// Returns a description of the problem if the dimensions are incompatible
+pub type ShapeRule = fn(&Vec<Pair<SymPolynomial>>) -> Result<Pair<SymPolynomial>, String>;
+
+/// Builds the gradient message to a single parent of a custom operator.
+/// The arguments are the graph, the id of the node, the id of its gradient and the position of the parent,
+/// while the result is the id of the message, which must have the dimensions of the parent
+pub type GradientRule = fn(&mut ComputeGraph, usize, usize, usize) -> Result<usize, GraphError>;
+
+/// Builds the tangent of a custom operator in forward mode.
+/// The arguments are the graph, the id of the node and the tangents of its parents, which are `None` for parents without one,
+/// while the result is the id of the tangent, which must have the dimensions of the node
+pub type TangentRule = fn(&mut ComputeGraph, usize, &Vec<Option<usize>>) -> Result<usize, GraphError>;
+
+/// Computes the value of a custom operator from the values of its parents, used by the reference interpreter
+pub type ValueRule = fn(&Vec<&Matrix>) -> Result<Matrix, String>;
+
+/// A user defined operator, which can be called by name from the parsed sources
+#[derive(Clone)]
+pub struct CustomOperator {
+	/// The name under which the operator is called
+	pub name: String,
+	/// The number of parents of the operator
+	pub arity: usize,
+	/// The rule for the dimensions of the result
+	pub shape: ShapeRule,
+	/// The rule for the gradient messages to the parents
+	pub gradient: GradientRule,
+	/// The rule for the tangent in forward mode, if the operator supports it
+	pub tangent: Option<TangentRule>,
+	/// The rule for evaluating the operator, if it can be evaluated by `eval`
+	pub value: Option<ValueRule>,
+	/// The code of the operator for each backend - "rust", "numpy", "matlab", "eigen", "c" or "opencl".
+	/// Each occurrence of `{i}` is replaced by the variable of the i-th parent. For "c" and "opencl"
+	/// the code is a scalar expression evaluated elementwise, thus the result must have the broadcast dimensions of the parents
+	pub code: HashMap<String, String>
+}
+
+impl CustomOperator {
+	/// Creates an operator without a tangent rule, a value rule or any code
+	pub fn new(name: &str, arity: usize, shape: ShapeRule, gradient: GradientRule) -> Self {
+		CustomOperator{name: name.to_string(), arity: arity, shape: shape, gradient: gradient, tangent: None, value: None,
+			code: HashMap::new()}
+	}
+
+	/// Returns the code for the backend with the variables of the parents substituted
+	pub fn code_for(&self, backend: &str, parents: &Vec<String>) -> Option<String> {
+		self.code.get(backend).map(|code| parents.iter().enumerate()
+			.fold(code.clone(), |acc, (i, p)| acc.replace(&format!("{{{}}}", i), p)))
+	}
+}
+
+impl ::std::fmt::Debug for CustomOperator {
+	fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+		write!(f, "CustomOperator {{ name: {:?}, arity: {}, code: {:?} }}", self.name, self.arity, self.code)
+	}
+}
+
+static REGISTRY_INIT: Once = ONCE_INIT;
+static mut REGISTRY: *const RwLock<Vec<CustomOperator>> = 0 as *const RwLock<Vec<CustomOperator>>;
+
+/// Returns the registry shared by all threads. Operators are only ever appended to it and their names are unique,
+/// thus the id of an operator is its position and refers to the same operator on every thread
+fn registry() -> &'static RwLock<Vec<CustomOperator>> {
+	unsafe {
+		REGISTRY_INIT.call_once(|| REGISTRY = Box::into_raw(Box::new(RwLock::new(Vec::new()))));
+		&*REGISTRY
+	}
+}
+
+/// Registers the operator for the whole process, such that sources parsed afterwards on any thread can call it.
+/// Returns the `OperatorType` of the operator.
+/// An error if the name is already used by a built in or a registered function
+pub fn register_operator(op: CustomOperator) -> Result<OperatorType, GraphError> {
+	let taken = format!("The name {} is already used by another function", op.name);
+	if ComputeGraph::is_function_name(&op.name) {
+		return Err(GraphError::CustomOperator(taken))
+	}
+	let mut registry = registry().write().unwrap();
+	// Another thread may have registered the name in the meantime
+	if registry.iter().any(|x| x.name == op.name) {
+		return Err(GraphError::CustomOperator(taken))
+	}
+	registry.push(op);
+	Ok(OperatorType::Custom(registry.len() - 1))
+}
+
+/// Returns the registered operator with the id
+pub fn custom_operator(id: usize) -> Option<CustomOperator> {
+	registry().read().unwrap().get(id).cloned()
+}
+
+/// Returns the id of the registered operator with the name
+pub fn custom_operator_id(name: &str) -> Option<usize> {
+	registry().read().unwrap().iter().position(|x| x.name == name)
+}
@@ -0,0 +1,222 @@
+use std::fmt::{Display, Formatter, Error};
+use std::ops::{Add, Sub, Mul, Neg};
+
+/// A pair of values, used for representing the two dimensions of a node
+pub type Pair<T> = (T, T);
+
+/// The dimensions of the matrix product of two nodes, where a product with a scalar is a multiplication
+pub fn dot_dims(a: Pair<SymPolynomial>, b: Pair<SymPolynomial>) -> Pair<SymPolynomial> {
+	if a.0.is_one() && a.1.is_one() {
+		b
+	} else if b.0.is_one() && b.1.is_one() {
+		a
+	} else {
+		(a.0, b.1)
+	}
+}
+
+/// A product of symbols, each raised to a positive power. The symbols are kept sorted by name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SymMonomial {
+	pub powers: Vec<(String, u32)>
+}
+
+impl SymMonomial {
+	/// The empty product, e.g. the monomial of a constant term
+	pub fn one() -> Self {
+		SymMonomial{powers: Vec::new()}
+	}
+
+	/// Returns the product of the two monomials
+	fn product(&self, other: &SymMonomial) -> SymMonomial {
+		let mut powers = self.powers.clone();
+		for &(ref symbol, power) in other.powers.iter() {
+			match powers.iter().position(|x| x.0 == *symbol) {
+				Some(index) => powers[index].1 += power,
+				None => powers.push((symbol.clone(), power))
+			}
+		}
+		powers.sort();
+		SymMonomial{powers: powers}
+	}
+}
+
+impl Display for SymMonomial {
+	fn fmt(&self, f : &mut Formatter) -> Result<(), Error> {
+		let factors = self.powers.iter().map(|&(ref symbol, power)| match power {
+			1 => symbol.clone(),
+			_ => format!("{}^{}", symbol, power)
+		}).collect::<Vec<String>>();
+		write!(f, "{}", factors.join("*"))
+	}
+}
+
+/// A polynomial with integer coefficients over named symbols, used for representing the dimensions of nodes.
+/// The terms are kept sorted and never have a zero coefficient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymPolynomial {
+	pub terms: Vec<(SymMonomial, i64)>
+}
+
+impl SymPolynomial {
+	/// Creates a constant polynomial
+	pub fn constant(value: i64) -> Self {
+		SymPolynomial::from_terms(vec![(SymMonomial::one(), value)])
+	}
+
+	/// Creates a polynomial consisting of a single symbol
+	pub fn symbol(name: &str) -> Self {
+		SymPolynomial{terms: vec![(SymMonomial{powers: vec![(name.to_string(), 1)]}, 1)]}
+	}
+
+	/// Sorts the terms, merges equal monomials and removes any zero coefficients
+	fn from_terms(mut terms: Vec<(SymMonomial, i64)>) -> Self {
+		terms.sort_by(|a, b| a.0.cmp(&b.0));
+		let mut result : Vec<(SymMonomial, i64)> = Vec::new();
+		for (monomial, coefficient) in terms.into_iter() {
+			let merged = match result.last_mut() {
+				Some(last) if last.0 == monomial => {last.1 += coefficient; true},
+				_ => false
+			};
+			if !merged {
+				result.push((monomial, coefficient));
+			}
+		}
+		SymPolynomial{terms: result.into_iter().filter(|x| x.1 != 0).collect()}
+	}
+
+	/// If the polynomial does not depend on any symbol returns its value
+	pub fn as_constant(&self) -> Option<i64> {
+		match self.terms.len() {
+			0 => Some(0),
+			1 if self.terms[0].0.powers.len() == 0 => Some(self.terms[0].1),
+			_ => None
+		}
+	}
+
+	/// If the polynomial is a single symbol returns its name
+	pub fn as_symbol(&self) -> Option<&str> {
+		match self.terms.len() {
+			1 if self.terms[0].1 == 1 && self.terms[0].0.powers.len() == 1 && self.terms[0].0.powers[0].1 == 1 =>
+				Some(&self.terms[0].0.powers[0].0),
+			_ => None
+		}
+	}
+
+	/// Checks if the polynomial is the constant one
+	pub fn is_one(&self) -> bool {
+		self.as_constant() == Some(1)
+	}
+
+	/// Returns a lower bound of the polynomial, assuming every symbol is at least 1 as all dimensions are.
+	/// If any of the coefficients of a non constant term is negative no bound is returned.
+	pub fn lower_bound(&self) -> Option<i64> {
+		match self.terms.iter().any(|x| x.1 < 0 && x.0.powers.len() > 0) {
+			true => None,
+			false => Some(self.terms.iter().fold(0, |acc, x| acc + x.1))
+		}
+	}
+
+	/// Checks if the two polynomials can be equal for some positive values of the symbols.
+	/// This is conservative - it returns `false` only when they are guaranteed to differ.
+	pub fn may_equal(&self, other: &SymPolynomial) -> bool {
+		let difference = self.clone() - other.clone();
+		match difference.as_constant() {
+			Some(x) => x == 0,
+			None => difference.lower_bound().unwrap_or(0) <= 0 && (-difference).lower_bound().unwrap_or(0) <= 0
+		}
+	}
+
+	/// Returns all symbols the polynomial depends on
+	pub fn symbols(&self) -> Vec<String> {
+		let mut symbols : Vec<String> = Vec::new();
+		for &(ref monomial, _) in self.terms.iter() {
+			for &(ref symbol, _) in monomial.powers.iter() {
+				if !symbols.contains(symbol) {
+					symbols.push(symbol.clone());
+				}
+			}
+		}
+		symbols
+	}
+
+	/// Replaces every occurrence of the symbol with the given polynomial
+	pub fn substitute(&self, symbol: &str, value: &SymPolynomial) -> SymPolynomial {
+		let mut result = SymPolynomial::constant(0);
+		for &(ref monomial, coefficient) in self.terms.iter() {
+			let mut term = SymPolynomial::constant(coefficient);
+			for &(ref name, power) in monomial.powers.iter() {
+				let factor = if name == symbol {value.clone()} else {SymPolynomial::symbol(name)};
+				for _ in 0..power {
+					term = term * factor.clone();
+				}
+			}
+			result = result + term;
+		}
+		result
+	}
+}
+
+impl Add for SymPolynomial {
+	type Output = SymPolynomial;
+	fn add(self, other: SymPolynomial) -> SymPolynomial {
+		let mut terms = self.terms;
+		terms.extend(other.terms.into_iter());
+		SymPolynomial::from_terms(terms)
+	}
+}
+
+impl Sub for SymPolynomial {
+	type Output = SymPolynomial;
+	fn sub(self, other: SymPolynomial) -> SymPolynomial {
+		self + (-other)
+	}
+}
+
+impl Mul for SymPolynomial {
+	type Output = SymPolynomial;
+	fn mul(self, other: SymPolynomial) -> SymPolynomial {
+		let mut terms : Vec<(SymMonomial, i64)> = Vec::new();
+		for &(ref a, x) in self.terms.iter() {
+			for &(ref b, y) in other.terms.iter() {
+				terms.push((a.product(b), x * y));
+			}
+		}
+		SymPolynomial::from_terms(terms)
+	}
+}
+
+impl Neg for SymPolynomial {
+	type Output = SymPolynomial;
+	fn neg(self) -> SymPolynomial {
+		SymPolynomial{terms: self.terms.into_iter().map(|(m, c)| (m, -c)).collect()}
+	}
+}
+
+impl Display for SymPolynomial {
+	fn fmt(&self, f : &mut Formatter) -> Result<(), Error> {
+		if self.terms.len() == 0 {
+			return write!(f, "0")
+		}
+		// The constant term is always first in the ordering, but it is printed last
+		let terms = self.terms.iter().skip_while(|x| x.0.powers.len() == 0)
+			.chain(self.terms.iter().take_while(|x| x.0.powers.len() == 0));
+		for (i, &(ref monomial, coefficient)) in terms.enumerate() {
+			let sign = match (i, coefficient < 0) {
+				(0, true) => "-",
+				(0, false) => "",
+				(_, true) => " - ",
+				(_, false) => " + "
+			};
+			let value = coefficient.abs();
+			if monomial.powers.len() == 0 {
+				try!(write!(f, "{}{}", sign, value));
+			} else if value == 1 {
+				try!(write!(f, "{}{}", sign, monomial));
+			} else {
+				try!(write!(f, "{}{}*{}", sign, value, monomial));
+			}
+		}
+		Ok(())
+	}
+}
@@ -27,6 +27,17 @@ pub fn print_graph(graph: &core::ComputeGraph, directory: &mut std::path::PathBu
 	Ok(())
 }
 
+/// Writes the source code generated by `writer` for the graph to the file `directory/file_name`
+pub fn write_source(graph: &core::ComputeGraph, directory: &mut std::path::PathBuf, file_name: &String,
+	writer: fn(&mut std::io::Write, &core::ComputeGraph) -> Result<(), std::io::Error>) -> Result<(), ProgramError>{
+	directory.push(file_name);
+	let file = try!(std::fs::File::create(directory.as_path()));
+	let mut buffer = std::io::BufWriter::new(&file);
+	try!(writer(&mut buffer as &mut std::io::Write, graph));
+	directory.pop();
+	Ok(())
+}
+
 #[derive(Debug)]
 pub enum ProgramError {
 	Io(std::io::Error),
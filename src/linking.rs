@@ -68,6 +68,19 @@ pub fn dynamical_linking_rust<P,R>(source: &str, file_name: &str, location: &mut
     Ok(DLFunction{lib: lib, func: func})
 }
 
+/// Generates the Rust source code of the graph via `codegen::write_rust` and links it via `dynamical_linking_rust`.
+///
+/// The source file and the dynamic library are named after the graph, as is the loaded function.
+/// The function takes as inputs all parameters and constant inputs of the graph in the order they were defined,
+/// and returns the outputs of the graph followed by the gradients of the parameters.
+pub fn dynamical_linking_graph(graph: &::core::ComputeGraph, location: &mut ::std::path::PathBuf)
+    -> Result<DLFunction<Vec<::codegen::RawMatrix>, Vec<::codegen::RawMatrix>>,DynamicLinkingError>{
+    let mut source : Vec<u8> = Vec::new();
+    try!(::codegen::write_rust(&mut source, graph));
+    let source = String::from_utf8(source).unwrap();
+    dynamical_linking_rust(&source, &graph.name, location, &graph.name)
+}
+
 #[derive(Debug)]
 pub enum DynamicLinkingError{
     Io(::std::io::Error),
@@ -5,18 +5,27 @@ extern crate docopt;
 static USAGE: &'static str = "Meta Diff
 
 Usage:
-meta_diff <source>
+meta_diff [-O <level>] [--log-exp] [--verbose] <source>
 meta_diff --help
 meta_diff --version
 
 Options:
 -h --help     Show this usage message.
 -v --version  Show the version and exit.
+-O <level>    Optimization level - 0 for none, 1 for a single sweep of the passes, 2 for running them until
+              a fixed point is reached [default: 1].
+--log-exp     Also rewrite logarithms of products and products of exponentials, which is valid only if
+              the operands of the logarithms are positive.
+--verbose     Print the statistics of each optimization pass to stderr.
 ";
 
+#[allow(non_snake_case)]
 #[derive(RustcDecodable, Debug)]
 struct Args {
 	arg_source: String,
+	flag_O: usize,
+	flag_log_exp: bool,
+	flag_verbose: bool,
 	flag_version: bool
 }
 
@@ -30,7 +39,7 @@ fn main() {
 		Ok(_) => (),
 		Err(err) => {
 			use std::io::Write;
-			writeln!(&mut std::io::stderr(), "{}", err).unwrap();
+			let _ = writeln!(&mut std::io::stderr(), "{}", err);
 			std::process::exit(1);
 		}
 	}
@@ -58,17 +67,32 @@ fn main_proxy(args: Args) ->  Result<(), meta_diff::ProgramError> {
 	try!(meta_diff::print_graph(&graph, &mut directory, &file_noextension));
 	// Gradient
 	try!(graph.direct_gradient());
+	// Optimization
+	let mut pipeline = match args.flag_log_exp {
+		true => meta_diff::optimization::Pipeline::with_log_exp(args.flag_O, meta_diff::optimization::LogExpRewrites::all()),
+		false => meta_diff::optimization::Pipeline::with_level(args.flag_O)
+	};
+	try!(pipeline.run(&mut graph));
+	if args.flag_verbose {
+		for stats in pipeline.stats.iter() {
+			try!(writeln!(&mut std::io::stderr(), "{}: modified the graph on {} out of {} runs, removing {} nodes",
+				stats.name, stats.modifications, stats.runs, stats.nodes_removed));
+		}
+	}
 	try!(meta_diff::print_graph(&graph, &mut directory, &(file_noextension.clone() + "_grad")));
+	// Generated sources
+	try!(meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".m"), meta_diff::codegen::write_matlab));
+	try!(meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".hpp"), meta_diff::codegen::write_eigen));
+	try!(meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".rs"), meta_diff::codegen::write_rust));
+	try!(meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".py"), meta_diff::codegen::write_numpy));
+	try!(meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".cl"), meta_diff::codegen::write_opencl));
+	// The C backend requires all dimensions to be known from the input dimensions
+	if let Err(err) = meta_diff::write_source(&graph, &mut directory, &(graph.name.clone() + ".c"), meta_diff::codegen::write_c) {
+		try!(writeln!(&mut std::io::stderr(), "Skipping C code generation: {}", err));
+	}
 	// Hessian-vector product
-	let (ids, names) = graph.get_params();
-	let vs = ids.iter().zip(names.iter()).map(|(id, name)| {
-		let v = graph.add_const_input(name.clone() + &"_v".to_string());
-		let op = meta_diff::core::OperatorType::Nary(meta_diff::core::NaryOperatorType::Mul);
-		graph.add_operation(op,vec![*id, v]).unwrap()
-	}).collect::<Vec<usize>>();
-	let target = try!(graph.add_operation(
-		meta_diff::core::OperatorType::Nary(meta_diff::core::NaryOperatorType::Add),vs));
-	try!(graph.gradient(target));
+	let target = try!(graph.outputs.first().cloned().ok_or(meta_diff::core::GraphError::NoOutputs));
+	try!(graph.hessian_vector_product(target));
 	try!(meta_diff::print_graph(&graph, &mut directory, &(file_noextension.clone() + "_hess")));
 	Ok(())
 }
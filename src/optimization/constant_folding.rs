@@ -604,13 +604,12 @@ fn single_fold(graph: &mut ComputeGraph, old: usize) -> Result<bool, GraphError>
                 let children : &mut Vec<usize> = &mut try!(graph.get_mut_node(i)).children;
                 children.iter().position(|&x| x == old).map(|x| children.remove(x));
             }
+            try!(super::move_gradient_links(graph, old, node));
             // Remove node from the graph
             graph.insert_node(old, None);
             graph.outputs.iter().position(|&x| x == old).map(|x| {graph.outputs.push(node); graph.outputs.swap_remove(x);});
             // Remove node from the ordering and put all created nodes
             let order:usize = graph.ordering.iter().position(|&x| x == old).unwrap();
-            println!("Ordering: {:?}",graph.ordering);
-            println!("Removing {} from position {}",old,order);
             let _ = graph.ordering.remove(order);
             for _ in created_nodes.iter(){
                 let i = graph.ordering.pop().unwrap();
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use core::*;
+
+/// The type of a node as a key of a `HashMap`, where a `Float` is identified by its bits
+#[derive(PartialEq, Eq, Hash)]
+enum TypeKey {
+    Float(u64),
+    Integer(i64),
+    ConstInput,
+    Parameter,
+    ConstDerived,
+    ParameterDerived
+}
+
+impl TypeKey {
+    fn new(node_type: Type) -> Self {
+        match node_type {
+            Type::Float(x) => TypeKey::Float(x.to_bits()),
+            Type::Integer(x) => TypeKey::Integer(x),
+            Type::ConstInput => TypeKey::ConstInput,
+            Type::Parameter => TypeKey::Parameter,
+            Type::ConstDerived => TypeKey::ConstDerived,
+            Type::ParameterDerived => TypeKey::ParameterDerived
+        }
+    }
+}
+
+/// Performs common subexpression elimination on all nodes of the graph
+///
+/// Nodes with the same operator and the same parents and arguments are merged in to the first of them,
+/// where the order of the parents does not matter for the commutative `Add` and `Mul`.
+/// `Float` and `Integer` nodes with the same value are merged as well. Inputs are never merged.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn cse(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    // The id of every node kept so far, keyed by its type and operator
+    let mut kept : HashMap<(TypeKey, Operator), usize> = HashMap::new();
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let key = {
+            let node = try!(graph.get_node(i));
+            let mut op = node.op.clone();
+            match node.node_type {
+                Type::Parameter | Type::ConstInput => continue,
+                _ => ()
+            }
+            match op.op_type {
+                OPERATOR_ADD | OPERATOR_MUL => op.parents.sort(),
+                _ => ()
+            }
+            (TypeKey::new(node.node_type), op)
+        };
+        // The ordering is topological, thus all parents of the node have already been merged
+        match kept.get(&key).cloned() {
+            Some(node) => {
+                try!(super::replace_node(graph, i, node));
+                outcome = true;
+            },
+            None => {kept.insert(key, i);}
+        }
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,41 @@
+use core::*;
+
+/// Cancels operators applied directly on the result of their inverse
+///
+/// This means that `log(exp(x))`, `-(-x)`, `1/(1/x)`, `(x')'`, `minv(minv(x))` and `mdiag(vdiag(x))` are replaced by `x`,
+/// while `sqrt(square(x))` is replaced by `abs(x)`. The inner nodes are not removed, even if they are not used anymore.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn inverse_cancellation(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let (op, parent) = {
+            let node = try!(graph.get_node(i));
+            match node.op.op_type {
+                OPERATOR_LOG | OPERATOR_NEG | OPERATOR_DIV | OPERATOR_TRANSPOSE | OPERATOR_MINV
+                | OPERATOR_MDIAG | OPERATOR_SQRT => (node.op.op_type, try!(graph.get_node(node.op.parents[0])).op.clone()),
+                _ => continue
+            }
+        };
+        let inverse = match op {
+            OPERATOR_LOG => OPERATOR_EXP,
+            OPERATOR_MDIAG => OPERATOR_VDIAG,
+            OPERATOR_SQRT => OPERATOR_SQUARE,
+            _ => op
+        };
+        if parent.op_type != inverse {
+            continue
+        }
+        let x = parent.parents[0];
+        let new_node = match op {
+            // Only a column vector is recovered from the diagonal
+            OPERATOR_MDIAG if !try!(graph.get_node(x)).dims.1.is_one() => continue,
+            OPERATOR_SQRT => try!(super::add_before(graph, i, OPERATOR_ABS, vec![x])),
+            _ => x
+        };
+        try!(super::replace_node(graph, i, new_node));
+        outcome = true;
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,90 @@
+use core::*;
+
+/// Selects which of the rewrites of `log_exp_rewriting` are performed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogExpRewrites {
+    /// Rewrite `log(a*b*c)` to `log(a)+log(b)+log(c)`
+    pub log_of_product: bool,
+    /// Rewrite `exp(a)*exp(b)` to `exp(a+b)`
+    pub product_of_exp: bool,
+    /// Rewrite `pow(exp(x), y)` to `exp(x*y)`
+    pub power_of_exp: bool
+}
+
+impl LogExpRewrites {
+    /// Creates a selection with all of the rewrites enabled
+    pub fn all() -> Self {
+        LogExpRewrites{log_of_product: true, product_of_exp: true, power_of_exp: true}
+    }
+}
+
+/// Rewrites logarithms of products and products of exponentials
+///
+/// This means that `log(a*b*c)` becomes `log(a)+log(b)+log(c)`, `exp(a)*exp(b)*c` becomes `exp(a+b)*c`
+/// and `pow(exp(x), y)` becomes `exp(x*y)`, where each rewrite is performed only if it is enabled in `rewrites`.
+/// The inner nodes are not removed, even if they are not used anymore.
+/// A logarithm of a product is rewritten only if the product is used by nothing else.
+///
+/// The pass assumes that every factor of a rewritten product is positive. Otherwise `log(a*b)` may be defined,
+/// while `log(a)+log(b)` is not, thus it must be enabled only for graphs where this holds.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn log_exp_rewriting(graph: &mut ComputeGraph, rewrites: &LogExpRewrites) -> Result<bool, GraphError> {
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let op = try!(graph.get_node(i)).op.clone();
+        let new_node = match op.op_type {
+            OPERATOR_LOG if rewrites.log_of_product => {
+                let parent = try!(graph.get_node(op.parents[0])).clone();
+                // A product used elsewhere is still computed, thus the logarithms would only add work
+                if parent.op.op_type != OPERATOR_MUL || parent.children.len() != 1 {
+                    continue
+                }
+                let mut logs : Vec<usize> = Vec::new();
+                for p in parent.op.parents.iter() {
+                    logs.push(try!(super::add_before(graph, i, OPERATOR_LOG, vec![*p])));
+                }
+                try!(super::add_before(graph, i, OPERATOR_ADD, logs))
+            },
+            OPERATOR_MUL if rewrites.product_of_exp => {
+                // The positions of the parents which are exponentials
+                let mut exps : Vec<usize> = Vec::new();
+                for (k, p) in op.parents.iter().enumerate() {
+                    if try!(graph.get_node(*p)).op.op_type == OPERATOR_EXP {
+                        exps.push(k);
+                    }
+                }
+                if exps.len() < 2 {
+                    continue
+                }
+                let mut exponents : Vec<usize> = Vec::new();
+                for k in exps.iter() {
+                    exponents.push(try!(graph.get_node(op.parents[*k])).op.parents[0]);
+                }
+                // The combined exponential is placed where the first one was
+                let sum = try!(super::add_before(graph, i, OPERATOR_ADD, exponents));
+                let mut parents = op.parents.clone();
+                parents[exps[0]] = try!(super::add_before(graph, i, OPERATOR_EXP, vec![sum]));
+                parents = parents.into_iter().enumerate().filter(|&(k, _)| !exps[1..].contains(&k))
+                    .map(|(_, p)| p).collect();
+                match parents.len() {
+                    1 => parents[0],
+                    _ => try!(super::add_before(graph, i, OPERATOR_MUL, parents))
+                }
+            },
+            OPERATOR_POW if rewrites.power_of_exp => {
+                let base = try!(graph.get_node(op.parents[0])).op.clone();
+                if base.op_type != OPERATOR_EXP {
+                    continue
+                }
+                let product = try!(super::add_before(graph, i, OPERATOR_MUL, vec![base.parents[0], op.parents[1]]));
+                try!(super::add_before(graph, i, OPERATOR_EXP, vec![product]))
+            },
+            _ => continue
+        };
+        try!(super::replace_node(graph, i, new_node));
+        outcome = true;
+    }
+    Ok(outcome)
+}
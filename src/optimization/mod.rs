@@ -1,2 +1,91 @@
 mod constant_folding;
+mod prune;
+mod cse;
+mod inverse_cancellation;
+mod nary_flattening;
+mod neg_div_reordering;
+mod log_exp_rewriting;
+mod sub_indexing;
+mod pipeline;
 pub use self::constant_folding::constant_folding;
+pub use self::prune::{prune, prune_targets};
+pub use self::cse::cse;
+pub use self::inverse_cancellation::inverse_cancellation;
+pub use self::nary_flattening::nary_flattening;
+pub use self::neg_div_reordering::neg_div_reordering;
+pub use self::log_exp_rewriting::{log_exp_rewriting, LogExpRewrites};
+pub use self::sub_indexing::sub_indexing;
+pub use self::pipeline::{Pass, FnPass, PassStats, Pipeline};
+
+use core::*;
+
+/// Replaces the node `old` with `new` everywhere in the graph and removes it
+fn replace_node(graph: &mut ComputeGraph, old: usize, new: usize) -> Result<(), GraphError> {
+    // Swap connection of the children to point to the new node
+    try!(graph.swap_child_connections(old, new));
+    let node = try!(graph.get_node(old)).clone();
+    // Delete the node from the ancestors' children
+    for i in node.op.get_ancestors() {
+        let children : &mut Vec<usize> = &mut try!(graph.get_mut_node(*i)).children;
+        children.iter().position(|&x| x == old).map(|x| children.remove(x));
+    }
+    try!(move_gradient_links(graph, old, new));
+    let rename = graph.outputs.contains(&old) && !graph.outputs.contains(&new);
+    {
+        // Keep the name of an output, unless the new node is an input or an output itself
+        let new_node = try!(graph.get_mut_node(new));
+        if rename && new_node.op.op_type != OPERATOR_NONE {
+            new_node.name = node.name.clone();
+        }
+    }
+    for output in graph.outputs.iter_mut().filter(|x| **x == old) {
+        *output = new;
+    }
+    // Remove node from the graph and the ordering
+    graph.insert_node(old, None);
+    graph.ordering.retain(|&x| x != old);
+    Ok(())
+}
+
+/// Moves the gradient links of the node `old` to `new`, which adopts the gradient of `old` only if it has none
+fn move_gradient_links(graph: &mut ComputeGraph, old: usize, new: usize) -> Result<(), GraphError> {
+    let node = try!(graph.get_node(old)).clone();
+    for i in node.grad_parents.iter() {
+        try!(graph.get_mut_node(*i)).grad_child = Some(new);
+    }
+    let adopt = match node.grad_child {
+        Some(grad) => {
+            let adopt = try!(graph.get_node(new)).grad_child.is_none();
+            let grad_parents = &mut try!(graph.get_mut_node(grad)).grad_parents;
+            grad_parents.retain(|&x| x != old);
+            if adopt {
+                grad_parents.push(new);
+            }
+            adopt
+        },
+        None => false
+    };
+    for level in graph.levels.iter_mut().filter(|x| x.target == old) {
+        level.target = new;
+    }
+    let new_node = try!(graph.get_mut_node(new));
+    new_node.grad_parents.extend(node.grad_parents.iter().cloned());
+    if adopt {
+        new_node.grad_child = node.grad_child;
+    }
+    Ok(())
+}
+
+/// Adds a new operation to the graph, placing it in the ordering just before the node `before`
+fn add_before(graph: &mut ComputeGraph, before: usize, op_type: OperatorType, args: Vec<usize>) -> Result<usize, GraphError> {
+    let new = try!(graph.add_operation(op_type, args));
+    move_before(graph, before, new);
+    Ok(new)
+}
+
+/// Moves a node, which has just been added to the graph, in the ordering just before the node `before`
+fn move_before(graph: &mut ComputeGraph, before: usize, new: usize) {
+    graph.ordering.retain(|&x| x != new);
+    let order = graph.ordering.iter().position(|&x| x == before).unwrap();
+    graph.ordering.insert(order, new);
+}
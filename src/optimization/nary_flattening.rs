@@ -0,0 +1,53 @@
+use core::*;
+
+/// Collects nested n-ary operators in to a single node
+///
+/// This means that a parent of an `Add`, `Mul`, `Dot`, `HorzCat` or `VertCat` node, which is of the same operator and
+/// is used only by that node, is replaced by its own parents, preserving their order - e.g. `(a+b) + (c+d)` becomes `a + b + c + d`.
+/// Parents which are outputs of the graph or gradients of other nodes are never collected.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn nary_flattening(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let op = try!(graph.get_node(i)).op.clone();
+        match op.op_type {
+            OPERATOR_ADD | OPERATOR_MUL | OPERATOR_DOT | OPERATOR_HORZCAT | OPERATOR_VERTCAT => (),
+            _ => continue
+        }
+        let mut parents : Vec<usize> = Vec::new();
+        let mut collected : Vec<usize> = Vec::new();
+        for p in op.parents.iter() {
+            let parent = try!(graph.get_node(*p));
+            if parent.op.op_type == op.op_type && parent.children == vec![i]
+                && parent.grad_parents.len() == 0 && !graph.outputs.contains(p) {
+                // The ordering is topological, thus the parent has already been flattened
+                parents.extend(parent.op.parents.iter().cloned());
+                collected.push(*p);
+            } else {
+                parents.push(*p);
+            }
+        }
+        if collected.len() == 0 {
+            continue
+        }
+        for p in collected.iter() {
+            let parent = try!(graph.get_node(*p)).clone();
+            // Connect the parents of the collected node directly to the node
+            for j in parent.op.parents.iter() {
+                let children : &mut Vec<usize> = &mut try!(graph.get_mut_node(*j)).children;
+                children.iter().position(|x| x == p).map(|x| children[x] = i);
+            }
+            if let Some(grad) = parent.grad_child {
+                try!(graph.get_mut_node(grad)).grad_parents.retain(|x| x != p);
+            }
+            // Remove node from the graph and the ordering
+            graph.insert_node(*p, None);
+            graph.ordering.retain(|x| x != p);
+        }
+        try!(graph.get_mut_node(i)).op = try!(Operator::new(op.op_type, parents, vec![]));
+        outcome = true;
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,84 @@
+use core::*;
+
+/// Reorders negations and divisions, which the parser creates for subtraction and division
+///
+/// This means that a negation of an `Add`, used only by it, is pushed to its parents - `-(b+c)` becomes `(-b)+(-c)`.
+/// The negations of the parents of a `Mul` or `Dot` are pulled out, such that at most one of them remains - `(-a)*(-b)` becomes `a*b`.
+/// The divisions of the parents of a `Mul` are combined in to a single one - `div(a)*div(b)` becomes `div(a*b)`.
+/// A negation of a negation is never created, instead the inner one is removed.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn neg_div_reordering(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let op = try!(graph.get_node(i)).op.clone();
+        let new_node = match op.op_type {
+            OPERATOR_NEG => {
+                let parent = try!(graph.get_node(op.parents[0])).clone();
+                if parent.op.op_type != OPERATOR_ADD || parent.children != vec![i]
+                    || parent.grad_parents.len() > 0 || graph.outputs.contains(&parent.id) {
+                    continue
+                }
+                let mut parents : Vec<usize> = Vec::new();
+                for p in parent.op.parents.iter() {
+                    parents.push(try!(negate(graph, i, *p)));
+                }
+                try!(super::add_before(graph, i, OPERATOR_ADD, parents))
+            },
+            OPERATOR_MUL | OPERATOR_DOT => {
+                // The positions of the parents which are negations or divisions
+                let mut negations : Vec<usize> = Vec::new();
+                let mut divisions : Vec<usize> = Vec::new();
+                for (k, p) in op.parents.iter().enumerate() {
+                    match try!(graph.get_node(*p)).op.op_type {
+                        OPERATOR_NEG => negations.push(k),
+                        OPERATOR_DIV if op.op_type == OPERATOR_MUL => divisions.push(k),
+                        _ => ()
+                    }
+                }
+                if negations.len() < 2 && divisions.len() < 2 {
+                    continue
+                }
+                let mut parents = op.parents.clone();
+                if negations.len() > 1 {
+                    for k in negations.iter() {
+                        parents[*k] = try!(graph.get_node(parents[*k])).op.parents[0];
+                    }
+                }
+                if divisions.len() > 1 {
+                    let mut divided : Vec<usize> = Vec::new();
+                    for k in divisions.iter() {
+                        divided.push(try!(graph.get_node(parents[*k])).op.parents[0]);
+                    }
+                    // The combined division is placed where the first one was
+                    let product = try!(super::add_before(graph, i, OPERATOR_MUL, divided));
+                    parents[divisions[0]] = try!(super::add_before(graph, i, OPERATOR_DIV, vec![product]));
+                    parents = parents.into_iter().enumerate().filter(|&(k, _)| !divisions[1..].contains(&k))
+                        .map(|(_, p)| p).collect();
+                }
+                let result = match parents.len() {
+                    1 => parents[0],
+                    _ => try!(super::add_before(graph, i, op.op_type, parents))
+                };
+                match negations.len() > 1 && negations.len() % 2 == 1 {
+                    true => try!(negate(graph, i, result)),
+                    false => result
+                }
+            },
+            _ => continue
+        };
+        try!(super::replace_node(graph, i, new_node));
+        outcome = true;
+    }
+    Ok(outcome)
+}
+
+/// Returns the negation of the node, placing any new node before `before`
+fn negate(graph: &mut ComputeGraph, before: usize, node: usize) -> Result<usize, GraphError> {
+    let op = try!(graph.get_node(node)).op.clone();
+    match op.op_type {
+        OPERATOR_NEG => Ok(op.parents[0]),
+        _ => super::add_before(graph, before, OPERATOR_NEG, vec![node])
+    }
+}
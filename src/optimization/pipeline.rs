@@ -0,0 +1,154 @@
+use core::*;
+use super::*;
+
+/// A single optimization pass, which can be run as part of a `Pipeline`
+pub trait Pass {
+    /// The name under which the statistics of the pass are recorded
+    fn name(&self) -> &str;
+    /// Runs the pass once over the graph
+    ///
+    /// Returns true if  the graph has been modified, false otherwise.
+    /// An error if any of the operations brings up a `GraphError`
+    fn run(&self, graph: &mut ComputeGraph) -> Result<bool, GraphError>;
+}
+
+/// A pass given by a function, such as any of the passes in this module
+pub struct FnPass {
+    name: String,
+    pass: fn(&mut ComputeGraph) -> Result<bool, GraphError>
+}
+
+impl FnPass {
+    /// Creates a pass with the given name running the function
+    pub fn new(name: &str, pass: fn(&mut ComputeGraph) -> Result<bool, GraphError>) -> Self {
+        FnPass{name: name.to_string(), pass: pass}
+    }
+}
+
+impl Pass for FnPass {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+        (self.pass)(graph)
+    }
+}
+
+impl Pass for LogExpRewrites {
+    fn name(&self) -> &str {
+        "log_exp_rewriting"
+    }
+
+    fn run(&self, graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+        log_exp_rewriting(graph, self)
+    }
+}
+
+/// The statistics recorded for a single pass of a `Pipeline`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassStats {
+    /// The name of the pass
+    pub name: String,
+    /// The number of times the pass has been run
+    pub runs: usize,
+    /// The number of runs in which the pass has modified the graph
+    pub modifications: usize,
+    /// The total number of nodes removed by the pass, negative if it has added more than it has removed
+    pub nodes_removed: isize
+}
+
+/// An ordered list of passes, which are run until none of them modifies the graph
+pub struct Pipeline {
+    passes: Vec<Box<Pass>>,
+    /// The maximum number of iterations over all of the passes
+    pub max_iterations: usize,
+    /// The number of iterations performed on the last run
+    pub iterations: usize,
+    /// The statistics of each pass, in the order in which they were added
+    pub stats: Vec<PassStats>
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline running at most `max_iterations` iterations
+    pub fn new(max_iterations: usize) -> Self {
+        Pipeline{passes: Vec::new(), max_iterations: max_iterations, iterations: 0, stats: Vec::new()}
+    }
+
+    /// Creates the pipeline for the optimization level
+    ///
+    /// Level 0 performs no optimizations, level 1 runs each of the default passes once,
+    /// while level 2 and above also fold constants and run the passes until a fixed point is reached.
+    /// No level runs `log_exp_rewriting`, as rewriting `log(a*b)` to `log(a)+log(b)` is valid only for positive operands,
+    /// see `with_log_exp`.
+    pub fn with_level(level: usize) -> Self {
+        Pipeline::for_level(level, None)
+    }
+
+    /// Creates the pipeline for the optimization level, which in addition runs `log_exp_rewriting` with the selected
+    /// rewrites after `nary_flattening`. This is an opt-in, since the caller has to know that the operands of the
+    /// logarithms are positive.
+    pub fn with_log_exp(level: usize, rewrites: LogExpRewrites) -> Self {
+        Pipeline::for_level(level, Some(rewrites))
+    }
+
+    fn for_level(level: usize, log_exp: Option<LogExpRewrites>) -> Self {
+        let mut pipeline = match level {
+            0 => return Pipeline::new(0),
+            1 => Pipeline::new(1),
+            _ => Pipeline::new(10)
+        };
+        if level > 1 {
+            pipeline.add(FnPass::new("constant_folding", constant_folding));
+        }
+        pipeline.add(FnPass::new("neg_div_reordering", neg_div_reordering));
+        pipeline.add(FnPass::new("inverse_cancellation", inverse_cancellation));
+        pipeline.add(FnPass::new("nary_flattening", nary_flattening));
+        if let Some(rewrites) = log_exp {
+            pipeline.add(rewrites);
+        }
+        pipeline.add(FnPass::new("sub_indexing", sub_indexing));
+        pipeline.add(FnPass::new("cse", cse));
+        pipeline.add(FnPass::new("prune", prune));
+        pipeline
+    }
+
+    /// Adds the pass at the end of the pipeline
+    pub fn add<T: Pass + 'static>(&mut self, pass: T) -> &mut Self {
+        self.stats.push(PassStats{name: pass.name().to_string(), runs: 0, modifications: 0, nodes_removed: 0});
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Runs all of the passes in order, until none of them modifies the graph or `max_iterations` is reached.
+    /// When `VERIFICATION` is on, the graph is verified after every pass.
+    ///
+    /// Returns true if  the graph has been modified, false otherwise.
+    /// An error if any of the operations brings up a `GraphError`
+    pub fn run(&mut self, graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+        let mut outcome = false;
+        self.iterations = 0;
+        while self.iterations < self.max_iterations {
+            self.iterations += 1;
+            let mut modified = false;
+            for (pass, stats) in self.passes.iter().zip(self.stats.iter_mut()) {
+                let before = graph.len() as isize;
+                let result = try!(pass.run(graph));
+                if super::super::VERIFICATION {
+                    try!(graph.verify());
+                }
+                stats.runs += 1;
+                stats.nodes_removed += before - graph.len() as isize;
+                if result {
+                    stats.modifications += 1;
+                    modified = true;
+                }
+            }
+            outcome = outcome || modified;
+            if !modified {
+                break;
+            }
+        }
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,62 @@
+use core::*;
+
+/// Removes all nodes which are not needed for computing the outputs of the graph or the gradients of its parameters.
+///
+/// Inputs are never removed, as they define the signature of the function.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn prune(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    let mut targets = graph.outputs.clone();
+    targets.extend(graph.get_params().0.into_iter());
+    prune_targets(graph, targets)
+}
+
+/// Removes all nodes which are not needed for computing any of the targets.
+///
+/// Inputs are never removed, as they define the signature of the function.
+/// Any output or gradient, which is not required by the targets, is removed as well.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn prune_targets(graph: &mut ComputeGraph, targets: Vec<usize>) -> Result<bool, GraphError> {
+    let mut alive = vec![false; graph.nodes.len()];
+    for i in try!(graph.generate_ordering(targets)) {
+        alive[i] = true;
+    }
+    for node in graph.nodes.iter() {
+        match *node {
+            Some(ref node) => match node.node_type {
+                Type::Parameter | Type::ConstInput => alive[node.id] = true,
+                _ => ()
+            },
+            None => ()
+        }
+    }
+    let mut outcome = false;
+    for i in 0..graph.nodes.len() {
+        if !alive[i] && graph.nodes[i].is_some() {
+            graph.insert_node(i, None);
+            outcome = true;
+        }
+    }
+    if !outcome {
+        return Ok(false)
+    }
+    // Fix all links of the remaining nodes
+    for node in graph.nodes.iter_mut() {
+        match *node {
+            Some(ref mut node) => {
+                node.children.retain(|&x| alive[x]);
+                node.grad_parents.retain(|&x| alive[x]);
+                if node.grad_child.map_or(false, |x| !alive[x]) {
+                    node.grad_child = None;
+                }
+            },
+            None => ()
+        }
+    }
+    graph.ordering.retain(|&x| alive[x]);
+    graph.outputs.retain(|&x| alive[x]);
+    Ok(true)
+}
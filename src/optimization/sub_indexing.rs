@@ -0,0 +1,87 @@
+use core::*;
+
+/// Simplifies sub indexing operations
+///
+/// This means that a `SubIndex` of a `SubIndex` is combined in to a single one, a `SubIndex` selecting exactly one of the
+/// parents of a `VertCat` or `HorzCat` is replaced by that parent, and a `SubIndex` selecting exactly the block
+/// assigned by a `SubAssign` is replaced by the assigned value.
+/// The bounds are compared through their symbolic values, thus they must be provably equal.
+/// The inner nodes are not removed, even if they are not used anymore.
+///
+/// Returns true if  the graph has been modified, false otherwise.
+/// An error if any of the operations brings up a `GraphError`
+pub fn sub_indexing(graph: &mut ComputeGraph) -> Result<bool, GraphError> {
+    let mut outcome = false;
+    for i in graph.ordering.clone() {
+        let op = try!(graph.get_node(i)).op.clone();
+        if op.op_type != OPERATOR_SUBINDEX {
+            continue
+        }
+        let parent = try!(graph.get_node(op.parents[0])).clone();
+        let mut bounds : Vec<SymPolynomial> = Vec::new();
+        for a in op.args.iter() {
+            bounds.push(try!(graph.symbolic_value(*a)));
+        }
+        let new_node = match parent.op.op_type {
+            OPERATOR_SUBINDEX => {
+                let start_x = try!(add_offsets(graph, i, parent.op.args[0], op.args[0]));
+                let start_y = try!(add_offsets(graph, i, parent.op.args[2], op.args[2]));
+                try!(super::add_before(graph, i, OPERATOR_SUBINDEX,
+                    vec![parent.op.parents[0], start_x, op.args[1], start_y, op.args[3]]))
+            },
+            OPERATOR_SUBASSIGN => {
+                let mut same = true;
+                for (k, a) in parent.op.args.iter().take(4).enumerate() {
+                    same = same && try!(graph.symbolic_value(*a)) == bounds[k];
+                }
+                match same {
+                    true => parent.op.parents[0],
+                    false => continue
+                }
+            },
+            OPERATOR_VERTCAT | OPERATOR_HORZCAT => {
+                // The bounds of the concatenated dimension are checked for each parent, the others must span it fully
+                let vertical = parent.op.op_type == OPERATOR_VERTCAT;
+                let (start, size, other_start, other_size) = match vertical {
+                    true => (&bounds[0], &bounds[1], &bounds[2], &bounds[3]),
+                    false => (&bounds[2], &bounds[3], &bounds[0], &bounds[1])
+                };
+                let mut offset = SymPolynomial::constant(0);
+                let mut piece : Option<usize> = None;
+                for p in parent.op.parents.iter() {
+                    let dims = try!(graph.get_node(*p)).dims.clone();
+                    let (length, other_length) = if vertical {(dims.0, dims.1)} else {(dims.1, dims.0)};
+                    if offset == *start && length == *size && other_start.as_constant() == Some(0)
+                        && other_length == *other_size {
+                        piece = Some(*p);
+                        break;
+                    }
+                    offset = offset + length;
+                }
+                match piece {
+                    Some(p) => p,
+                    None => continue
+                }
+            },
+            _ => continue
+        };
+        try!(super::replace_node(graph, i, new_node));
+        outcome = true;
+    }
+    Ok(outcome)
+}
+
+/// Returns a node with the sum of the two offsets, placing any new node before `before`
+fn add_offsets(graph: &mut ComputeGraph, before: usize, first: usize, second: usize) -> Result<usize, GraphError> {
+    let values = (try!(graph.symbolic_value(first)).as_constant(), try!(graph.symbolic_value(second)).as_constant());
+    match values {
+        (Some(0), _) => Ok(second),
+        (_, Some(0)) => Ok(first),
+        (Some(x), Some(y)) => {
+            let new = graph.add_int(x + y);
+            super::move_before(graph, before, new);
+            Ok(new)
+        },
+        _ => super::add_before(graph, before, OPERATOR_ADD, vec![first, second])
+    }
+}
@@ -0,0 +1,100 @@
+extern crate meta_diff;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// Compiles the code and a driver with the C compiler given by `MD_CC` or `cc` on the path, then runs it
+fn run_c(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_c(&mut code, graph).unwrap();
+	harness::write_file(dir, "code.c", &code);
+	let results = (0..graph.outputs.len() + graph.get_params().0.len()).map(|k| format!("r{}", k)).collect::<Vec<String>>();
+	let mut driver = "#include <stdio.h>\n#include <stdlib.h>\n#include \"code.c\"\n\n\
+		static void print(const md_matrix* m) {\n\tprintf(\"%d %d\", (int) m->rows, (int) m->cols);\n\
+		\tfor (size_t k = 0; k < m->rows * m->cols; ++k) printf(\" %.17g\", m->data[k]);\n\tprintf(\"\\n\");\n}\n\n\
+		int main(void) {\n".to_string();
+	for &(ref name, ref m) in inputs.iter() {
+		driver.push_str(&format!("\tdouble in_{0}_data[] = {{{1}}};\n\tmd_matrix in_{0} = {{{2}, {3}, in_{0}_data}};\n",
+			name, harness::data(m), m.rows, m.cols));
+	}
+	let arguments = inputs.iter().map(|&(ref name, _)| format!("&in_{}", name)).collect::<Vec<String>>().join(", ");
+	driver.push_str(&format!("\tmd_matrix {};\n", results.join(", ")));
+	driver.push_str(&format!("\tdouble* workspace = malloc({}_workspace_size({}));\n", graph.name, arguments));
+	driver.push_str(&format!("\t{}({}, workspace, {});\n", graph.name, arguments,
+		results.iter().map(|r| format!("&{}", r)).collect::<Vec<String>>().join(", ")));
+	for r in results.iter() {
+		driver.push_str(&format!("\tprint(&{});\n", r));
+	}
+	driver.push_str("\tfree(workspace);\n\treturn 0;\n}\n");
+	harness::write_file(dir, "driver.c", driver.as_bytes());
+	let cc = ::std::env::var("MD_CC").unwrap_or("cc".to_string());
+	match harness::run(Command::new(&cc).arg("-std=c99").arg("-o").arg("driver").arg("driver.c").arg("-lm"), dir) {
+		Some(_) => harness::run(&mut Command::new(dir.join("driver")), dir),
+		None => None
+	}
+}
+
+fn c_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_c, "c", name, source, inputs, -0.5);
+}
+
+fn c_fail(fail_msg: &str, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let mut code : Vec<u8> = Vec::new();
+	match meta_diff::codegen::write_c(&mut code, &graph) {
+		Ok(_) => assert!(false, "Generated code, but should have failed."),
+		Err(msg) => assert!(format!("{}", msg) == fail_msg, "Expected error: {}, was: {}", fail_msg, msg)
+	}
+}
+
+parametarise_test!(c_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(w * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"subassign",
+	"function [L] = mat(@w,x)
+	h = horzcat(w,x);
+	L = sum(h[0,2,1,3] * h[1,1,0,3], 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)]
+},{
+	"inverse",
+	"function [L] = mat(@w,x)
+	h = minv(w dot w' + eye(2)) dot x;
+	L = sum(h * h, 0) + sum(mdiag(w dot w'), 0) + sum(vdiag(x[0,2,0,1]) dot w, 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2)]
+});
+
+parametarise_test!(c_fail,{
+	"The value of node 1 can not be computed from the dimensions of the inputs",
+	"function [L] = mat(@w,n)
+	L = sum(w dot ones(n,1),0);
+	end"
+});
@@ -0,0 +1,80 @@
+extern crate meta_diff;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// Compiles the code and a driver with the C++ compiler given by `MD_CXX` or `c++` on the path,
+/// against the Eigen headers in `MD_EIGEN_INCLUDE` or `/usr/include/eigen3`, then runs it
+fn run_eigen(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let include = ::std::env::var("MD_EIGEN_INCLUDE").unwrap_or("/usr/include/eigen3".to_string());
+	if !Path::new(&include).join("Eigen").join("Dense").exists() {
+		return None
+	}
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_eigen(&mut code, graph).unwrap();
+	harness::write_file(dir, "code.hpp", &code);
+	let results = (0..graph.outputs.len() + graph.get_params().0.len()).map(|k| format!("r{}", k)).collect::<Vec<String>>();
+	let mut driver = "#include <cstdio>\n#include \"code.hpp\"\n\n\
+		static void print(const Eigen::MatrixXd& m) {\n\tstd::printf(\"%d %d\", (int) m.rows(), (int) m.cols());\n\
+		\tfor (Eigen::Index k = 0; k < m.size(); ++k) std::printf(\" %.17g\", m.data()[k]);\n\tstd::printf(\"\\n\");\n}\n\n\
+		int main() {\n".to_string();
+	for &(ref name, ref m) in inputs.iter() {
+		driver.push_str(&format!("\tdouble in_{0}_data[] = {{{1}}};\n\tEigen::MatrixXd in_{0} = Eigen::Map<Eigen::MatrixXd>(in_{0}_data, {2}, {3});\n",
+			name, harness::data(m), m.rows, m.cols));
+	}
+	driver.push_str(&format!("\tEigen::MatrixXd {};\n", results.join(", ")));
+	driver.push_str(&format!("\t{}({}, {});\n", graph.name,
+		inputs.iter().map(|&(ref name, _)| format!("in_{}", name)).collect::<Vec<String>>().join(", "), results.join(", ")));
+	for r in results.iter() {
+		driver.push_str(&format!("\tprint({});\n", r));
+	}
+	driver.push_str("\treturn 0;\n}\n");
+	harness::write_file(dir, "driver.cpp", driver.as_bytes());
+	let cxx = ::std::env::var("MD_CXX").unwrap_or("c++".to_string());
+	match harness::run(Command::new(&cxx).arg("-std=c++11").arg("-I").arg(&include).arg("-o").arg("driver").arg("driver.cpp"), dir) {
+		Some(_) => harness::run(&mut Command::new(dir.join("driver")), dir),
+		None => None
+	}
+}
+
+fn eigen_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_eigen, "eigen", name, source, inputs, -0.5);
+}
+
+parametarise_test!(eigen_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(minv(w) * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"subassign",
+	"function [L] = mat(@w,x)
+	h = horzcat(w,x);
+	L = sum(h[0,2,1,3] * h[1,1,0,3], 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)]
+},{
+	"inverse",
+	"function [L] = mat(@w,x)
+	h = minv(w dot w' + eye(2)) dot x;
+	L = sum(h * h, 0) + sum(mdiag(w dot w'), 0) + sum(quad(w, x), 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2)]
+});
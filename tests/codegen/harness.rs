@@ -0,0 +1,79 @@
+extern crate meta_diff;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix, Type};
+use super::super::core::common::to_inputs;
+
+/// Writes the code of the graph and a driver calling it on the inputs, given in the order of the graph inputs, to the directory
+/// and runs the driver. It must print each output followed by each gradient, in the order of `get_params`, on a separate line
+/// as `rows cols data...` with the data in column major order.
+/// Returns `None` if a tool needed by the backend is not available
+pub type Runner = fn(&ComputeGraph, &Vec<(String, Matrix)>, &PathBuf) -> Option<String>;
+
+/// Exit code of a driver which found that a library needed by the backend is not available
+pub const SKIP: i32 = 77;
+
+/// Runs the generated code of the source with the runner and compares the outputs and the gradients to those of `core::eval`.
+/// The inputs are filled with values in [low, low + 1]. Skipped if the runner returns `None`
+pub fn run_ok(runner: Runner, backend: &str, name: &str, source: &str, inputs: Vec<(&str, usize, usize)>, low: f64){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let values = to_inputs(&inputs, low);
+	let ordered = graph.nodes.iter().filter_map(|x| x.as_ref())
+		.filter(|x| x.node_type == Type::Parameter || x.node_type == Type::ConstInput)
+		.map(|x| (x.name.clone(), values[&x.name].clone())).collect::<Vec<(String, Matrix)>>();
+	let dir = ::std::env::temp_dir().join(format!("meta_diff_{}_{}", backend, name));
+	fs::create_dir_all(&dir).unwrap();
+	let stdout = match runner(&graph, &ordered, &dir) {
+		Some(stdout) => stdout,
+		None => {return println!("Skipped running the {} code, as its tools are not available", backend);}
+	};
+	let result = meta_diff::core::eval(&graph, &values).unwrap();
+	let expected = graph.outputs.iter().chain(graph.get_params().0.iter()).map(|id| result[id].clone()).collect::<Vec<Matrix>>();
+	let lines = stdout.lines().filter(|line| line.trim().len() > 0).collect::<Vec<&str>>();
+	assert!(lines.len() == expected.len(), "Expected {} results, the {} code printed:\n{}", expected.len(), backend, stdout);
+	for (k, (line, m)) in lines.iter().zip(expected.iter()).enumerate() {
+		let numbers = line.split_whitespace().map(|x| x.parse::<f64>().unwrap()).collect::<Vec<f64>>();
+		assert!(numbers.len() >= 2 && numbers[0] as usize == m.rows && numbers[1] as usize == m.cols && numbers.len() == 2 + m.data.len(),
+			"Expected result {} to have dimensions ({}, {}), the {} code printed: {}", k, m.rows, m.cols, backend, line);
+		for (i, (x, y)) in numbers.iter().skip(2).zip(m.data.iter()).enumerate() {
+			assert!((x - y).abs() < 1e-9 * y.abs().max(1.0),
+				"Expected element {} of result {} to be {}, the {} code computed: {}", i, k, y, backend, x);
+		}
+	}
+}
+
+/// Writes the file in the directory, returning its path
+pub fn write_file(dir: &PathBuf, name: &str, content: &[u8]) -> PathBuf {
+	let path = dir.join(name);
+	File::create(&path).unwrap().write_all(content).unwrap();
+	path
+}
+
+/// Runs the command and returns what it printed. `None` if the command can not be started or exits with `SKIP`,
+/// while any other failure is reported together with the code in the directory
+pub fn run(command: &mut Command, dir: &PathBuf) -> Option<String> {
+	let output = match command.current_dir(dir).output() {
+		Ok(output) => output,
+		Err(_) => return None
+	};
+	if output.status.code() == Some(SKIP) {
+		return None
+	}
+	assert!(output.status.success(), "Running {:?} in {} failed:\n{}\n{}", command, dir.display(),
+		String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+	Some(String::from_utf8(output.stdout).unwrap())
+}
+
+/// The elements of the matrix in column major order, separated by commas
+pub fn data(m: &Matrix) -> String {
+	m.data.iter().map(|x| format!("{:?}", x)).collect::<Vec<String>>().join(", ")
+}
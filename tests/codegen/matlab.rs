@@ -0,0 +1,66 @@
+extern crate meta_diff;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// Runs the function with Octave, given by `MD_OCTAVE` or `octave-cli` on the path
+fn run_matlab(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_matlab(&mut code, graph).unwrap();
+	harness::write_file(dir, &format!("{}.m", graph.name), &code);
+	let results = (0..graph.outputs.len() + graph.get_params().0.len()).map(|k| format!("r{}", k)).collect::<Vec<String>>();
+	let mut script = String::new();
+	for &(ref name, ref m) in inputs.iter() {
+		script.push_str(&format!("in_{} = reshape([{}], {}, {});\n", name, harness::data(m), m.rows, m.cols));
+	}
+	script.push_str(&format!("[{}] = {}({});\n", results.join(", "), graph.name,
+		inputs.iter().map(|&(ref name, _)| format!("in_{}", name)).collect::<Vec<String>>().join(", ")));
+	script.push_str(&format!("results = {{{}}};\n", results.join(", ")));
+	script.push_str("for k = 1:numel(results)\n\tm = results{k};\n\tfprintf('%d %d', size(m, 1), size(m, 2));\n\
+		\tfprintf(' %.17g', m(:));\n\tfprintf('\\n');\nend\n");
+	harness::write_file(dir, "driver.m", script.as_bytes());
+	let octave = ::std::env::var("MD_OCTAVE").unwrap_or("octave-cli".to_string());
+	harness::run(Command::new(&octave).arg("--no-init-file").arg("--quiet").arg("driver.m"), dir)
+}
+
+fn matlab_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_matlab, "matlab", name, source, inputs, -0.5);
+}
+
+parametarise_test!(matlab_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(w * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"subassign",
+	"function [L] = mat(@w,x)
+	h = horzcat(w,x);
+	L = sum(h[0,2,1,3] * h[1,1,0,3], 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)]
+},{
+	"inverse",
+	"function [L] = mat(@w,x)
+	h = minv(w dot w' + eye(2)) dot x;
+	L = sum(h * h, 0) + sum(mdiag(w dot w'), 0) + sum(quad(w, x), 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2)]
+});
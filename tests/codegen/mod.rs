@@ -0,0 +1,7 @@
+mod harness;
+mod matlab;
+mod eigen;
+mod rust;
+mod numpy;
+mod c;
+mod opencl;
@@ -0,0 +1,68 @@
+extern crate meta_diff;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// Runs the code with the interpreter given by `MD_PYTHON` or `python3` on the path, skipped if NumPy is not installed
+fn run_numpy(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_numpy(&mut code, graph).unwrap();
+	harness::write_file(dir, "generated.py", &code);
+	let mut driver = format!("import sys\ntry:\n    import numpy as np\nexcept ImportError:\n    sys.exit({})\nimport generated\n\n\
+		def show(m):\n    m = np.atleast_2d(m)\n    print(' '.join(['%d %d' % m.shape] + ['%.17g' % x for x in m.flatten(order='F')]))\n\n",
+		harness::SKIP);
+	for &(ref name, ref m) in inputs.iter() {
+		driver.push_str(&format!("in_{} = np.array([{}]).reshape(({}, {}), order='F')\n", name, harness::data(m), m.rows, m.cols));
+	}
+	driver.push_str(&format!("results = generated.{}({})\n", graph.name,
+		inputs.iter().map(|&(ref name, _)| format!("in_{}", name)).collect::<Vec<String>>().join(", ")));
+	driver.push_str("for m in results[:-1]:\n    show(m)\n");
+	for name in graph.get_params().1.iter() {
+		driver.push_str(&format!("show(results[-1]['{}'])\n", name));
+	}
+	harness::write_file(dir, "driver.py", driver.as_bytes());
+	let python = ::std::env::var("MD_PYTHON").unwrap_or("python3".to_string());
+	harness::run(Command::new(&python).arg("driver.py"), dir)
+}
+
+fn numpy_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_numpy, "numpy", name, source, inputs, -0.5);
+}
+
+parametarise_test!(numpy_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(minv(w) * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"subassign",
+	"function [L] = mat(@w,x)
+	h = horzcat(w,x);
+	L = sum(h[0,2,1,3] * h[1,1,0,3], 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)]
+},{
+	"replicate",
+	"function [L] = mat(@w,x)
+	h = reshape(w, 1, 6) dot ones(6, 2) + sum(x, 1)' dot sum(w, 0);
+	L = sum(h * vertcat(x[0,1,0,2], x[1,1,0,2]), 0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 2)]
+});
@@ -0,0 +1,257 @@
+extern crate meta_diff;
+use std::io::Write;
+use std::fs::File;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// A kernel of the generated code with its parameters and its launch configuration, as given in the comment above it
+struct Launch {
+	name: String,
+	params: Vec<String>,
+	global: Vec<String>,
+	local: Vec<String>
+}
+
+/// Reads the kernels from the code, writing their launch configurations as C expressions
+fn launches(code: &str) -> Vec<Launch> {
+	let mut list : Vec<Launch> = Vec::new();
+	let mut sizes : Option<(Vec<String>, Vec<String>)> = None;
+	for line in code.lines() {
+		if line.starts_with("// Computes ") {
+			let config = line.split("Global size: ").nth(1).unwrap();
+			let mut parts = config.split(", local size: ");
+			let global = parts.next().unwrap();
+			let global = match global.find(") rounded up to MD_TILE") {
+				Some(end) => global[1..end].split(", ").map(|x| format!("MD_ROUND({})", x)).collect(),
+				None => vec![global.to_string()]
+			};
+			let local = match parts.next() {
+				Some("(MD_TILE, MD_TILE)") => vec!["MD_TILE".to_string(), "MD_TILE".to_string()],
+				Some(local) => vec![local.to_string()],
+				None => Vec::new()
+			};
+			sizes = Some((global, local));
+		} else if line.starts_with("__kernel void ") {
+			let (global, local) = sizes.take().unwrap();
+			let open = line.find('(').unwrap();
+			let close = line.rfind(')').unwrap();
+			let params = line[open + 1..close].split(", ").map(|x| x.split(' ').last().unwrap().to_string()).collect();
+			list.push(Launch{name: line[14..open].to_string(), params: params, global: global, local: local});
+		}
+	}
+	list
+}
+
+/// The dimensions of a buffer - `n<id>`, `n<id>_scratch` or `n<id>_<k>` for the k-th intermediate product of a `Dot`
+fn buffer_dims(graph: &ComputeGraph, values: &HashMap<usize, Matrix>, name: &str) -> (usize, usize) {
+	let parts = name[1..].split('_').collect::<Vec<&str>>();
+	let id = parts[0].parse::<usize>().unwrap();
+	match parts.get(1).and_then(|x| x.parse::<usize>().ok()) {
+		Some(k) => {
+			let parents = &graph.get_node(id).unwrap().op.parents;
+			let product = parents.iter().skip(1).take(k).fold(values[&parents[0]].clone(), |acc, p| acc.dot(&values[p]).unwrap());
+			(product.rows, product.cols)
+		},
+		None => (values[&id].rows, values[&id].cols)
+	}
+}
+
+/// Runs the kernels from a host program, compiled with the C compiler given by `MD_CC` or `cc` on the path and linked
+/// with the OpenCL library. The sizes of all buffers are taken from `core::eval`.
+/// Skipped if there are no OpenCL headers and library, or no device supporting double precision
+fn run_opencl(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_opencl(&mut code, graph).unwrap();
+	harness::write_file(dir, "kernels.cl", &code);
+	let code = String::from_utf8(code).unwrap();
+	let values = meta_diff::core::eval(graph, &inputs.iter().cloned().collect()).unwrap();
+	let ids = inputs.iter().map(|&(ref name, _)| graph.nodes.iter().filter_map(|x| x.as_ref())
+		.find(|x| &x.name == name).unwrap().id).collect::<Vec<usize>>();
+	let results = graph.outputs.iter().chain(graph.get_params().0.iter()).cloned().collect::<Vec<usize>>();
+	let launches = launches(&code);
+	let mut buffers : Vec<String> = Vec::new();
+	for launch in launches.iter() {
+		for param in launch.params.iter() {
+			let is_buffer = code.contains(&format!("double* {},", param)) || code.contains(&format!("double* {})", param));
+			if is_buffer && !buffers.contains(param) {
+				buffers.push(param.clone());
+			}
+		}
+	}
+	for id in ids.iter().chain(results.iter()) {
+		if !buffers.contains(&format!("n{}", id)) {
+			buffers.push(format!("n{}", id));
+		}
+	}
+	let mut host = "#define CL_TARGET_OPENCL_VERSION 120\n#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n#include <CL/cl.h>\n\n\
+		#define MD_TILE 16\n#define MD_REDUCE_LOCAL 64\n#define MD_ROUND(x) (((x) + MD_TILE - 1) / MD_TILE * MD_TILE)\n\
+		#define CHECK(x) do { cl_int status = (x); if (status != CL_SUCCESS) { fprintf(stderr, \"%s failed with %d\\n\", #x, (int) status); exit(1); } } while (0)\n\n\
+		static cl_mem create(cl_context context, size_t n, double* data) {\n\tcl_int error;\n\
+		\tcl_mem buffer = clCreateBuffer(context, CL_MEM_READ_WRITE | (data ? CL_MEM_COPY_HOST_PTR : 0), (n > 0 ? n : 1) * sizeof(double), data, &error);\n\
+		\tCHECK(error);\n\treturn buffer;\n}\n\n\
+		static void print(cl_command_queue queue, cl_mem buffer, size_t rows, size_t cols) {\n\
+		\tdouble* data = malloc((rows * cols > 0 ? rows * cols : 1) * sizeof(double));\n\
+		\tCHECK(clEnqueueReadBuffer(queue, buffer, CL_TRUE, 0, rows * cols * sizeof(double), data, 0, NULL, NULL));\n\
+		\tprintf(\"%d %d\", (int) rows, (int) cols);\n\tfor (size_t k = 0; k < rows * cols; ++k) printf(\" %.17g\", data[k]);\n\
+		\tprintf(\"\\n\");\n\tfree(data);\n}\n\n\
+		int main(void) {\n\tcl_platform_id platform;\n\tcl_device_id device;\n\tcl_uint count = 0;\n\tcl_int error;\n\
+		\tchar extensions[8192];\n\
+		\tif (clGetPlatformIDs(1, &platform, &count) != CL_SUCCESS || count == 0) return 77;\n\
+		\tif (clGetDeviceIDs(platform, CL_DEVICE_TYPE_ALL, 1, &device, NULL) != CL_SUCCESS) return 77;\n\
+		\tCHECK(clGetDeviceInfo(device, CL_DEVICE_EXTENSIONS, sizeof(extensions), extensions, NULL));\n\
+		\tif (!strstr(extensions, \"cl_khr_fp64\")) return 77;\n\
+		\tcl_context context = clCreateContext(NULL, 1, &device, NULL, NULL, &error);\n\tCHECK(error);\n\
+		\tcl_command_queue queue = clCreateCommandQueue(context, device, 0, &error);\n\tCHECK(error);\n\
+		\tFILE* file = fopen(\"kernels.cl\", \"rb\");\n\tfseek(file, 0, SEEK_END);\n\tconst size_t length = (size_t) ftell(file);\n\
+		\tfseek(file, 0, SEEK_SET);\n\tchar* source = calloc(length + 1, 1);\n\tif (fread(source, 1, length, file) != length) return 1;\n\tfclose(file);\n\
+		\tcl_program program = clCreateProgramWithSource(context, 1, (const char**) &source, NULL, &error);\n\tCHECK(error);\n\
+		\tif (clBuildProgram(program, 1, &device, NULL, NULL, NULL) != CL_SUCCESS) {\n\t\tstatic char log[65536];\n\
+		\t\tclGetProgramBuildInfo(program, device, CL_PROGRAM_BUILD_LOG, sizeof(log), log, NULL);\n\
+		\t\tfprintf(stderr, \"%s\\n\", log);\n\t\treturn 1;\n\t}\n".to_string();
+	// Every buffer gets its dimensions, while only the inputs are initialized
+	for name in buffers.iter() {
+		let (rows, cols) = buffer_dims(graph, &values, name);
+		host.push_str(&format!("\tconst size_t {0}_rows = {1}, {0}_cols = {2};\n", name, rows, cols));
+		match ids.iter().position(|id| &format!("n{}", id) == name) {
+			Some(k) => {
+				host.push_str(&format!("\tdouble {}_data[] = {{{}}};\n", name, harness::data(&inputs[k].1)));
+				host.push_str(&format!("\tcl_mem {0} = create(context, {0}_rows * {0}_cols, {0}_data);\n", name));
+			},
+			None => host.push_str(&format!("\tcl_mem {0} = create(context, {0}_rows * {0}_cols, NULL);\n", name))
+		}
+	}
+	for launch in launches.iter() {
+		host.push_str(&format!("\t{{\n\t\tcl_kernel kernel = clCreateKernel(program, \"{}\", &error);\n\t\tCHECK(error);\n", launch.name));
+		for (k, param) in launch.params.iter().enumerate() {
+			if buffers.contains(param) {
+				host.push_str(&format!("\t\tCHECK(clSetKernelArg(kernel, {}, sizeof(cl_mem), &{}));\n", k, param));
+			} else {
+				let value = if param.ends_with("_rows") || param.ends_with("_cols") {
+					param.clone()
+				} else {
+					format!("{}", values[&param[1..].parse::<usize>().unwrap()].data[0] as i64)
+				};
+				host.push_str(&format!("\t\tCHECK(clSetKernelArg(kernel, {}, sizeof(cl_int), &(cl_int){{(cl_int) {}}}));\n", k, value));
+			}
+		}
+		host.push_str(&format!("\t\tconst size_t global[] = {{{}}};\n", launch.global.join(", ")));
+		let local = match launch.local.len() {
+			0 => "NULL".to_string(),
+			_ => {
+				host.push_str(&format!("\t\tconst size_t local[] = {{{}}};\n", launch.local.join(", ")));
+				"local".to_string()
+			}
+		};
+		host.push_str(&format!("\t\tCHECK(clEnqueueNDRangeKernel(queue, kernel, {}, NULL, global, {}, 0, NULL, NULL));\n",
+			launch.global.len(), local));
+		host.push_str("\t\tCHECK(clFinish(queue));\n\t\tclReleaseKernel(kernel);\n\t}\n");
+	}
+	for id in results.iter() {
+		host.push_str(&format!("\tprint(queue, n{0}, n{0}_rows, n{0}_cols);\n", id));
+	}
+	host.push_str("\treturn 0;\n}\n");
+	harness::write_file(dir, "host.c", host.as_bytes());
+	let cc = ::std::env::var("MD_CC").unwrap_or("cc".to_string());
+	harness::write_file(dir, "probe.c", b"#include <CL/cl.h>\nint main(void) {\n\treturn clGetPlatformIDs(0, NULL, NULL) == CL_SUCCESS ? 0 : 1;\n}\n");
+	match Command::new(&cc).arg("-o").arg("probe").arg("probe.c").arg("-lOpenCL").current_dir(dir).output() {
+		Ok(ref output) if output.status.success() => (),
+		_ => return None
+	}
+	match harness::run(Command::new(&cc).arg("-std=c99").arg("-o").arg("host").arg("host.c").arg("-lOpenCL"), dir) {
+		Some(_) => harness::run(&mut Command::new(dir.join("host")), dir),
+		None => None
+	}
+}
+
+fn opencl_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_opencl, "opencl", name, source, inputs, -0.5);
+}
+
+parametarise_test!(opencl_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(w * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"runtime_scalar_dot",
+	"function [L] = mat(s,@v,x)
+	h = s dot v + x dot v;
+	L = sum(h * h, 0);
+	end",
+	vec![("s", 1, 1), ("v", 20, 20), ("x", 20, 20)]
+},{
+	"inverse",
+	"function [L] = mat(@w,x)
+	h = minv(w dot w' + eye(2)) dot x;
+	L = sum(h * h, 0) + sum(mdiag(w dot w'), 0) + sum(quad(w, x), 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2)]
+});
+
+/// Compiles the kernels with clang, given by `MD_OPENCL_CLANG` or found on the path, the same front end a CPU implementation
+/// such as POCL uses. Skipped if there is no such compiler
+fn opencl_compiles(name: &str, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let mut code : Vec<u8> = Vec::new();
+	match meta_diff::codegen::write_opencl(&mut code, &graph) {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let path = ::std::env::temp_dir().join(format!("meta_diff_{}.cl", name));
+	File::create(&path).unwrap().write_all(&code).unwrap();
+	let clang = ::std::env::var("MD_OPENCL_CLANG").unwrap_or("clang".to_string());
+	let output = match Command::new(&clang).arg("-x").arg("cl").arg("-cl-std=CL1.2").arg("-Xclang").arg("-finclude-default-header")
+		.arg("-fsyntax-only").arg(&path).output() {
+		Ok(output) => output,
+		Err(_) => {return println!("Skipped compiling the OpenCL kernels, as {} was not found", clang);}
+	};
+	assert!(output.status.success(), "The kernels do not compile:\n{}\n{}",
+		String::from_utf8_lossy(&output.stderr), String::from_utf8(code).unwrap());
+}
+
+parametarise_test!(opencl_compiles,{
+	"elementwise",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end"
+},{
+	"reduction",
+	"function [L] = mat(@w,x,@z)
+	g = horzcat(w, x);
+	h = sigm(w * x) + rect(z') + g[0,2,1,2];
+	L = sum(h,1) + sum(l1(h, 2), 0);
+	end"
+},{
+	"inverse",
+	"function [L] = mat(@w,x)
+	h = minv(w dot x dot w') + quad(w, mdiag(x) dot ones(1, 2));
+	L = sum(h,2);
+	end"
+});
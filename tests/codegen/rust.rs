@@ -0,0 +1,66 @@
+extern crate meta_diff;
+use std::path::PathBuf;
+use std::process::Command;
+use self::meta_diff::core::{ComputeGraph, Matrix};
+use super::harness;
+
+/// Compiles the code together with a `main` calling it, using the compiler given by `MD_RUSTC` or `rustc` on the path,
+/// then runs it. This is the same code `meta_diff::linking` builds as a library
+fn run_rust(graph: &ComputeGraph, inputs: &Vec<(String, Matrix)>, dir: &PathBuf) -> Option<String> {
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_rust(&mut code, graph).unwrap();
+	let mut driver = String::from_utf8(code).unwrap();
+	driver.push_str("\nfn main() {\n\tlet inputs = vec![");
+	driver.push_str(&inputs.iter().map(|&(_, ref m)| format!("({}, {}, vec![{}])", m.rows, m.cols, harness::data(m)))
+		.collect::<Vec<String>>().join(", "));
+	driver.push_str(&format!("];\n\tfor (rows, cols, data) in {}(inputs) {{\n", graph.name));
+	driver.push_str("\t\tlet data = data.iter().map(|x| format!(\" {:?}\", x)).collect::<Vec<String>>().concat();\n");
+	driver.push_str("\t\tprintln!(\"{} {}{}\", rows, cols, data);\n\t}\n}\n");
+	harness::write_file(dir, "driver.rs", driver.as_bytes());
+	let rustc = ::std::env::var("MD_RUSTC").unwrap_or("rustc".to_string());
+	match harness::run(Command::new(&rustc).arg("-o").arg("driver").arg("driver.rs"), dir) {
+		Some(_) => harness::run(&mut Command::new(dir.join("driver")), dir),
+		None => None
+	}
+}
+
+fn rust_ok(name: &str, source: &str, inputs: Vec<(&str, usize, usize)>){
+	harness::run_ok(run_rust, "rust", name, source, inputs, -0.5);
+}
+
+parametarise_test!(rust_ok,{
+	"concatenation",
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 1), ("y", 2, 1)]
+},{
+	"elementwise",
+	"function [L] = mat(@w,x,@z)
+	h = sigm(minv(w) * x) + rect(z') + max(w, z) - pow(square(x) + 1, w);
+	L = sum(sum(h, 1) + l1(h, 2)', 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 2), ("z", 2, 2)]
+},{
+	"scalar_dot",
+	"function [L] = mat(@z,@w,x)
+	h = sum(z, 0) dot w dot x + w dot x dot l2(z, 0) + 2 dot w dot x;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("z", 2, 2), ("w", 2, 3), ("x", 3, 2)]
+},{
+	"subassign",
+	"function [L] = mat(@w,x)
+	h = horzcat(w,x);
+	L = sum(h[0,2,1,3] * h[1,1,0,3], 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)]
+},{
+	"replicate",
+	"function [L] = mat(@w,x)
+	h = reshape(w, 1, 6) dot ones(6, 2) + sum(x, 1)' dot sum(w, 0);
+	L = sum(h * vertcat(x[0,1,0,2], x[1,1,0,2]), 0);
+	end",
+	vec![("w", 2, 3), ("x", 2, 2)]
+});
@@ -0,0 +1,44 @@
+extern crate meta_diff;
+use std::collections::HashMap;
+use self::meta_diff::core::{Matrix, ComputeGraph};
+
+/// The step of the central finite differences
+pub const EPS: f64 = 1e-6;
+
+/// Creates the inputs with dimensions given by the names, filled with deterministic values in [low, low + 1]
+pub fn to_inputs(inputs: &Vec<(&str, usize, usize)>, low: f64) -> HashMap<String, Matrix> {
+	let mut k = 0;
+	inputs.iter().map(|&(name, rows, cols)| {
+		let data = (0..rows * cols).map(|_| {k += 1; low + ((k * 37) % 101) as f64 / 100.0}).collect();
+		(name.to_string(), Matrix::new(rows, cols, data))
+	}).collect()
+}
+
+/// Creates a direction which changes only element `k` of the input `name`
+pub fn unit_direction(values: &HashMap<String, Matrix>, name: &str, k: usize) -> HashMap<String, Matrix> {
+	let mut unit = Matrix::constant(values[name].rows, values[name].cols, 0.0);
+	unit.data[k] = 1.0;
+	let mut direction = HashMap::new();
+	direction.insert(name.to_string(), unit);
+	direction
+}
+
+/// Evaluates the derivatives of all nodes of the graph along the direction of the inputs with central finite differences
+pub fn central_differences(graph: &ComputeGraph, values: &HashMap<String, Matrix>, direction: &HashMap<String, Matrix>)
+	-> HashMap<usize, Matrix> {
+	let mut shifted = vec![values.clone(), values.clone()];
+	for (k, sign) in [1.0, -1.0].iter().enumerate() {
+		for (name, d) in direction.iter() {
+			let x = shifted[k].get_mut(name).unwrap();
+			for (a, b) in x.data.iter_mut().zip(d.data.iter()) {
+				*a += sign * EPS * b;
+			}
+		}
+	}
+	let plus = meta_diff::core::eval(graph, &shifted[0]).unwrap();
+	let minus = meta_diff::core::eval(graph, &shifted[1]).unwrap();
+	plus.iter().map(|(id, p)| {
+		let data = p.data.iter().zip(minus[id].data.iter()).map(|(a, b)| (a - b) / (2.0 * EPS)).collect();
+		(*id, Matrix::new(p.rows, p.cols, data))
+	}).collect()
+}
@@ -0,0 +1,122 @@
+extern crate meta_diff;
+use std::collections::HashMap;
+use self::meta_diff::core::Matrix;
+
+fn to_inputs(inputs: Vec<(&str, usize, usize, Vec<f64>)>) -> HashMap<String, Matrix> {
+	inputs.into_iter().map(|(name, rows, cols, data)| (name.to_string(), Matrix::new(rows, cols, data))).collect()
+}
+
+fn eval_values(source: &str, inputs: Vec<(&str, usize, usize, Vec<f64>)>, expected: Vec<(usize, usize, Vec<f64>)>){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let values = match meta_diff::core::eval(&graph, &to_inputs(inputs)) {
+		Ok(values) => values,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	// The outputs followed by the gradients of the parameters
+	let (grads, _) = graph.get_params();
+	for (id, &(rows, cols, ref data)) in graph.outputs.iter().chain(grads.iter()).zip(expected.iter()) {
+		let value = &values[id];
+		assert!(value.rows == rows && value.cols == cols, "Expected dimensions of node {} to be ({}, {}), was: ({}, {})",
+			id, rows, cols, value.rows, value.cols);
+		for (x, y) in value.data.iter().zip(data.iter()) {
+			assert!((x - y).abs() < 1e-10, "Expected value of node {} to be {:?}, was: {:?}", id, data, value.data);
+		}
+	}
+}
+
+fn eval_optimized(source: &str, inputs: Vec<(&str, usize, usize, Vec<f64>)>){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let inputs = to_inputs(inputs);
+	let before = match meta_diff::core::eval(&graph, &inputs) {
+		Ok(values) => values,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let (grads_before, _) = graph.get_params();
+	let targets_before = graph.outputs.iter().chain(grads_before.iter()).cloned().collect::<Vec<usize>>();
+	match meta_diff::optimization::Pipeline::with_level(2).run(&mut graph) {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let after = match meta_diff::core::eval(&graph, &inputs) {
+		Ok(values) => values,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let (grads_after, _) = graph.get_params();
+	let targets_after = graph.outputs.iter().chain(grads_after.iter()).cloned().collect::<Vec<usize>>();
+	for (i, j) in targets_before.iter().zip(targets_after.iter()) {
+		let (x, y) = (&before[i], &after[j]);
+		assert!(x.rows == y.rows && x.cols == y.cols && x.data.iter().zip(y.data.iter()).all(|(a, b)| (a - b).abs() < 1e-10),
+			"Value of node {} changed after optimization from {:?} to {:?}", i, x, y);
+	}
+}
+
+#[test]
+fn eval_missing_input(){
+	let graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end").unwrap();
+	match meta_diff::core::eval(&graph, &to_inputs(vec![("w", 1, 2, vec![1.0, 2.0])])) {
+		Ok(_) => assert!(false, "Evaluated a graph with a missing input"),
+		Err(msg) => assert!(format!("{}", msg) == "MissingInput: No value given for x", "{}", msg)
+	}
+}
+
+parametarise_test!(eval_values,{
+	"function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end",
+	vec![("w", 1, 2, vec![1.0, 2.0]), ("x", 2, 1, vec![3.0, 4.0])],
+	vec![(1, 1, vec![11.0]), (1, 2, vec![3.0, 4.0])]
+},{
+	"function [L] = mat(@w,x)
+	L = sum(square(w), 0) + sum(exp(x), 0);
+	end",
+	vec![("w", 2, 1, vec![1.0, 2.0]), ("x", 1, 1, vec![0.0])],
+	vec![(1, 1, vec![6.0]), (2, 1, vec![2.0, 4.0])]
+},{
+	"function [L] = mat(@w,x)
+	h = vertcat(w, x);
+	L = sum(h[1,2,0,1] * 3, 0);
+	end",
+	vec![("w", 1, 1, vec![2.0]), ("x", 2, 1, vec![5.0, 7.0])],
+	vec![(1, 1, vec![36.0]), (1, 1, vec![0.0])]
+});
+
+parametarise_test!(eval_optimized,{
+	"function [L] = mat(@w,x)
+	h = -(w + -x) * -(x dot w) * -w / x / (w + 3);
+	g = (-w) dot (-x) dot (-h) + -(-h - x);
+	L = sum(g / w / x * 2, 0) - sum(-(h + g), 0);
+	end",
+	vec![("w", 2, 2, vec![0.5, -1.0, 2.0, 1.5]), ("x", 2, 2, vec![1.0, 0.5, -0.5, 2.0])]
+},{
+	"function [L] = mat(@w,x)
+	h = vertcat(w, x dot w, x * w);
+	k = h[1,4,0,2];
+	c = horzcat(w, x);
+	g = k[1,2,0,2] + h[rows(w),rows(x),0,cols(w)] + c[0,rows(x),cols(w),cols(x)];
+	L = sum(g * g, 0) + sum(log(exp(h)), 0);
+	end",
+	vec![("w", 2, 2, vec![0.5, -1.0, 2.0, 1.5]), ("x", 2, 2, vec![1.0, 0.5, -0.5, 2.0])]
+},{
+	"function [L] = mat(@w,@b,x)
+	h = sigm(w dot x + b);
+	L = sum(h * log(h) + (1 - h) * log(1 - h), 0) + sum(w dot w', 0);
+	end",
+	vec![("w", 2, 3, vec![0.5, -1.0, 2.0, 1.5, 0.1, 0.3]), ("b", 2, 1, vec![0.2, -0.4]),
+		("x", 3, 2, vec![1.0, 0.5, -0.5, 2.0, 0.7, -1.2])]
+});
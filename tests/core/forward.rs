@@ -0,0 +1,93 @@
+extern crate meta_diff;
+use std::collections::HashMap;
+use super::common::{to_inputs, central_differences};
+
+fn forward_ok(source: &str, inputs: Vec<(&str, usize, usize)>, low: f64, gradient: bool){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	if gradient {
+		match graph.direct_gradient() {
+			Ok(_) => (),
+			Err(msg) => {return assert!(false, "{}", msg);}
+		}
+	}
+	let mut targets = graph.outputs.clone();
+	targets.extend(graph.get_params().0);
+	// A tangent input with a "_t" suffix for each parameter
+	let params = graph.nodes.iter().filter_map(|x| x.as_ref())
+		.filter(|x| x.node_type == meta_diff::core::Type::Parameter)
+		.map(|x| (x.id, x.name.clone(), x.dims.clone())).collect::<Vec<_>>();
+	let mut tangents : HashMap<usize, usize> = HashMap::new();
+	for &(id, ref name, ref dims) in params.iter() {
+		let t = graph.add_const_input(name.clone() + "_t");
+		graph.get_mut_node(t).unwrap().dims = dims.clone();
+		tangents.insert(id, t);
+	}
+	let tangents = match graph.forward_gradient(tangents) {
+		Ok(t) => t,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let mut values = to_inputs(&inputs, low);
+	for &(_, ref name, _) in params.iter() {
+		let mut t = values[name].clone();
+		t.data = t.data.iter().rev().map(|x| x - low - 0.5).collect();
+		values.insert(name.clone() + "_t", t);
+	}
+	let jvp = meta_diff::core::eval(&graph, &values).unwrap();
+	// Central finite differences along the tangents
+	let directions = params.iter().map(|&(_, ref name, _)| (name.clone(), values[&(name.clone() + "_t")].clone())).collect();
+	let numeric = central_differences(&graph, &values, &directions);
+	for target in targets.iter() {
+		let tangent = match tangents.get(target) {
+			Some(x) => &jvp[x],
+			None => {return assert!(false, "No tangent for the target {}", target);}
+		};
+		for (i, numeric) in numeric[target].data.iter().cloned().enumerate() {
+			// A tangent may be broadcast to its target
+			let value = tangent.data[if tangent.data.len() == 1 {0} else {i}];
+			assert!((value - numeric).abs() < 1e-5 * numeric.abs().max(1.0),
+				"Expected element {} of the tangent of {} to be {}, was: {}", i, target, numeric, value);
+		}
+	}
+}
+
+parametarise_test!(forward_ok,{
+	"function [L] = mat(@w,@v,x)
+	h = cos(w) + sin(v) + tan(x * w) + cosh(w) - sinh(v) * tanh(w) + abs(w - v) + exp(v) / w;
+	L = sigm(h) + rect(h - v) + square(h) + log(w) * sqrt(v);
+	end",
+	vec![("w", 2, 2), ("v", 2, 2), ("x", 2, 2)], 0.5, false
+},{
+	"function [L] = mat(@w,@v,x)
+	m = minv(v' dot v + eye(3));
+	h = quad(w, v) * m[0,2,0,2] + max(x[0,2,0,2], w[0,2,0,2]) - min(x[1,2,0,2], pow(w[1,2,0,2], 2));
+	g = horzcat(h, x[0,2,0,1]) dot vertcat(v[0,2,0,2], w[0,1,0,2]) dot vdiag(mdiag(w[0,2,0,2]));
+	r = reshape(h, 1, 4);
+	L = sum(l2(g, 1) + l1(r[0,1,0,2], 1), 0);
+	end",
+	vec![("w", 3, 2), ("v", 3, 3), ("x", 3, 2)], 0.5, false
+},{
+	"function [L] = mat(@w,@v,x)
+	h = tanh(w dot x + v[0,2,0,1]);
+	L = sum(h * log(sigm(h)), 0) + sum(square(h dot w), 0);
+	end",
+	vec![("w", 2, 3), ("v", 2, 1), ("x", 3, 2)], -0.5, true
+},{
+	"function [L] = mat(@a,x)
+	L = sum(a + x, 0) + sum(exp(a - x), 1);
+	end",
+	vec![("a", 1, 1), ("x", 2, 2)], -0.5, false
+},{
+	"function [L] = mat(@w,@v,x)
+	a = v[0,1,0,1];
+	h = tanh(w dot x + a);
+	L = sum(h + a, 0) + sum(a + x[0,2,0,2], 0) * sum(h, 0);
+	end",
+	vec![("w", 2, 3), ("v", 2, 2), ("x", 3, 2)], -0.5, true
+});
@@ -0,0 +1,88 @@
+extern crate meta_diff;
+use super::common::to_inputs;
+
+fn gradcheck_ok(source: &str, inputs: Vec<(&str, usize, usize)>, low: f64){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	match meta_diff::core::gradcheck(&graph, &to_inputs(&inputs, low), 1e-6, 1e-6) {
+		Ok(checks) => {
+			assert!(checks.len() == graph.get_params().0.len(), "Not all parameters were checked");
+			for check in checks.iter() {
+				assert!(check.passed, "Gradient of {} has a relative error of {} at element {}",
+					check.name, check.max_error, check.worst_element);
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+#[test]
+fn gradcheck_wrong_gradient(){
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(square(w) * x, 0);
+	end").unwrap();
+	graph.direct_gradient().unwrap();
+	// Replace the gradient of w with x
+	let x = graph.nodes.iter().filter_map(|n| n.as_ref()).find(|n| n.name == "x").unwrap().id;
+	graph.get_mut_node(0).unwrap().grad_child = Some(x);
+	let checks = meta_diff::core::gradcheck(&graph, &to_inputs(&vec![("w", 2, 2), ("x", 2, 2)], -0.5), 1e-6, 1e-6).unwrap();
+	assert!(checks.len() == 1 && !checks[0].passed, "The wrong gradient passed the check: {:?}", checks);
+}
+
+#[test]
+fn gradcheck_no_outputs(){
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(w * x, 0);
+	end").unwrap();
+	graph.direct_gradient().unwrap();
+	graph.outputs.clear();
+	match meta_diff::core::gradcheck(&graph, &to_inputs(&vec![("w", 2, 2), ("x", 2, 2)], -0.5), 1e-6, 1e-6) {
+		Err(meta_diff::core::EvalError::Graph(meta_diff::core::GraphError::NoOutputs)) => (),
+		other => assert!(false, "Expected a NoOutputs error, got {:?}", other)
+	}
+}
+
+parametarise_test!(gradcheck_ok,{
+	"function [L] = mat(@w,@v,x)
+	h = cos(w) + sin(v) + tan(x * w) + cosh(w dot v) - sinh(v) * tanh(w) + abs(w - v);
+	L = sum(exp(h) * square(h) + sigm(h) + rect(h - v), 0);
+	end",
+	vec![("w", 2, 2), ("v", 2, 2), ("x", 2, 2)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	h = log(w) * sqrt(v) + pow(w, v) / w - v;
+	L = sum(max(h, x) + min(x, w * v) + h, 0);
+	end",
+	vec![("w", 2, 2), ("v", 2, 2), ("x", 2, 2)], 0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	h = quad(w, v) * sum(minv(v' dot v + eye(3)) dot mdiag(v dot v'), 0);
+	L = sum(l2(h, 1), 0) + sum(l1(h, 2), 0) + sum(vdiag(x) dot w, 0);
+	end",
+	vec![("w", 3, 2), ("v", 3, 3), ("x", 3, 1)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	h = w dot v dot x dot w dot v + w * w dot w;
+	g = horzcat(h, x) dot vertcat(v, w);
+	L = sum(g[0,1,0,2] + reshape(h, 1, 4) dot ones(4, 2), 0) + sum(g[1,1,0,2] * h[0,1,0,1], 0);
+	end",
+	vec![("w", 2, 2), ("v", 2, 2), ("x", 2, 2)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	h = sigm(w dot x + v[0,2,0,1]);
+	L = sum(h * log(h) + (1 - h) * log(1 - h), 0) + sum(w dot v[0,1,0,1], 0);
+	end",
+	vec![("w", 2, 3), ("v", 2, 1), ("x", 3, 2)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	h = sum(v, 0) dot w dot x dot l2(v, 0) + w dot x dot 2 + w dot sum(x, 0) dot v;
+	L = sum(sigm(h), 0);
+	end",
+	vec![("w", 2, 3), ("v", 3, 2), ("x", 3, 2)], -0.5
+});
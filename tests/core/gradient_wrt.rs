@@ -0,0 +1,74 @@
+extern crate meta_diff;
+use super::common::{to_inputs, unit_direction, central_differences};
+
+/// Checks the gradients of the listed inputs with central finite differences, while the rest must have none.
+/// When `fewer` is set the graph must be smaller than the one after a gradient with respect to all parameters
+fn gradient_wrt_ok(source: &str, wrt: Vec<&str>, inputs: Vec<(&str, usize, usize)>, fewer: bool){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let leaves = graph.nodes.iter().filter_map(|x| x.as_ref()).filter(|x| x.node_type == meta_diff::core::Type::Parameter
+		|| x.node_type == meta_diff::core::Type::ConstInput).map(|x| (x.id, x.name.clone())).collect::<Vec<_>>();
+	let ids = leaves.iter().filter(|&&(_, ref name)| wrt.contains(&&name[..])).map(|&(id, _)| id).collect::<Vec<usize>>();
+	let mut full = graph.clone();
+	full.direct_gradient().unwrap();
+	let target = graph.outputs[0];
+	match graph.gradient_wrt(target, &ids) {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	if fewer {
+		assert!(graph.len() < full.len(), "Expected fewer than {} nodes, was: {}", full.len(), graph.len());
+	}
+	let values = to_inputs(&inputs, 0.5);
+	let result = meta_diff::core::eval(&graph, &values).unwrap();
+	for &(id, ref name) in leaves.iter() {
+		let grad = graph.get_node(id).unwrap().grad_child;
+		if !ids.contains(&id) {
+			assert!(grad.is_none(), "Expected no gradient for {}, was: {:?}", name, grad);
+			continue
+		}
+		let grad = &result[&grad.unwrap()];
+		assert!(grad.data.len() == values[name].data.len(), "Expected the gradient of {} to have the same dimensions", name);
+		for k in 0..grad.data.len() {
+			let numeric = central_differences(&graph, &values, &unit_direction(&values, name, k))[&target].data[0];
+			assert!((grad.data[k] - numeric).abs() < 1e-5 * numeric.abs().max(1.0),
+				"Expected element {} of the gradient of {} to be {}, was: {}", k, name, numeric, grad.data[k]);
+		}
+	}
+}
+
+parametarise_test!(gradient_wrt_ok,{
+	"function [L] = mat(@w,x)
+	L = sum(sigm(w dot x), 0);
+	end",
+	vec!["x"], vec![("w", 2, 3), ("x", 3, 1)], false
+},{
+	"function [L] = mat(@w,@v,x)
+	L = sum(tanh(w dot x), 0) + sum(exp(v) * sin(v), 0);
+	end",
+	vec!["w"], vec![("w", 2, 3), ("v", 2, 2), ("x", 3, 2)], true
+},{
+	"function [L] = mat(@w,@v,x)
+	L = sum(square(w dot x) * sigm(v), 0);
+	end",
+	vec!["w", "x"], vec![("w", 2, 3), ("v", 2, 2), ("x", 3, 2)], true
+});
+
+#[test]
+fn gradient_wrt_constant(){
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(square(w) * x, 0);
+	end").unwrap();
+	let target = graph.outputs[0];
+	let constant = graph.add_int(3);
+	match graph.gradient_wrt(target, &[constant]) {
+		Ok(_) => assert!(false, "Expected the gradient with respect to a constant to fail"),
+		Err(msg) => assert_eq!(format!("{}", msg), format!("Can not take a gradient with respect to a cosntant node - {}", constant))
+	}
+}
@@ -0,0 +1,65 @@
+extern crate meta_diff;
+use super::common::{to_inputs, central_differences};
+
+fn hvp_ok(source: &str, inputs: Vec<(&str, usize, usize)>, gradient: bool){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	if gradient {
+		match graph.direct_gradient() {
+			Ok(_) => (),
+			Err(msg) => {return assert!(false, "{}", msg);}
+		}
+	}
+	let target = graph.outputs[0];
+	let info = match graph.hessian_vector_product(target) {
+		Ok(info) => info,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let (grads, names) = graph.get_params();
+	assert!(info.params.len() == grads.len() && info.vectors.len() == grads.len() && info.products.len() == grads.len(),
+		"Expected {} parameters, vectors and products, were: {:?}", grads.len(), info);
+	// The vectors are the names of the parameters with a "_v" suffix
+	let mut values = to_inputs(&inputs, -0.5);
+	for name in names.iter() {
+		let mut v = values[name].clone();
+		v.data = v.data.iter().rev().cloned().collect();
+		values.insert(name.clone() + "_v", v);
+	}
+	let hv = meta_diff::core::eval(&graph, &values).unwrap();
+	// Central finite differences of the gradients along the vectors
+	let vectors = names.iter().map(|name| (name.clone(), values[&(name.clone() + "_v")].clone())).collect();
+	let numeric = central_differences(&graph, &values, &vectors);
+	for (grad, product) in grads.iter().zip(info.products.iter()) {
+		let value = &hv[product];
+		assert!(value.rows == numeric[grad].rows && value.cols == numeric[grad].cols, "Expected dimensions of the product to be ({}, {}), was: ({}, {})",
+			numeric[grad].rows, numeric[grad].cols, value.rows, value.cols);
+		for (i, x) in value.data.iter().enumerate() {
+			let numeric = numeric[grad].data[i];
+			assert!((x - numeric).abs() < 1e-5 * numeric.abs().max(1.0), "Expected product of node {} to be {}, was: {}", product, numeric, x);
+		}
+	}
+}
+
+parametarise_test!(hvp_ok,{
+	"function [L] = mat(@w,x)
+	L = sum(square(w dot x), 0);
+	end",
+	vec![("w", 2, 3), ("x", 3, 2)], true
+},{
+	"function [L] = mat(@w,@v,x)
+	h = tanh(w dot x) * sigm(v);
+	L = sum(h * h, 0) + sum(exp(v), 0);
+	end",
+	vec![("w", 2, 2), ("v", 2, 2), ("x", 2, 2)], false
+},{
+	"function [L] = mat(@w,@v,x)
+	L = sum(w dot x, 0) + sum(log(square(v) + 1), 0);
+	end",
+	vec![("w", 1, 2), ("v", 2, 2), ("x", 2, 1)], true
+});
@@ -0,0 +1,71 @@
+extern crate meta_diff;
+use std::collections::HashMap;
+use self::meta_diff::core::Matrix;
+
+/// Takes the third order gradient of the target with respect to `w` and compares it with the closed form,
+/// given the values of `w` and `v`, while `v` must stay linked to its first order gradient
+fn third_order_ok<F: Fn(f64, f64) -> f64>(source: &str, closed_form: F, low: f64){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let param = |graph: &meta_diff::core::ComputeGraph, name: &str| graph.nodes.iter().filter_map(|x| x.as_ref())
+		.filter(|x| x.node_type == meta_diff::core::Type::Parameter && x.name == name).next().unwrap().id;
+	let (w, v) = (param(&graph, "w"), param(&graph, "v"));
+	let v_grad = graph.get_node(v).unwrap().grad_child;
+	// The sum of an elementwise gradient is differentiated again
+	for _ in 0..2 {
+		let grad = graph.get_node(w).unwrap().grad_child.unwrap();
+		let target = graph.add_operation(meta_diff::core::OPERATOR_SUM_ALL, vec![grad]).unwrap();
+		match graph.gradient_wrt(target, &[w]) {
+			Ok(_) => (),
+			Err(msg) => {return assert!(false, "{}", msg);}
+		}
+	}
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	assert_eq!(graph.levels.len(), 3);
+	assert_eq!(graph.levels[2].wrt, vec![w]);
+	assert_eq!(graph.get_node(v).unwrap().grad_child, v_grad);
+	let third = graph.get_node(w).unwrap().grad_child.unwrap();
+	assert_eq!(graph.get_node(third).unwrap().grad_level, 3);
+	let mut inputs : HashMap<String, Matrix> = HashMap::new();
+	inputs.insert("w".to_string(), Matrix::new(2, 2, vec![low, low + 0.3, low + 0.5, low + 0.9]));
+	inputs.insert("v".to_string(), Matrix::new(2, 2, vec![low + 0.7, low + 0.2, low + 0.4, low + 0.1]));
+	let values = meta_diff::core::eval(&graph, &inputs).unwrap();
+	for k in 0..4 {
+		let expected = closed_form(inputs["w"].data[k], inputs["v"].data[k]);
+		let value = values[&third].data[k];
+		assert!((value - expected).abs() < 1e-9 * expected.abs().max(1.0),
+			"Expected element {} of the third order gradient to be {}, was: {}", k, expected, value);
+	}
+	// A cluster for each of the levels
+	let mut graphviz : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_graphviz(&mut graphviz, &graph).unwrap();
+	let graphviz = String::from_utf8(graphviz).unwrap();
+	assert!(graphviz.contains("subgraph cluster_3{") && !graphviz.contains("subgraph cluster_4{"),
+		"Expected exactly four clusters in:\n{}", graphviz);
+}
+
+parametarise_test!(third_order_ok,{
+	"function [L] = mat(@w,@v)
+	L = sum(pow(w, 4) + v * w, 0);
+	end",
+	|w: f64, _: f64| 24.0 * w, 0.5
+},{
+	"function [L] = mat(@w,@v)
+	L = sum(exp(w) * sin(v), 0);
+	end",
+	|w: f64, v: f64| w.exp() * v.sin(), -0.5
+},{
+	"function [L] = mat(@w,@v)
+	L = sum(log(w) * square(v), 0);
+	end",
+	|w: f64, v: f64| 2.0 * v * v / (w * w * w), 0.5
+});
@@ -0,0 +1,87 @@
+extern crate meta_diff;
+use super::common::{to_inputs, unit_direction, central_differences};
+
+/// The first output of the source is differentiated with respect to the second,
+/// which must be either the parameter `w` or its top left block
+fn jacobian_ok(source: &str, inputs: Vec<(&str, usize, usize)>, low: f64, reverse: bool){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let (output, wrt) = (graph.outputs[0], graph.outputs[1]);
+	let jacobian = match graph.jacobian(output, wrt) {
+		Ok(j) => j,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let values = to_inputs(&inputs, low);
+	let result = meta_diff::core::eval(&graph, &values).unwrap();
+	let (y, u, j) = (&result[&output], &result[&wrt], &result[&jacobian]);
+	// A single row or column is not assembled
+	if (reverse && y.data.len() > 1) || (!reverse && u.data.len() > 1) {
+		let expected = if reverse {meta_diff::core::OPERATOR_VERTCAT} else {meta_diff::core::OPERATOR_HORZCAT};
+		assert!(graph.get_node(jacobian).unwrap().op.op_type == expected,
+			"Expected the Jacobian to be assembled with {:?}, was: {:?}", expected, graph.get_node(jacobian).unwrap().op.op_type);
+	}
+	assert!(j.rows == y.data.len() && j.cols == u.data.len(),
+		"Expected the Jacobian to have dimensions ({}, {}), was: ({}, {})", y.data.len(), u.data.len(), j.rows, j.cols);
+	// Central finite differences along each element of wrt
+	for col in 0..u.data.len() {
+		let k = col % u.rows + (col / u.rows) * values["w"].rows;
+		let numeric = central_differences(&graph, &values, &unit_direction(&values, "w", k));
+		for row in 0..y.data.len() {
+			let numeric = numeric[&output].data[row];
+			let value = j.get(row, col);
+			assert!((value - numeric).abs() < 1e-5 * numeric.abs().max(1.0),
+				"Expected element ({}, {}) of the Jacobian to be {}, was: {}", row, col, numeric, value);
+		}
+	}
+}
+
+parametarise_test!(jacobian_ok,{
+	"function [y,w] = mat(@w,x)
+	h = w[0,2,0,3] dot x[0,3,0,1];
+	y = tanh(h) + square(h) * exp(w[1,1,0,1]);
+	end",
+	vec![("w", 3, 3), ("x", 3, 4)], -0.5, true
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = sigm(u dot x) + u * log(square(x) + 1);
+	end",
+	vec![("w", 3, 2), ("x", 1, 3)], 0.5, false
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,1,0,2];
+	y = x[0,2,0,2] + sum(square(u), 0);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)], 0.5, false
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,1,0,1];
+	y = sum(u + x[0,2,0,2], 1);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)], 0.5, false
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = sum(u * x[0,2,0,3] + u, 2);
+	end",
+	vec![("w", 2, 2), ("x", 2, 3)], 0.5, true
+});
+
+#[test]
+fn jacobian_unknown_size(){
+	let source = "function [y,w] = mat(@w,x)
+	y = tanh(w dot x);
+	end";
+	let mut graph = meta_diff::core::parseMetaFile(source).unwrap();
+	let (output, wrt) = (graph.outputs[0], graph.outputs[1]);
+	match graph.jacobian(output, wrt) {
+		Ok(_) => assert!(false, "Expected the Jacobian to fail, as neither size is known"),
+		Err(msg) => assert_eq!(format!("{}", msg), format!("UnknownSize: neither node {} nor node {} has a known number of elements", output, wrt))
+	}
+}
@@ -1,2 +1,13 @@
+pub mod common;
 mod parser;
 mod gradient;
+mod shapes;
+mod verify;
+mod eval;
+mod gradcheck;
+mod hessian;
+mod forward;
+mod jacobian;
+mod higher_order;
+mod gradient_wrt;
+mod registry;
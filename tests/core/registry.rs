@@ -0,0 +1,217 @@
+extern crate meta_diff;
+use self::meta_diff::core::{Matrix, ComputeGraph, GraphError, CustomOperator, Pair, SymPolynomial};
+use std::sync::{Once, ONCE_INIT};
+use super::common::{to_inputs, unit_direction, central_differences};
+
+fn softplus_shape(dims: &Vec<Pair<SymPolynomial>>) -> Result<Pair<SymPolynomial>, String> {
+	Ok(dims[0].clone())
+}
+
+fn softplus_gradient(graph: &mut ComputeGraph, child: usize, grad: usize, _: usize) -> Result<usize, GraphError> {
+	let parent = try!(graph.get_node(child)).op.parents[0];
+	let sigm = try!(graph.add_operation(meta_diff::core::OPERATOR_SIGM, vec![parent]));
+	graph.add_operation(meta_diff::core::OPERATOR_MUL, vec![sigm, grad])
+}
+
+fn softplus_tangent(graph: &mut ComputeGraph, child: usize, tangents: &Vec<Option<usize>>) -> Result<usize, GraphError> {
+	let parent = try!(graph.get_node(child)).op.parents[0];
+	let sigm = try!(graph.add_operation(meta_diff::core::OPERATOR_SIGM, vec![parent]));
+	graph.add_operation(meta_diff::core::OPERATOR_MUL, vec![sigm, tangents[0].unwrap()])
+}
+
+fn softplus_value(p: &Vec<&Matrix>) -> Result<Matrix, String> {
+	Ok(p[0].map(|x| (1.0 + x.exp()).ln()))
+}
+
+fn outer_shape(dims: &Vec<Pair<SymPolynomial>>) -> Result<Pair<SymPolynomial>, String> {
+	if !dims[0].1.may_equal(&dims[1].1) {
+		return Err("The parents of outer must have the same number of columns".to_string())
+	}
+	Ok((dims[0].0.clone(), dims[1].0.clone()))
+}
+
+fn outer_gradient(graph: &mut ComputeGraph, child: usize, grad: usize, index: usize) -> Result<usize, GraphError> {
+	let parents = try!(graph.get_node(child)).op.parents.clone();
+	if index == 0 {
+		graph.add_operation(meta_diff::core::OPERATOR_DOT, vec![grad, parents[1]])
+	} else {
+		let transpose = try!(graph.add_operation(meta_diff::core::OPERATOR_TRANSPOSE, vec![grad]));
+		graph.add_operation(meta_diff::core::OPERATOR_DOT, vec![transpose, parents[0]])
+	}
+}
+
+fn outer_value(p: &Vec<&Matrix>) -> Result<Matrix, String> {
+	p[0].dot(&p[1].transpose())
+}
+
+static REGISTER: Once = ONCE_INIT;
+
+/// Registers `softplus` and `outer`, where only `softplus` has a tangent rule, once for all tests
+fn register() {
+	REGISTER.call_once(|| {
+		let mut softplus = CustomOperator::new("softplus", 1, softplus_shape, softplus_gradient);
+		softplus.tangent = Some(softplus_tangent);
+		softplus.value = Some(softplus_value);
+		softplus.code.insert("rust".to_string(), "{0}.map(|x| (1.0 + x.exp()).ln())".to_string());
+		meta_diff::core::register_operator(softplus).unwrap();
+		let mut outer = CustomOperator::new("outer", 2, outer_shape, outer_gradient);
+		outer.value = Some(outer_value);
+		meta_diff::core::register_operator(outer).unwrap();
+	});
+}
+
+fn custom_gradcheck_ok(source: &str, inputs: Vec<(&str, usize, usize)>, low: f64){
+	register();
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	match meta_diff::core::gradcheck(&graph, &to_inputs(&inputs, low), 1e-6, 1e-6) {
+		Ok(checks) => {
+			assert!(checks.len() == graph.get_params().0.len(), "Not all parameters were checked");
+			for check in checks.iter() {
+				assert!(check.passed, "Gradient of {} has a relative error of {} at element {}",
+					check.name, check.max_error, check.worst_element);
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(custom_gradcheck_ok,{
+	"function [L] = mat(@w,x)
+	L = sum(softplus(w dot x), 0);
+	end",
+	vec![("w", 2, 3), ("x", 3, 2)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	L = sum(outer(w, v) * x, 0);
+	end",
+	vec![("w", 2, 3), ("v", 3, 3), ("x", 2, 3)], -0.5
+},{
+	"function [L] = mat(@w,@v,x)
+	L = sum(softplus(outer(w, x) + v) * v, 0);
+	end",
+	vec![("w", 2, 3), ("v", 2, 2), ("x", 2, 3)], 0.5
+});
+
+/// The first output of the source is differentiated with respect to `u`, the second output, which must be the first column of `w`.
+/// Operators without a tangent rule must lead to reverse sweeps
+fn custom_jacobian_ok(source: &str, inputs: Vec<(&str, usize, usize)>, reverse: bool){
+	register();
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let (output, wrt) = (graph.outputs[0], graph.outputs[1]);
+	let jacobian = match graph.jacobian(output, wrt) {
+		Ok(j) => j,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	let expected = if reverse {meta_diff::core::OPERATOR_VERTCAT} else {meta_diff::core::OPERATOR_HORZCAT};
+	assert!(graph.get_node(jacobian).unwrap().op.op_type == expected,
+		"Expected the Jacobian to be assembled with {:?}, was: {:?}", expected, graph.get_node(jacobian).unwrap().op.op_type);
+	let values = to_inputs(&inputs, -0.5);
+	let j = meta_diff::core::eval(&graph, &values).unwrap()[&jacobian].clone();
+	for col in 0..j.cols {
+		let numeric = central_differences(&graph, &values, &unit_direction(&values, "w", col));
+		for row in 0..j.rows {
+			let numeric = numeric[&output].data[row];
+			assert!((j.get(row, col) - numeric).abs() < 1e-5 * numeric.abs().max(1.0),
+				"Expected element ({}, {}) of the Jacobian to be {}, was: {}", row, col, numeric, j.get(row, col));
+		}
+	}
+}
+
+parametarise_test!(custom_jacobian_ok,{
+	"function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = softplus(u dot x) * x;
+	end",
+	vec![("w", 2, 2), ("x", 1, 3)], false
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = outer(u, x[0,3,0,1]);
+	end",
+	vec![("w", 2, 2), ("x", 3, 2)], true
+},{
+	"function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = softplus(outer(u, x[0,3,0,1]) + u);
+	end",
+	vec![("w", 2, 2), ("x", 3, 2)], true
+});
+
+#[test]
+fn custom_jacobian_no_tangent(){
+	register();
+	let mut graph = meta_diff::core::parseMetaFile("function [y,u] = mat(@w,x)
+	u = w[0,2,0,1];
+	y = outer(u, x);
+	end").unwrap();
+	let (output, wrt) = (graph.outputs[0], graph.outputs[1]);
+	match graph.jacobian(output, wrt) {
+		Ok(_) => assert!(false, "Expected the Jacobian to fail, as the size of the output is not known"),
+		Err(msg) => assert_eq!(format!("{}", msg), format!("NoTangent: node {} has an unknown number of elements and depends on node {} \
+			through a custom operator without a tangent rule", output, wrt))
+	}
+}
+
+#[test]
+fn custom_taken_name(){
+	register();
+	for name in vec!["sin", "softplus"] {
+		let op = CustomOperator::new(name, 1, softplus_shape, softplus_gradient);
+		match meta_diff::core::register_operator(op) {
+			Ok(_) => assert!(false, "Expected registering {} to fail", name),
+			Err(msg) => assert_eq!(format!("{}", msg), format!("CustomOperator: The name {} is already used by another function", name))
+		}
+	}
+}
+
+#[test]
+fn custom_shape_mismatch(){
+	register();
+	match meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(outer(w[0,2,0,3], x[0,2,0,2]), 0);
+	end") {
+		Ok(_) => assert!(false, "Expected the parents of outer to be incompatible"),
+		Err(msg) => assert!(format!("{}", msg).contains("The parents of outer must have the same number of columns"),
+			"Unexpected error: {}", msg)
+	}
+}
+
+#[test]
+fn custom_codegen(){
+	register();
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(softplus(w dot x), 0);
+	end").unwrap();
+	graph.direct_gradient().unwrap();
+	let mut code : Vec<u8> = Vec::new();
+	meta_diff::codegen::write_rust(&mut code, &graph).unwrap();
+	let code = String::from_utf8(code).unwrap();
+	assert!(code.contains(".map(|x| (1.0 + x.exp()).ln())"), "Generated code does not contain softplus:\n{}", code);
+	let mut code : Vec<u8> = Vec::new();
+	match meta_diff::codegen::write_numpy(&mut code, &graph) {
+		Ok(_) => assert!(false, "Expected the numpy code generation to fail"),
+		Err(msg) => assert_eq!(format!("{}", msg), "The operator softplus has no code for the numpy backend")
+	}
+}
+
+#[test]
+fn custom_other_thread(){
+	register();
+	let handle = ::std::thread::spawn(|| meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(softplus(w dot x), 0);
+	end").map(|_| ()).map_err(|msg| format!("{}", msg)));
+	match handle.join().unwrap() {
+		Ok(_) => (),
+		Err(msg) => assert!(false, "Expected the operators to be registered on every thread: {}", msg)
+	}
+}
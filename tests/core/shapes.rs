@@ -0,0 +1,109 @@
+extern crate meta_diff;
+
+fn shape_ok(source: &str, expected: Vec<(usize, &str, &str)>){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	for &(id, rows, cols) in expected.iter() {
+		let node = graph.get_node(id).unwrap();
+		let dims = (format!("{}", node.dims.0), format!("{}", node.dims.1));
+		assert!(dims.0 == rows && dims.1 == cols, "Expected dimensions of node {} to be ({}, {}), was: ({}, {})",
+			id, rows, cols, dims.0, dims.1);
+	}
+}
+
+parametarise_test!(shape_ok,{
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,1) + sum(h[0,2,0,1],0);
+	end",
+	vec![(0, "w_rows", "w_cols"),
+	(4, "x_rows + 1", "x_cols"),
+	(5, "w_rows", "x_cols"),
+	(9, "1", "x_cols"),
+	(14, "2", "1"),
+	(15, "1", "1"),
+	(24, "w_rows", "x_cols"),
+	(36, "w_rows", "x_rows + 1")]
+},{
+	"function [L] = mat(@A,b)
+	h = quad(b, A) + horzcat(A, b)' dot reshape(replicateV(b, 3), 2, rows(b) * 2);
+	L = sum(eye(cols(A)) * mdiag(h) + vdiag(b), 2);
+	end",
+	vec![(2, "b_cols", "b_cols"),
+	(4, "A_cols + b_cols", "A_rows"),
+	(6, "3*b_rows", "b_cols"),
+	(11, "2", "2*b_rows"),
+	(12, "A_cols + b_cols", "2*b_rows"),
+	(15, "A_cols", "A_cols"),
+	(18, "b_cols*b_rows", "b_cols*b_rows"),
+	(20, "A_cols", "1")]
+},{
+	"function [L] = mat(@w,x)
+	L = sum(ones(3,cols(x)) * w - 2 * x, 0);
+	end",
+	vec![(4, "3", "x_cols"),
+	(5, "3", "x_cols"),
+	(8, "x_rows", "x_cols"),
+	(9, "3", "x_cols"),
+	(10, "1", "1")]
+});
+
+fn shape_fail(fail_msg: &str, source: &str){
+	match meta_diff::core::parseMetaFile(source) {
+		Ok(_) => assert!(false, "Fail parsed, but should have failed."),
+		Err(msg) => assert!(format!("{}",msg) == fail_msg, "Parser failed message expected: {}, was: {}", fail_msg, msg)
+	}
+}
+
+parametarise_test!(shape_fail,{
+	"Error at 2:14: ShapeMismatch: node 8 with parents [4, 7] - Can not create an operator Dot with parents of dimensions (2, 3) and (4, 1)",
+	"function [L] = mat(@w,x)
+	L = sum(w + ones(2,3) dot ones(4,1), 0);
+	end"
+},{
+	"Error at 3:10: ShapeMismatch: node 5 with parents [3, 4] - Can not create an operator Add with parents of dimensions (2*x_rows, x_cols) and (3*x_rows, x_cols)",
+	"function [L] = mat(@w,x)
+	h = vertcat(x,x) * w;
+	L = sum(h + vertcat(x,x,x), 0);
+	end"
+},{
+	"Error at 2:10: ShapeMismatch: node 4 with parents [3, 1] - Can not create an operator HorzCat with parents of dimensions (x_rows + 1, x_cols) and (x_rows, x_cols)",
+	"function [L] = mat(@w,x)
+	L = sum(horzcat(vertcat(x,1), x) * w, 0);
+	end"
+},{
+	"Error at 2:9: ShapeMismatch: node 7 with parents [6] - Can not create an operator MatrixInverse of a non square matrix with dimensions (2, w_cols + 2)",
+	"function [L] = mat(@w)
+	L = l2(minv(horzcat(eye(2), w * eye(2))), 0);
+	end"
+},{
+	"Error at 2:10: ShapeMismatch: node 5 with parents [4] - Can not create an operator MatrixDiag of a non square matrix with dimensions (2, 3)",
+	"function [L] = mat(@w)
+	L = sum(mdiag(ones(2,3) * w), 0);
+	end"
+});
+
+#[test]
+fn shape_mismatch_without_source(){
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end").unwrap();
+	let (two, three) = (graph.add_int(2), graph.add_int(3));
+	let a = graph.add_operation(meta_diff::core::OPERATOR_EYE, vec![two]).unwrap();
+	let b = graph.add_operation(meta_diff::core::OPERATOR_EYE, vec![three]).unwrap();
+	let next = graph.nodes.len();
+	match graph.add_operation(meta_diff::core::OPERATOR_DOT, vec![a, b]) {
+		Err(meta_diff::core::GraphError::ShapeMismatch(node, parents, _, location)) => {
+			assert_eq!(node, next);
+			assert_eq!(parents, vec![a, b]);
+			assert!(location.is_none(), "A graph built without a source has no location, but got {:?}", location);
+		},
+		result => assert!(false, "Expected a ShapeMismatch, but got {:?}", result)
+	}
+}
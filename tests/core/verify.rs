@@ -0,0 +1,73 @@
+extern crate meta_diff;
+
+fn verify_ok(source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	match graph.verify() {
+		Ok(_) => (),
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn verify_fail(corrupt: fn(&mut meta_diff::core::ComputeGraph), expected: &str, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	corrupt(&mut graph);
+	match graph.verify() {
+		Ok(_) => assert!(false, "The corrupted graph was verified"),
+		Err(msg) => assert!(format!("{}", msg) == expected, "Expected error: {}, was: {}", expected, msg)
+	}
+}
+
+parametarise_test!(verify_ok,{
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,1) + sum(h[0,2,0,1],0);
+	end"
+},{
+	"function [L] = mat(@w,x)
+	h = w dot x + exp(w) * 2;
+	L = sum(horzcat(h, x) dot ones(cols(x) * 2, 1), 0);
+	end"
+},{
+	"function [L] = mat(@w,@b,x)
+	h = sigm(w dot x + b);
+	L = sum(h * log(h), 0);
+	end"
+});
+
+parametarise_test!(verify_fail,{
+	|graph| {graph.get_mut_node(1).unwrap().children.push(0);},
+	"Verification: node 1 - The children are [2, 9, 0], but should be [2, 9]",
+	"function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end"
+},{
+	|graph| {graph.ordering.swap(0, 2);},
+	"Verification: node 2 - The node is before its ancestor 0 in the ordering",
+	"function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end"
+},{
+	|graph| {graph.get_mut_node(0).unwrap().grad_child = Some(1);},
+	"Verification: node 0 - The gradient node 1 does not list it as a gradient parent",
+	"function [L] = mat(@w,x)
+	L = sum(w dot x, 0);
+	end"
+});
@@ -0,0 +1,32 @@
+extern crate meta_diff;
+extern crate tempdir;
+
+use self::meta_diff::linking::*;
+use self::meta_diff::codegen::RawMatrix;
+
+pub fn dynamic_linking_graph_ok(inputs: Vec<RawMatrix>, expected: Vec<RawMatrix>, source: &str) {
+    let mut graph = meta_diff::core::parseMetaFile(source).unwrap();
+    graph.direct_gradient().unwrap();
+    let mut location = tempdir::TempDir::new("rust_test").unwrap().into_path();
+    let dl_func = dynamical_linking_graph(&graph, &mut location).unwrap();
+
+    let results = dl_func.eval(inputs);
+    assert!(results.len() == expected.len(), "Expected {} results, was {}", expected.len(), results.len());
+    for (res, out) in results.iter().zip(expected.iter()) {
+        assert!(res.0 == out.0 && res.1 == out.1,
+            format!("Incorrect dimensions, expected ({}, {}), was ({}, {})", out.0, out.1, res.0, res.1));
+        for (r, o) in res.2.iter().zip(out.2.iter()) {
+            assert!((r - o).abs() < 1e-12, format!("Incorrect result, expected {:?}, was {:?}", out.2, res.2));
+        }
+    }
+}
+
+parametarise_test!(dynamic_linking_graph_ok,{
+    vec![(2, 3, vec![0.1, -0.2, 0.3, 0.4, -0.5, 0.6]), (2, 1, vec![1.0, 2.0]), (2, 1, vec![0.5, -0.5])],
+    vec![(1, 1, vec![2.901246234850785]),
+    (2, 3, vec![0.3793723330256684, 1.1186026425530906, 0.7587446660513368, 2.237205285106181, 0.3793723330256684, 1.1186026425530906])],
+    "function [L] = mat(@w,x,y)
+    h = tanh(w dot vertcat(x,1));
+    L = l2(h-y,0) + sum(h[0,2,0,1],0);
+    end"
+});
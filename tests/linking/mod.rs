@@ -1 +1,2 @@
 mod linking_rust;
+mod linking_graph;
@@ -2,6 +2,7 @@
 mod macros;
 mod core;
 mod optimization;
+mod codegen;
 mod linking;
 // use tempdir::*;
 // use std::process::Command;
@@ -0,0 +1,81 @@
+extern crate meta_diff;
+
+fn cse_some(nodes_before: usize, nodes_after: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	match meta_diff::optimization::cse(&mut graph) {
+		Ok(b) => {
+			assert!(b, "Did not return true for eliminating");
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			let nodes = graph.nodes.iter().filter_map(|x| x.as_ref()).collect::<Vec<_>>();
+			for node in nodes.iter() {
+				for i in node.children.iter().chain(node.op.get_ancestors()) {
+					assert!(graph.get_node(*i).is_ok(), "Node {} is linked to the removed node {}", node.id, i);
+				}
+				for other in nodes.iter().filter(|x| x.id != node.id && x.op.op_type != meta_diff::core::OPERATOR_NONE) {
+					assert!(node.op != other.op, "Nodes {} and {} were not merged", node.id, other.id);
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn cse_none(nodes: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	assert!(graph.len() == nodes, "Number of the initial graph nodes expected: {}, was: {}", nodes, graph.len());
+	match meta_diff::optimization::cse(&mut graph) {
+		Ok(b) => {
+			assert!(!b, "Returned true for eliminating");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(cse_some,{
+	35, 30,
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0) + sum(h[0,2,0,1],0);
+	end"
+},{
+	30, 21,
+	"function [L] = mat(@w,x)
+	h = cos(w) * x + x * cos(w);
+	L = sum(square(h) + square(h), 0);
+	end"
+},{
+	32, 27,
+	"function [L] = mat(@w,@v,x)
+	h = sigm(w dot x) + sigm(v dot x);
+	L = l2(h, 0) + l2(h, 0) * 0.5;
+	end"
+});
+
+parametarise_test!(cse_none,{
+	6,
+	"function [L] = mat(@w,@v)
+	L = sum(w * v + v dot w, 0);
+	end"
+},{
+	8,
+	"function [L] = mat(@w,x)
+	L = sum(sin(w) + cos(w) + 1.5 + 1, 0);
+	end"
+},{
+	7,
+	"function [L] = mat(@w,x)
+	L = sum(w dot x - x dot w, 2);
+	end"
+});
@@ -0,0 +1,99 @@
+extern crate meta_diff;
+
+fn inverse_some(nodes_before: usize, nodes_after: usize, gradient: bool, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	if gradient {
+		match graph.direct_gradient() {
+			Ok(_) => (),
+			Err(msg) => {return assert!(false, "{}", msg);}
+		}
+	}
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	let params = graph.get_params().1;
+	match meta_diff::optimization::inverse_cancellation(&mut graph) {
+		Ok(b) => {
+			assert!(b, "Did not return true for cancellation");
+			match meta_diff::optimization::prune(&mut graph) {
+				Ok(_) => (),
+				Err(msg) => {return assert!(false, "{}", msg);}
+			}
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			assert!(graph.get_params().1 == params, "The gradients of the parameters were lost");
+			for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+				let mut linked = node.children.clone();
+				linked.extend(node.grad_parents.iter().cloned());
+				linked.extend(node.grad_child.iter().cloned());
+				linked.extend(node.op.get_ancestors().cloned());
+				for i in linked {
+					assert!(graph.get_node(i).is_ok(), "Node {} is linked to the removed node {}", node.id, i);
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn inverse_none(nodes: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match meta_diff::optimization::inverse_cancellation(&mut graph) {
+		Ok(b) => {
+			assert!(!b, "Returned true for cancellation");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(inverse_some,{
+	16, 6, false,
+	"function [L] = mat(@w,x)
+	h = log(exp(w)) - -x;
+	L = sum(minv(minv((h')')) + mdiag(vdiag(sum(x,2))), 0);
+	end"
+},{
+	6, 5, false,
+	"function [L] = mat(@w,x)
+	L = sum(sqrt(square(w dot x)), 0);
+	end"
+},{
+	31, 25, true,
+	"function [L] = mat(@w,x)
+	h = tanh(log(exp(w dot x)));
+	L = l2((sqrt(square(h))')', 0);
+	end"
+});
+
+parametarise_test!(inverse_none,{
+	8,
+	"function [L] = mat(@w,x)
+	L = sum(exp(log(w)) + square(sqrt(x)), 0);
+	end"
+},{
+	10,
+	"function [L] = mat(@w,x)
+	L = sum(mdiag(vdiag(x')) + -(w + -x), 0);
+	end"
+},{
+	11,
+	"function [L] = mat(@w,x)
+	L = sum(minv(-minv(w)) + log(2 * exp(x)), 0);
+	end"
+});
+
+#[test]
+fn inverse_reciprocal(){
+	let mut graph = meta_diff::core::ComputeGraph::new();
+	let w = graph.add_parameter("w".to_string());
+	let div = graph.add_operation(meta_diff::core::OPERATOR_DIV, vec![w]).unwrap();
+	let div = graph.add_operation(meta_diff::core::OPERATOR_DIV, vec![div]).unwrap();
+	let sum = graph.add_operation(meta_diff::core::OPERATOR_SUM_ALL, vec![div]).unwrap();
+	graph.outputs.push(sum);
+	assert!(meta_diff::optimization::inverse_cancellation(&mut graph).unwrap(), "Did not return true for cancellation");
+	assert!(graph.get_node(sum).unwrap().op.parents == vec![w], "The reciprocal was not cancelled");
+}
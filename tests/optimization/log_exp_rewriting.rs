@@ -0,0 +1,84 @@
+extern crate meta_diff;
+use self::meta_diff::core::*;
+use self::meta_diff::optimization::LogExpRewrites;
+
+fn log_exp_some(nodes_before: usize, nodes_after: usize, rewrites: LogExpRewrites, source: &str){
+	let mut graph = match parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	match meta_diff::optimization::log_exp_rewriting(&mut graph, &rewrites) {
+		Ok(b) => {
+			assert!(b, "Did not return true for rewriting");
+			match meta_diff::optimization::prune(&mut graph) {
+				Ok(_) => (),
+				Err(msg) => {return assert!(false, "{}", msg);}
+			}
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+				let parents = node.op.parents.iter().map(|x| graph.get_node(*x).unwrap().op.op_type).collect::<Vec<_>>();
+				match node.op.op_type {
+					OPERATOR_LOG => assert!(!rewrites.log_of_product || parents[0] != OPERATOR_MUL,
+						"Node {} is a logarithm of a product", node.id),
+					OPERATOR_MUL => assert!(!rewrites.product_of_exp || parents.iter().filter(|x| **x == OPERATOR_EXP).count() < 2,
+						"Node {} is a product of exponentials", node.id),
+					OPERATOR_POW => assert!(!rewrites.power_of_exp || parents[0] != OPERATOR_EXP,
+						"Node {} is a power of an exponential", node.id),
+					_ => ()
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn log_exp_none(nodes: usize, rewrites: LogExpRewrites, source: &str){
+	let mut graph = match parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match meta_diff::optimization::log_exp_rewriting(&mut graph, &rewrites) {
+		Ok(b) => {
+			assert!(!b, "Returned true for rewriting");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(log_exp_some,{
+	18, 20, meta_diff::optimization::LogExpRewrites::all(),
+	"function [L] = mat(@w,x)
+	h = log(w * x * 3) + exp(w) * exp(x) * x + exp(w) * 2 * exp(w dot x);
+	L = sum(h + pow(exp(w), x), 0);
+	end"
+},{
+	11, 12, meta_diff::optimization::LogExpRewrites{log_of_product: true, product_of_exp: false, power_of_exp: false},
+	"function [L] = mat(@w,x)
+	L = sum(log(w * x) + exp(w) * exp(x) + pow(exp(w), x), 0);
+	end"
+},{
+	11, 10, meta_diff::optimization::LogExpRewrites{log_of_product: false, product_of_exp: true, power_of_exp: true},
+	"function [L] = mat(@w,x)
+	L = sum(log(w * x) + exp(w) * exp(x) + pow(exp(w), x), 0);
+	end"
+});
+
+parametarise_test!(log_exp_none,{
+	11, meta_diff::optimization::LogExpRewrites::all(),
+	"function [L] = mat(@w,x)
+	L = sum(log(w + x) + exp(w) + exp(x) * x + pow(w, exp(x)), 0);
+	end"
+},{
+	11, meta_diff::optimization::LogExpRewrites{log_of_product: false, product_of_exp: false, power_of_exp: false},
+	"function [L] = mat(@w,x)
+	L = sum(log(w * x) + exp(w) * exp(x) + pow(exp(w), x), 0);
+	end"
+},{
+	15, meta_diff::optimization::LogExpRewrites{log_of_product: true, product_of_exp: false, power_of_exp: false},
+	"function [L] = mat(@w,x)
+	h = w * x;
+	L = sum(log(exp(w) + x) + log(h) * h + exp(w) * exp(x) + pow(exp(w), x), 0);
+	end"
+});
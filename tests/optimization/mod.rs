@@ -1 +1,9 @@
 mod constant_folding;
+mod prune;
+mod cse;
+mod inverse_cancellation;
+mod nary_flattening;
+mod neg_div_reordering;
+mod log_exp_rewriting;
+mod sub_indexing;
+mod pipeline;
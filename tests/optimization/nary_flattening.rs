@@ -0,0 +1,81 @@
+extern crate meta_diff;
+
+fn flatten_some(nodes_before: usize, nodes_after: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	match meta_diff::optimization::nary_flattening(&mut graph) {
+		Ok(b) => {
+			assert!(b, "Did not return true for flattening");
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+				for i in node.children.iter().chain(node.op.get_ancestors()) {
+					assert!(graph.get_node(*i).is_ok(), "Node {} is linked to the removed node {}", node.id, i);
+				}
+				for i in node.children.iter() {
+					assert!(graph.get_node(*i).unwrap().op.get_ancestors().any(|x| *x == node.id),
+						"Node {} is not a parent of its child {}", node.id, i);
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn flatten_none(nodes: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match meta_diff::optimization::nary_flattening(&mut graph) {
+		Ok(b) => {
+			assert!(!b, "Returned true for flattening");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(flatten_some,{
+	86, 84,
+	"function [L] = mat(@w,x)
+	h = (w + x) + (x + w dot x dot w) - (x * w * x);
+	g = horzcat(horzcat(w, x), h dot w);
+	L = sum(vertcat(g, vertcat(g, g * 2)) dot ones(6,1), 0) + sum(h,0) * 2;
+	end"
+},{
+	25, 23,
+	"function [L] = mat(@w,x)
+	h = (w dot x + x) + (w + 1);
+	L = sum((h * x) * (h * w), 0);
+	end"
+},{
+	23, 21,
+	"function [L] = mat(@w,x)
+	L = sum((w dot x) dot (x dot (w dot x)), 0);
+	end"
+});
+
+parametarise_test!(flatten_none,{
+	7,
+	"function [L] = mat(@w,x)
+	h = w + x;
+	L = sum(h + h * (h + x), 0);
+	end"
+},{
+	9,
+	"function [L] = mat(@w,x)
+	L = sum((w + x) * (w * x) dot (w - x), 0);
+	end"
+},{
+	6,
+	"function [L] = mat(@w,x)
+	L = sum(horzcat(vertcat(w, x), vertcat(x, w)), 0);
+	end"
+});
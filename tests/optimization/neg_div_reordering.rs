@@ -0,0 +1,82 @@
+extern crate meta_diff;
+use self::meta_diff::core::*;
+
+fn reorder_some(nodes_before: usize, nodes_after: usize, source: &str){
+	let mut graph = match parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	match meta_diff::optimization::neg_div_reordering(&mut graph) {
+		Ok(b) => {
+			assert!(b, "Did not return true for reordering");
+			match meta_diff::optimization::prune(&mut graph) {
+				Ok(_) => (),
+				Err(msg) => {return assert!(false, "{}", msg);}
+			}
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+				let parents = node.op.parents.iter().map(|x| graph.get_node(*x).unwrap().op.op_type).collect::<Vec<_>>();
+				let count = |op_type: OperatorType| parents.iter().filter(|x| **x == op_type).count();
+				match node.op.op_type {
+					OPERATOR_NEG => assert!(parents[0] != OPERATOR_NEG, "Node {} is a negation of a negation", node.id),
+					OPERATOR_MUL => assert!(count(OPERATOR_NEG) < 2 && count(OPERATOR_DIV) < 2,
+						"Node {} has more than one negated or divided parent", node.id),
+					OPERATOR_DOT => assert!(count(OPERATOR_NEG) < 2, "Node {} has more than one negated parent", node.id),
+					_ => ()
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn reorder_none(nodes: usize, source: &str){
+	let mut graph = match parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match meta_diff::optimization::neg_div_reordering(&mut graph) {
+		Ok(b) => {
+			assert!(!b, "Returned true for reordering");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(reorder_some,{
+	7, 7,
+	"function [L] = mat(@w,x)
+	L = sum(-(w + -x + 2), 0);
+	end"
+},{
+	18, 13,
+	"function [L] = mat(@w,x)
+	h = -(w + -x) * -(x dot w) * -w / x / (w + 3);
+	L = sum((-w) dot (-x) dot (-h), 0);
+	end"
+},{
+	13, 11,
+	"function [L] = mat(@w,x)
+	L = sum(2 / w / x, 0) - sum(-w * -x, 0);
+	end"
+});
+
+parametarise_test!(reorder_none,{
+	6,
+	"function [L] = mat(@w,x)
+	h = w + x;
+	L = sum(-h + h, 0);
+	end"
+},{
+	8,
+	"function [L] = mat(@w,x)
+	L = sum(w / x - w * x, 0);
+	end"
+},{
+	8,
+	"function [L] = mat(@w,x)
+	L = sum(-w dot x + x dot -w, 0);
+	end"
+});
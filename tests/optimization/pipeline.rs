@@ -0,0 +1,77 @@
+extern crate meta_diff;
+
+fn pipeline_level(level: usize, nodes_after: usize, iterations: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let nodes_before = graph.len();
+	let mut pipeline = meta_diff::optimization::Pipeline::with_level(level);
+	match pipeline.run(&mut graph) {
+		Ok(_) => {
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			assert!(pipeline.iterations == iterations, "Number of iterations expected: {}, was: {}", iterations, pipeline.iterations);
+			let removed = pipeline.stats.iter().fold(0, |acc, x| acc + x.nodes_removed);
+			assert!(removed == nodes_before as isize - nodes_after as isize,
+				"Number of removed nodes expected: {}, was: {}", nodes_before - nodes_after, removed);
+			for stats in pipeline.stats.iter() {
+				assert!(stats.runs == iterations, "Pass {} was run {} times instead of {}", stats.name, stats.runs, iterations);
+			}
+		},
+		Err(msg) => return assert!(false, "{}", msg)
+	}
+	// Level 2 reaches a fixed point, thus running it again should not change the graph
+	if level > 1 {
+		match pipeline.run(&mut graph) {
+			Ok(b) => assert!(!b, "The graph was modified after reaching a fixed point"),
+			Err(msg) => assert!(false, "{}", msg)
+		}
+	}
+}
+
+parametarise_test!(pipeline_level,{
+	0, 23, 0,
+	"function [L] = mat(@w,x)
+	L = sum((w dot x) dot (x dot (w dot x)), 0);
+	end"
+},{
+	1, 70, 1,
+	"function [L] = mat(@w,x)
+	h = -(w + -x) * -(x dot w) * -w / x / (w + 3);
+	g = (-w) dot (-x) dot (-h) + -(-h - x);
+	L = sum(g / w / x * 2, 0) - sum(-(h + g), 0);
+	end"
+},{
+	2, 68, 3,
+	"function [L] = mat(@w,x)
+	h = -(w + -x) * -(x dot w) * -w / x / (w + 3);
+	g = (-w) dot (-x) dot (-h) + -(-h - x);
+	L = sum(g / w / x * 2, 0) - sum(-(h + g), 0);
+	end"
+});
+
+#[test]
+fn pipeline_log_exp(){
+	let names = |pipeline: &meta_diff::optimization::Pipeline| pipeline.stats.iter()
+		.map(|stats| stats.name.clone()).collect::<Vec<String>>();
+	for level in 0..3 {
+		let pipeline = meta_diff::optimization::Pipeline::with_level(level);
+		assert!(!names(&pipeline).contains(&"log_exp_rewriting".to_string()),
+			"Level {} should not run log_exp_rewriting", level);
+	}
+	let mut graph = meta_diff::core::parseMetaFile("function [L] = mat(@w,x)
+	L = sum(log(exp(w) * exp(x)), 0);
+	end").unwrap();
+	graph.direct_gradient().unwrap();
+	let mut pipeline = meta_diff::optimization::Pipeline::with_log_exp(1, meta_diff::optimization::LogExpRewrites::all());
+	pipeline.run(&mut graph).unwrap();
+	let log_exp = pipeline.stats.iter().find(|stats| stats.name == "log_exp_rewriting");
+	match log_exp {
+		Some(stats) => assert!(stats.modifications == 1, "Expected log_exp_rewriting to modify the graph"),
+		None => assert!(false, "The pipeline did not run log_exp_rewriting: {:?}", names(&pipeline))
+	}
+}
@@ -0,0 +1,108 @@
+extern crate meta_diff;
+
+fn check_links(graph: &meta_diff::core::ComputeGraph){
+	for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+		let mut linked = node.children.clone();
+		linked.extend(node.grad_parents.iter().cloned());
+		linked.extend(node.grad_child.iter().cloned());
+		linked.extend(node.op.get_ancestors().cloned());
+		for i in linked {
+			assert!(graph.get_node(i).is_ok(), "Node {} is linked to the removed node {}", node.id, i);
+		}
+	}
+	for i in graph.ordering.iter().chain(graph.outputs.iter()) {
+		assert!(graph.get_node(*i).is_ok(), "The removed node {} is still in the ordering or the outputs", i);
+	}
+}
+
+fn prune_ok(nodes_before: usize, nodes_after: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	let params = graph.get_params();
+	match meta_diff::optimization::prune(&mut graph) {
+		Ok(b) => {
+			assert!(b == (nodes_before != nodes_after), "Returned {} for pruning", b);
+			assert!(graph.len() == nodes_after, "Number of the pruned graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			assert!(graph.get_params() == params, "The gradients of the parameters were changed");
+			check_links(&graph);
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn prune_outputs(nodes_after: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	let outputs = graph.outputs.clone();
+	match meta_diff::optimization::prune_targets(&mut graph, outputs.clone()) {
+		Ok(b) => {
+			assert!(b, "Did not return true for pruning");
+			assert!(graph.len() == nodes_after, "Number of the pruned graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			assert!(graph.outputs == outputs, "The outputs were changed");
+			assert!(graph.get_params().0.len() == 0, "The gradients of the parameters were not removed");
+			check_links(&graph);
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(prune_ok,{
+	35, 35,
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0) + sum(h[0,2,0,1],0);
+	end"
+},{
+	23, 20,
+	"function [L] = mat(@w,x,y)
+	z = sin(w) + cos(x);
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end"
+},{
+	31, 29,
+	"function [L,M] = mat(@w,@v,x)
+	h = tanh(w dot x);
+	h = sigm(v dot h);
+	s = sinh(v) dot h;
+	L = sum(h,0);
+	M = l1(v,0);
+	end"
+});
+
+parametarise_test!(prune_outputs,{
+	17,
+	"function [L] = mat(@w,x,y)
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0) + sum(h[0,2,0,1],0);
+	end"
+},{
+	10,
+	"function [L] = mat(@w,x,y)
+	z = sin(w) + cos(x);
+	h = tanh(w dot vertcat(x,1));
+	L = l2(h-y,0);
+	end"
+},{
+	9,
+	"function [L,M] = mat(@w,@v,x)
+	h = tanh(w dot x);
+	h = sigm(v dot h);
+	s = sinh(v) dot h;
+	L = sum(h,0);
+	M = l1(v,0);
+	end"
+});
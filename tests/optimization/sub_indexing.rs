@@ -0,0 +1,87 @@
+extern crate meta_diff;
+
+fn sub_indexing_some(nodes_before: usize, nodes_after: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match graph.direct_gradient() {
+		Ok(_) => (),
+		Err(msg) => {return assert!(false, "{}", msg);}
+	}
+	assert!(graph.len() == nodes_before, "Number of the initial graph nodes expected: {}, was: {}", nodes_before, graph.len());
+	match meta_diff::optimization::sub_indexing(&mut graph).and_then(|b| meta_diff::optimization::prune(&mut graph).map(|_| b)) {
+		Ok(b) => {
+			assert!(b, "Did not return true for sub indexing");
+			assert!(graph.len() == nodes_after, "Number of the optimized graph nodes expected: {}, was: {}", nodes_after, graph.len());
+			for node in graph.nodes.iter().filter_map(|x| x.as_ref()) {
+				if node.op.op_type == meta_diff::core::OPERATOR_SUBINDEX {
+					let parent = graph.get_node(node.op.parents[0]).unwrap();
+					assert!(parent.op.op_type != meta_diff::core::OPERATOR_SUBINDEX,
+						"Node {} is a sub index of the sub index {}", node.id, parent.id);
+				}
+				for i in node.children.iter().chain(node.op.get_ancestors()) {
+					assert!(graph.get_node(*i).is_ok(), "Node {} is linked to the removed node {}", node.id, i);
+				}
+			}
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+fn sub_indexing_none(nodes: usize, source: &str){
+	let mut graph = match meta_diff::core::parseMetaFile(source) {
+		Ok(g) => g,
+		Err(msg) => {return assert!(false, "{}", msg);}
+	};
+	match meta_diff::optimization::sub_indexing(&mut graph) {
+		Ok(b) => {
+			assert!(!b, "Returned true for sub indexing");
+			assert!(graph.len() == nodes, "Number of the optimized graph nodes expected: {}, was: {}", nodes, graph.len());
+		},
+		Err(msg) => assert!(false, "{}", msg)
+	}
+}
+
+parametarise_test!(sub_indexing_some,{
+	96, 92,
+	"function [L] = mat(@w,x)
+	h = vertcat(w, x dot w, x * w);
+	k = h[1,4,0,2];
+	m = h[0,rows(w),0,cols(w)];
+	c = horzcat(w, x);
+	g = k[1,2,0,2] + h[rows(w),rows(x),0,cols(w)] + m[1,1,0,2] + c[0,rows(x),cols(w),cols(x)];
+	L = sum(g * g, 0) + sum(m, 0);
+	end"
+},{
+	35, 32,
+	"function [L] = mat(@w,x)
+	h = vertcat(w, x);
+	L = sum(h[0,rows(w),0,cols(w)] * h[rows(w),rows(x),0,cols(x)], 0);
+	end"
+},{
+	27, 28,
+	"function [L] = mat(@w,x)
+	h = w[1,4,0,2];
+	L = sum(h[1,2,0,1] * x, 0);
+	end"
+});
+
+parametarise_test!(sub_indexing_none,{
+	9,
+	"function [L] = mat(@w,x)
+	L = sum(w[0,2,0,2] * x, 0);
+	end"
+},{
+	9,
+	"function [L] = mat(@w,x)
+	h = vertcat(w, x);
+	L = sum(h[1,2,0,1], 0);
+	end"
+},{
+	9,
+	"function [L] = mat(@w,x)
+	h = horzcat(w, x);
+	L = sum(h[0,rows(w),1,cols(w)], 0);
+	end"
+});